@@ -0,0 +1,12 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // The messages of `--format protobuf`, compiled with a copy of `protoc`
+    // so that it does not have to be installed.
+    #[cfg(feature = "protobuf")]
+    prost_build::Config::new()
+        .protoc_executable(
+            protoc_bin_vendored::protoc_bin_path().expect("no protoc for this platform"),
+        )
+        .compile_protos(&["proto/dump.proto"], &["proto"])
+        .expect("failed to compile proto/dump.proto");
+}
@@ -0,0 +1,53 @@
+use std::future::Future;
+
+use tokio::io::AsyncBufRead;
+
+use crate::{split::Chunk, Error, Page, ParseOptions};
+
+/// Like [`parse`](crate::parse), but reads from an async reader and awaits the future
+/// returned by `page_processor` for each page.
+///
+/// The XML of each page is read asynchronously by searching for `<page>` and `</page>`,
+/// and then parsed without blocking on I/O, so the header, if any, is skipped.
+pub async fn parse_async<R, F, Fut, E>(mut reader: R, mut page_processor: F) -> Result<(), Error<E>>
+where
+    R: AsyncBufRead + Unpin,
+    F: FnMut(Page) -> Fut,
+    Fut: Future<Output = Result<(), Error<E>>>,
+    E: std::error::Error + 'static,
+{
+    let options = ParseOptions::default();
+    let mut position = 0;
+    loop {
+        let mut chunk = Chunk::new();
+        if !chunk
+            .read_page_async(&mut reader, &mut position)
+            .await
+            .map_err(Error::from_infallible)?
+        {
+            return Ok(());
+        }
+        for page in chunk.parse(&options).map_err(Error::from_infallible)? {
+            match page_processor(page).await {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_parse_async() {
+    use std::convert::Infallible;
+
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let mut pages = Vec::new();
+    parse_async::<_, _, _, Infallible>(tokio::io::BufReader::new(&xml[..]), |page| {
+        pages.push(page);
+        async { Ok(()) }
+    })
+    .await
+    .unwrap();
+    assert_eq!(pages, crate::parse_to_vec(xml));
+}
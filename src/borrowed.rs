@@ -0,0 +1,388 @@
+use std::{borrow::Cow, convert::TryFrom, io::BufRead};
+
+use chrono::{DateTime, Utc};
+use quick_xml::{events::Event, name::QName, Reader};
+
+use crate::{
+    parse_text, parse_timestamp, read_contributor_contents,
+    split::{read_page_xml, with_offset},
+    tag::Tag,
+    threads, upload, Comment, Contributor, Error, Interned, Page, ParseOptions, Revision,
+    ThreadingInfo, Upload,
+};
+
+/// A [`Page`] whose strings borrow from the buffer holding the XML of the page
+/// where possible, created by [`parse_borrowed`].
+/// A string is only allocated if it contains character or entity references.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRef<'a> {
+    pub title: Cow<'a, str>,
+    pub namespace: i32,
+    pub id: u32,
+    pub redirect_target: Option<Cow<'a, str>>,
+    pub restrictions: Option<Cow<'a, str>>,
+    pub revisions: Vec<RevisionRef<'a>>,
+    pub uploads: Vec<Upload>,
+    pub sha1: Option<Cow<'a, str>>,
+}
+
+/// A [`Revision`] whose text and SHA-1 borrow from the buffer holding the XML of the page.
+/// The contributor and comment are short, so they are owned as in [`Revision`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevisionRef<'a> {
+    pub id: u32,
+    pub parent_id: Option<u32>,
+    pub timestamp: DateTime<Utc>,
+    pub contributor: Contributor,
+    pub origin: u32,
+    pub minor: bool,
+    pub comment: Comment,
+    pub model: Interned,
+    pub format: Interned,
+    pub text: Cow<'a, str>,
+    pub text_bytes: Option<u64>,
+    pub text_deleted: bool,
+    pub threading_info: Option<ThreadingInfo>,
+    pub sha1: Option<Cow<'a, str>>,
+}
+
+impl PageRef<'_> {
+    /// Copies the borrowed strings to make a [`Page`] that can outlive the page processor.
+    pub fn into_owned(self) -> Page {
+        Page {
+            title: self.title.into_owned(),
+            raw_title: None,
+            namespace: self.namespace,
+            id: self.id,
+            redirect_target: self.redirect_target.map(Cow::into_owned),
+            restrictions: self.restrictions.map(Cow::into_owned),
+            revisions: self
+                .revisions
+                .into_iter()
+                .map(RevisionRef::into_owned)
+                .collect(),
+            uploads: self.uploads,
+            extra: Default::default(),
+            sha1: self.sha1.map(Cow::into_owned),
+        }
+    }
+}
+
+impl RevisionRef<'_> {
+    pub fn into_owned(self) -> Revision {
+        Revision {
+            id: self.id,
+            parent_id: self.parent_id,
+            timestamp: self.timestamp,
+            contributor: self.contributor,
+            origin: self.origin,
+            minor: self.minor,
+            comment: self.comment,
+            model: self.model,
+            format: self.format,
+            text: self.text.into(),
+            text_bytes: self.text_bytes,
+            text_deleted: self.text_deleted,
+            text_truncated: false,
+            size_delta: None,
+            entity: None,
+            threading_info: self.threading_info,
+            sha1: self.sha1.map(Cow::into_owned),
+            extra: Default::default(),
+        }
+    }
+}
+
+impl From<PageRef<'_>> for Page {
+    fn from(page: PageRef<'_>) -> Self {
+        page.into_owned()
+    }
+}
+
+/// Like [`parse`](crate::parse), but passes borrowed pages to `page_processor`.
+/// The XML of each page is copied into a buffer that is reused for the next page,
+/// so the strings in a [`PageRef`] are only valid during the call to `page_processor`;
+/// use [`PageRef::into_owned`] to keep a page.
+/// Pages are found by searching for the `<page>` start tag, so the header, if any, is skipped.
+pub fn parse_borrowed<
+    R: BufRead,
+    F: FnMut(PageRef<'_>) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: R,
+    mut page_processor: F,
+) -> Result<(), Error<E>> {
+    let mut page_xml = Vec::with_capacity(ParseOptions::DEFAULT_BUFFER_CAPACITY);
+    let mut buf = Vec::new();
+    let mut position = 0;
+    loop {
+        page_xml.clear();
+        let page_start = match read_page_xml(&mut reader, &mut page_xml, &mut position)? {
+            Some(page_start) => page_start,
+            None => return Ok(()),
+        };
+        page_xml.drain(..page_xml.len() - (position - page_start));
+
+        let mut page_reader = Reader::from_reader(page_xml.as_slice());
+        let page =
+            read_page_ref(&mut page_reader, &mut buf).map_err(|e| with_offset(e, page_start))?;
+        match page_processor(page) {
+            Err(Error::ShortCircuit) => return Ok(()),
+            Err(e) => return Err(e),
+            _ => {}
+        }
+    }
+}
+
+fn next_event<'a, E: std::error::Error>(
+    reader: &mut Reader<&'a [u8]>,
+) -> Result<Event<'a>, Error<E>> {
+    loop {
+        match reader.read_event() {
+            Ok(Event::Comment(_) | Event::PI(_)) => {}
+            Ok(Event::Eof) | Err(quick_xml::Error::UnexpectedEof(_)) => {
+                return Err(Error::UnexpectedEof {
+                    position: reader.buffer_position(),
+                })
+            }
+            Ok(event) => return Ok(event),
+            Err(_) => return Err(Error::format(reader, "invalid XML syntax")),
+        }
+    }
+}
+
+/// Reads the text of an element whose start tag has been read, up to and including the end tag,
+/// borrowing it from the input unless it has to be unescaped
+/// or is made of more than one piece of character data or CDATA.
+fn read_borrowed_text<'a, E: std::error::Error>(
+    reader: &mut Reader<&'a [u8]>,
+    tag: Tag,
+) -> Result<Cow<'a, str>, Error<E>> {
+    let mut text: Cow<'a, str> = Cow::Borrowed("");
+    loop {
+        let more = match next_event(reader)? {
+            Event::Text(more) => more.unescape().map_err(|_| Error::FailedToDecode {
+                position: reader.buffer_position(),
+            })?,
+            Event::CData(cdata) => match cdata.into_inner() {
+                Cow::Borrowed(bytes) => {
+                    Cow::Borrowed(std::str::from_utf8(bytes).map_err(|_| {
+                        Error::FailedToDecode {
+                            position: reader.buffer_position(),
+                        }
+                    })?)
+                }
+                Cow::Owned(_) => {
+                    return Err(Error::format(reader, "expected CDATA that can be borrowed"))
+                }
+            },
+            Event::End(end) if end.name() == tag.as_q_name() => return Ok(text),
+            _ => return Err(Error::format(reader, "expected text or an end tag")),
+        };
+        if text.is_empty() {
+            text = more;
+        } else {
+            text.to_mut().push_str(&more);
+        }
+    }
+}
+
+fn read_page_ref<'a, E: std::error::Error>(
+    reader: &mut Reader<&'a [u8]>,
+    buf: &mut Vec<u8>,
+) -> Result<PageRef<'a>, Error<E>> {
+    match next_event(reader)? {
+        Event::Start(start) if start.name() == Tag::Page.as_q_name() => {}
+        _ => return Err(Error::format(reader, "expected <page>")),
+    }
+
+    let mut title = None;
+    let mut namespace = None;
+    let mut id = None;
+    let mut redirect_target = None;
+    let mut restrictions = None;
+    let mut revisions = Vec::new();
+    let mut uploads = Vec::new();
+    let mut sha1 = None;
+    loop {
+        buf.clear();
+        let after_revisions = !revisions.is_empty() || !uploads.is_empty();
+        let (tag, is_empty) = match next_event(reader)? {
+            Event::Text(_) => continue,
+            Event::End(end) if end.name() == Tag::Page.as_q_name() => break,
+            // Before version 0.6 of the export schema, `<redirect />` had no target,
+            // and some malformed dumps have `<redirect></redirect>`,
+            // whose end tag and any text in it are skipped like the whitespace between elements.
+            Event::End(end) if end.name() == Tag::Redirect.as_q_name() => continue,
+            Event::Start(start) | Event::Empty(start)
+                if start.name() == Tag::Redirect.as_q_name() =>
+            {
+                let mut target = String::new();
+                for attr in start.attributes() {
+                    let attr =
+                        attr.map_err(|_| Error::format(reader, "invalid attribute of <redirect>"))?;
+                    if attr.key.as_ref() != b"title" {
+                        return Err(Error::format(
+                            reader,
+                            "expected no attribute but title on <redirect>",
+                        ));
+                    }
+                    target = attr
+                        .unescape_value()
+                        .map_err(|_| Error::FailedToDecode {
+                            position: reader.buffer_position(),
+                        })?
+                        .into_owned();
+                }
+                redirect_target = Some(Cow::Owned(target));
+                continue;
+            }
+            // Elements that are not part of the export schema after the revisions are skipped,
+            // as in `parse`.
+            Event::Start(start)
+                if after_revisions && Tag::from_bytes(start.name().as_ref()).is_err() =>
+            {
+                let name = start.name().as_ref().to_vec();
+                reader
+                    .read_to_end(QName(&name))
+                    .map_err(|_| Error::format(reader, "invalid XML while skipping an element"))?;
+                continue;
+            }
+            Event::Empty(start)
+                if after_revisions && Tag::from_bytes(start.name().as_ref()).is_err() =>
+            {
+                continue
+            }
+            Event::Start(start) => (Tag::try_from(start.name()), false),
+            Event::Empty(empty) => (Tag::try_from(empty.name()), true),
+            _ => return Err(Error::format(reader, "expected an element in <page>")),
+        };
+        let tag = tag.map_err(Error::from_infallible)?;
+        match (tag, is_empty) {
+            (Tag::Title, false) => title = Some(read_borrowed_text(reader, tag)?),
+            (Tag::Ns, false) => namespace = Some(parse_text(reader, buf, tag)?),
+            (Tag::Id, false) => id = Some(parse_text(reader, buf, tag)?),
+            (Tag::Restrictions, false) => restrictions = Some(read_borrowed_text(reader, tag)?),
+            (Tag::Revision, false) => revisions.push(read_revision_ref(reader, buf)?),
+            (Tag::Upload, false) => uploads.push(upload::parse_upload(reader, buf)?),
+            (Tag::Sha1, true) => sha1 = Some(Cow::Borrowed("")),
+            (Tag::Sha1, false) => sha1 = Some(read_borrowed_text(reader, tag)?),
+            _ => return Err(Error::format(reader, "unexpected element in <page>")),
+        }
+    }
+
+    let missing = |context| Error::format(reader, context);
+    Ok(PageRef {
+        title: title.ok_or_else(|| missing("missing <title> in <page>"))?,
+        namespace: namespace.ok_or_else(|| missing("missing <ns> in <page>"))?,
+        id: id.ok_or_else(|| missing("missing <id> in <page>"))?,
+        redirect_target,
+        restrictions,
+        revisions,
+        uploads,
+        sha1,
+    })
+}
+
+/// Reads the elements of `<revision>` up to and including the end tag.
+fn read_revision_ref<'a, E: std::error::Error>(
+    reader: &mut Reader<&'a [u8]>,
+    buf: &mut Vec<u8>,
+) -> Result<RevisionRef<'a>, Error<E>> {
+    let mut id = None;
+    let mut parent_id = None;
+    let mut timestamp = None;
+    let mut contributor = None;
+    let mut origin = None;
+    let mut minor = false;
+    let mut comment = Comment::Absent;
+    let mut model = None;
+    let mut format = None;
+    let mut text = None;
+    let mut text_bytes = None;
+    let mut text_deleted = false;
+    let mut threading_info = None;
+    let mut sha1 = None;
+    loop {
+        buf.clear();
+        let (start, is_empty) = match next_event(reader)? {
+            Event::Text(_) => continue,
+            Event::End(end) if end.name() == Tag::Revision.as_q_name() => break,
+            Event::Start(start) => (start, false),
+            Event::Empty(start) => (start, true),
+            _ => return Err(Error::format(reader, "expected an element in <revision>")),
+        };
+        let deleted = start
+            .try_get_attribute("deleted")
+            .map_err(|_| Error::format(reader, "invalid deleted attribute"))?
+            .is_some_and(|attr| attr.value.as_ref() == b"deleted");
+        let tag = Tag::try_from(start.name()).map_err(Error::from_infallible)?;
+        match (tag, is_empty) {
+            (Tag::Id, false) => id = Some(parse_text(reader, buf, tag)?),
+            (Tag::ParentId, false) => parent_id = Some(parse_text(reader, buf, tag)?),
+            (Tag::Timestamp, false) => timestamp = Some(parse_timestamp(reader, buf)?),
+            (Tag::Contributor, true) if deleted => contributor = Some(Contributor::Deleted),
+            (Tag::Contributor, false) => {
+                contributor = Some(read_contributor_contents(reader, buf)?)
+            }
+            (Tag::Minor, true) => minor = true,
+            (Tag::Minor, false) => {
+                read_borrowed_text(reader, tag)?;
+                minor = true
+            }
+            (Tag::Origin, false) => origin = Some(parse_text(reader, buf, tag)?),
+            (Tag::Comment, true) if deleted => comment = Comment::Deleted,
+            (Tag::Comment, true) => comment = Comment::Visible(String::new()),
+            (Tag::Comment, false) => {
+                comment = Comment::Visible(read_borrowed_text(reader, tag)?.into_owned())
+            }
+            (Tag::Model, false) => model = Some(parse_text(reader, buf, tag)?),
+            (Tag::Format, false) => format = Some(parse_text(reader, buf, tag)?),
+            (Tag::Text, _) => {
+                text_bytes = start
+                    .try_get_attribute("bytes")
+                    .map_err(|_| Error::format(reader, "invalid bytes attribute"))?
+                    .map(|attr| {
+                        std::str::from_utf8(&attr.value)
+                            .ok()
+                            .and_then(|bytes| bytes.parse().ok())
+                            .ok_or_else(|| Error::format(reader, "invalid bytes attribute"))
+                    })
+                    .transpose()?;
+                text_deleted = deleted;
+                text = Some(if is_empty {
+                    Cow::Borrowed("")
+                } else {
+                    read_borrowed_text(reader, tag)?
+                });
+            }
+            (Tag::DiscussionThreadingInfo, false) => {
+                threading_info = Some(threads::parse_threading_info(reader, buf)?)
+            }
+            // The SHA-1 is sometimes empty (`<sha1/>` or `<sha1></sha1>`) for deleted text.
+            (Tag::Sha1, true) => sha1 = None,
+            (Tag::Sha1, false) => {
+                sha1 = Some(read_borrowed_text(reader, tag)?).filter(|sha1| !sha1.is_empty())
+            }
+            _ => return Err(Error::format(reader, "unexpected element in <revision>")),
+        }
+    }
+
+    let missing = |context| Error::format(reader, context);
+    Ok(RevisionRef {
+        id: id.ok_or_else(|| missing("missing <id> in <revision>"))?,
+        parent_id,
+        timestamp: timestamp.ok_or_else(|| missing("missing <timestamp> in <revision>"))?,
+        contributor: contributor.ok_or_else(|| missing("missing <contributor> in <revision>"))?,
+        origin: origin.unwrap_or(0),
+        minor,
+        comment,
+        model: model.unwrap_or_else(|| Interned::new("wikitext")),
+        format: format.unwrap_or_else(|| Interned::new("text/x-wiki")),
+        text: text.ok_or_else(|| missing("missing <text> in <revision>"))?,
+        text_bytes,
+        text_deleted,
+        threading_info,
+        sha1,
+    })
+}
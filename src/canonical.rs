@@ -0,0 +1,80 @@
+use crate::{Comment, Page};
+
+/// Which fields [`canonicalize`] clears or normalizes.
+#[derive(Clone, Debug)]
+pub struct CanonicalizeOptions {
+    /// Clear `Page::restrictions`, which changes whenever a page is protected or unprotected.
+    pub strip_restrictions: bool,
+    /// Set `Revision::origin` to `0`.
+    pub strip_origin: bool,
+    /// Clear `Revision::sha1`.
+    pub strip_sha1: bool,
+    /// Convert `\r\n` to `\n`, remove whitespace at the end of each line,
+    /// and remove trailing newlines in revision text and comments.
+    pub normalize_whitespace: bool,
+}
+
+impl Default for CanonicalizeOptions {
+    fn default() -> Self {
+        Self {
+            strip_restrictions: true,
+            strip_origin: true,
+            strip_sha1: false,
+            normalize_whitespace: true,
+        }
+    }
+}
+
+/// Puts `page` into a form suitable for comparing dumps taken at different times:
+/// revisions are sorted by id and the fields selected by `options`
+/// are cleared or normalized.
+pub fn canonicalize(page: &mut Page, options: &CanonicalizeOptions) {
+    page.revisions.sort_by_key(|revision| revision.id);
+    if options.strip_restrictions {
+        page.restrictions = None;
+    }
+    for revision in &mut page.revisions {
+        if options.strip_origin {
+            revision.origin = 0;
+        }
+        if options.strip_sha1 {
+            revision.sha1 = None;
+        }
+        if options.normalize_whitespace {
+            revision.text = normalize_whitespace(&revision.text).into();
+            if let Comment::Visible(comment) = &mut revision.comment {
+                *comment = normalize_whitespace(comment);
+            }
+        }
+    }
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    let mut normalized = text
+        .split('\n')
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    normalized.truncate(normalized.trim_end_matches('\n').len());
+    normalized
+}
+
+#[test]
+fn test_canonicalize() {
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let mut page = pages[0].clone();
+    let mut other = page.clone();
+    other.revisions.reverse();
+    other.restrictions = None;
+    // Reversed, so this is the second revision.
+    other.revisions[0].origin += 1;
+    other.revisions[0].text = (other.revisions[0].text.replace(' ', " \r\n") + "\n\n").into();
+    page.revisions[1].text = page.revisions[1].text.replace(' ', "\n").into();
+    assert_ne!(page, other);
+
+    let options = CanonicalizeOptions::default();
+    canonicalize(&mut page, &options);
+    canonicalize(&mut other, &options);
+    assert_eq!(page, other);
+    assert_eq!(page.revisions[0].id, 1001);
+}
@@ -0,0 +1,27 @@
+//! Reading the CBOR sequence of pages that the binary writes by default.
+
+use std::io::Read;
+
+use crate::Page;
+
+/// Deserializes the pages in a CBOR sequence, such as the output of the binary
+/// with `--format cbor` (without `--batch` or `--framed`), so that it can be read again
+/// without parsing the XML.
+pub fn read_cbor_pages<R: Read>(
+    reader: R,
+) -> impl Iterator<Item = Result<Page, serde_cbor::Error>> {
+    serde_cbor::Deserializer::from_reader(reader).into_iter()
+}
+
+#[test]
+fn test_read_cbor_pages() {
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let mut cbor = Vec::new();
+    for page in &pages {
+        serde_cbor::to_writer(&mut cbor, page).unwrap();
+    }
+    let read: Vec<_> = read_cbor_pages(&cbor[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read, pages);
+}
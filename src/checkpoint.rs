@@ -0,0 +1,306 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, Seek, SeekFrom, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use quick_xml::Reader;
+
+use crate::{
+    read_header, read_next_document, read_page_with_range, skip_bom, split::skip_whitespace, Error,
+    Page, PageAction, PageHeader, ParseOptions,
+};
+
+/// The position just after a page in a dump and the id of the page,
+/// passed to the page processor of [`parse_with_checkpoints`] so that a long conversion
+/// can be continued with [`resume_from`] or [`resume_after_page`] after it is interrupted.
+///
+/// `offset` is counted in the bytes of the XML, so for a compressed dump it is a position
+/// in the decompressed stream, not in the file.
+/// It is written to a file as the offset and the page id separated by a space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub offset: u64,
+    pub page_id: u32,
+}
+
+impl Checkpoint {
+    /// Reads a checkpoint written by [`Checkpoint::write`], or returns `None` if there is no file at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .parse()
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the checkpoint to a temporary file next to `path` and renames it to `path`,
+    /// so that an interruption leaves either the old checkpoint or the new one.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut file = File::create(&temporary)?;
+        writeln!(file, "{}", self)?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.offset, self.page_id)
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (offset, page_id) = s
+            .trim()
+            .split_once(' ')
+            .ok_or("expected an offset and a page id")?;
+        Ok(Self {
+            offset: offset.parse().map_err(|_| "invalid offset")?,
+            page_id: page_id.parse().map_err(|_| "invalid page id")?,
+        })
+    }
+}
+
+/// Like [`parse_with_options`](crate::parse_with_options), but also passes `page_processor`
+/// the [`Checkpoint`] after each page. Write it with [`Checkpoint::write`]
+/// once the page has been handled, as often as is worth the cost of writing a file.
+pub fn parse_with_checkpoints<
+    R: BufRead,
+    F: FnMut(Page, Checkpoint) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: R,
+    options: &ParseOptions,
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let bom = skip_bom(&mut reader)?;
+    parse_from_offset(
+        Reader::from_reader(reader),
+        bom as u64,
+        options,
+        |_| PageAction::Keep,
+        page_processor,
+        skip_header,
+    )
+}
+
+/// Continues parsing an uncompressed dump after the page of `checkpoint`
+/// by seeking to its offset, and passes the pages after it to `page_processor`
+/// with checkpoints counted from the start of the dump, as [`parse_with_checkpoints`] does.
+///
+/// `reader` must be the XML itself, such as an uncompressed file: the offset of a checkpoint
+/// is a position in the decompressed XML, so seeking in a compressed file would not find the page.
+/// Use [`resume_after_page`] for compressed dumps and other input that cannot seek.
+/// Errors from seeking report the path as `-`.
+pub fn resume_from<
+    R: BufRead + Seek,
+    F: FnMut(Page, Checkpoint) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: R,
+    checkpoint: &Checkpoint,
+    options: &ParseOptions,
+    page_processor: F,
+) -> Result<(), Error<E>> {
+    let seek_error = |e| Error::from_io("seek", e, "-");
+    reader
+        .seek(SeekFrom::Start(checkpoint.offset))
+        .map_err(seek_error)?;
+    // The page ends before the whitespace that comes before the next page.
+    skip_whitespace(&mut reader).map_err(|e| Error::from_io("read", e, "-"))?;
+    let offset = reader.stream_position().map_err(seek_error)?;
+    let mut reader = Reader::from_reader(reader);
+    // The `</mediawiki>` end tag has no start tag in this part of the dump.
+    reader.check_end_names(false);
+    parse_from_offset(
+        reader,
+        offset,
+        options,
+        |_| PageAction::Keep,
+        page_processor,
+        false,
+    )
+}
+
+/// Continues parsing a dump after the page of `checkpoint` without seeking,
+/// for compressed dumps and other input that cannot seek: the pages up to and including
+/// the one with the id of the checkpoint are skipped without parsing their revisions,
+/// but the dump still has to be read (and decompressed) from the start.
+///
+/// If there is no page with that id, for instance because the dump is not the one
+/// that the checkpoint is from, every page is skipped.
+pub fn resume_after_page<
+    R: BufRead,
+    F: FnMut(Page, Checkpoint) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: R,
+    checkpoint: &Checkpoint,
+    options: &ParseOptions,
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let bom = skip_bom(&mut reader)?;
+    let mut passed = false;
+    parse_from_offset(
+        Reader::from_reader(reader),
+        bom as u64,
+        options,
+        |header| {
+            if passed {
+                PageAction::Keep
+            } else {
+                passed = header.id == checkpoint.page_id;
+                PageAction::Skip
+            }
+        },
+        page_processor,
+        skip_header,
+    )
+}
+
+/// Parses the pages in `reader`, which starts at `offset` in the dump,
+/// and passes each one to `page_processor` with its checkpoint.
+fn parse_from_offset<
+    R: BufRead,
+    H: FnMut(&PageHeader) -> PageAction,
+    F: FnMut(Page, Checkpoint) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: Reader<R>,
+    offset: u64,
+    options: &ParseOptions,
+    mut on_page_header: H,
+    mut page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    loop {
+        while let Some((page, range)) = read_page_with_range(
+            &mut reader,
+            &mut buf,
+            options,
+            &mut on_page_header,
+            &mut |_| {},
+        )? {
+            let checkpoint = Checkpoint {
+                offset: offset + range.end as u64,
+                page_id: page.id,
+            };
+            match page_processor(page, checkpoint) {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            }
+        }
+        if !(options.multi_document && read_next_document(&mut reader, &mut buf)?) {
+            return Ok(());
+        }
+    }
+}
+
+#[test]
+fn test_resume() {
+    use std::{convert::Infallible, io::Cursor};
+
+    let multi_document = ParseOptions {
+        multi_document: true,
+        ..ParseOptions::default()
+    };
+    let parse = |xml: &[u8], options| {
+        let mut pages = Vec::new();
+        parse_with_checkpoints::<_, _, Infallible>(
+            xml,
+            options,
+            |page, checkpoint| {
+                pages.push((page, checkpoint));
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        pages
+    };
+    for (xml, options) in [
+        (
+            &include_bytes!("../tests/fixtures/pages.xml")[..],
+            &ParseOptions::default(),
+        ),
+        (
+            include_bytes!("../tests/fixtures/history.xml"),
+            &ParseOptions::default(),
+        ),
+        (
+            include_bytes!("../tests/fixtures/bom.xml"),
+            &ParseOptions::default(),
+        ),
+        (
+            include_bytes!("../tests/fixtures/concatenated.xml"),
+            &multi_document,
+        ),
+    ] {
+        let all = parse(xml, options);
+        assert!(!all.is_empty());
+        for (i, (_, checkpoint)) in all.iter().enumerate() {
+            assert!(xml[..checkpoint.offset as usize].ends_with(b"</page>"));
+            let mut resumed = Vec::new();
+            resume_from::<_, _, Infallible>(
+                Cursor::new(xml),
+                checkpoint,
+                options,
+                |page, checkpoint| {
+                    resumed.push((page, checkpoint));
+                    Ok(())
+                },
+            )
+            .unwrap();
+            assert_eq!(resumed, all[i + 1..]);
+
+            let mut resumed = Vec::new();
+            resume_after_page::<_, _, Infallible>(
+                xml,
+                checkpoint,
+                options,
+                |page, checkpoint| {
+                    resumed.push((page, checkpoint));
+                    Ok(())
+                },
+                true,
+            )
+            .unwrap();
+            assert_eq!(resumed, all[i + 1..]);
+        }
+    }
+}
+
+#[test]
+fn test_checkpoint_file() {
+    let path = std::env::temp_dir().join(format!("checkpoint-test-{}", std::process::id()));
+    assert_eq!(Checkpoint::read(&path).unwrap(), None);
+    let checkpoint = Checkpoint {
+        offset: 12345,
+        page_id: 16,
+    };
+    checkpoint.write(&path).unwrap();
+    assert_eq!(Checkpoint::read(&path).unwrap(), Some(checkpoint));
+    fs::remove_file(&path).unwrap();
+    assert!("12345".parse::<Checkpoint>().is_err());
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    builder::{
+        BooleanBuilder, Int32Builder, LargeStringBuilder, StringBuilder,
+        TimestampMicrosecondBuilder, UInt32Builder,
+    },
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+
+use crate::{FlatRevision, Page};
+
+/// The columns of [`FlatRevision`] as an Arrow schema.
+/// Timestamps are microseconds in UTC, and `text` is a `LargeUtf8`
+/// because a batch of long revisions can exceed the 2 GiB limit of `Utf8`.
+pub fn revision_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("page_id", DataType::UInt32, false),
+        Field::new("title", DataType::Utf8, false),
+        Field::new("namespace", DataType::Int32, false),
+        Field::new("redirect_target", DataType::Utf8, true),
+        Field::new("revision_id", DataType::UInt32, false),
+        Field::new("parent_id", DataType::UInt32, true),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("contributor_type", DataType::Utf8, false),
+        Field::new("username", DataType::Utf8, true),
+        Field::new("user_id", DataType::UInt32, true),
+        Field::new("ip", DataType::Utf8, true),
+        Field::new("minor", DataType::Boolean, false),
+        Field::new("comment", DataType::Utf8, true),
+        Field::new("comment_deleted", DataType::Boolean, false),
+        Field::new("origin", DataType::UInt32, false),
+        Field::new("model", DataType::Utf8, false),
+        Field::new("format", DataType::Utf8, false),
+        Field::new("text", DataType::LargeUtf8, false),
+        Field::new("text_deleted", DataType::Boolean, false),
+        Field::new("sha1", DataType::Utf8, false),
+    ]))
+}
+
+/// Collects revisions into Arrow record batches with [`revision_schema`],
+/// so that columnar output holds at most one batch in memory.
+pub struct RevisionBatches {
+    schema: SchemaRef,
+    batch_size: usize,
+    rows: usize,
+    page_id: UInt32Builder,
+    title: StringBuilder,
+    namespace: Int32Builder,
+    redirect_target: StringBuilder,
+    revision_id: UInt32Builder,
+    parent_id: UInt32Builder,
+    timestamp: TimestampMicrosecondBuilder,
+    contributor_type: StringBuilder,
+    username: StringBuilder,
+    user_id: UInt32Builder,
+    ip: StringBuilder,
+    minor: BooleanBuilder,
+    comment: StringBuilder,
+    comment_deleted: BooleanBuilder,
+    origin: UInt32Builder,
+    model: StringBuilder,
+    format: StringBuilder,
+    text: LargeStringBuilder,
+    text_deleted: BooleanBuilder,
+    sha1: StringBuilder,
+}
+
+impl RevisionBatches {
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            schema: revision_schema(),
+            batch_size,
+            rows: 0,
+            page_id: UInt32Builder::new(),
+            title: StringBuilder::new(),
+            namespace: Int32Builder::new(),
+            redirect_target: StringBuilder::new(),
+            revision_id: UInt32Builder::new(),
+            parent_id: UInt32Builder::new(),
+            timestamp: TimestampMicrosecondBuilder::new().with_timezone("UTC"),
+            contributor_type: StringBuilder::new(),
+            username: StringBuilder::new(),
+            user_id: UInt32Builder::new(),
+            ip: StringBuilder::new(),
+            minor: BooleanBuilder::new(),
+            comment: StringBuilder::new(),
+            comment_deleted: BooleanBuilder::new(),
+            origin: UInt32Builder::new(),
+            model: StringBuilder::new(),
+            format: StringBuilder::new(),
+            text: LargeStringBuilder::new(),
+            text_deleted: BooleanBuilder::new(),
+            sha1: StringBuilder::new(),
+        }
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Adds the revisions of `page`, returning the batches that were filled.
+    pub fn push_page(&mut self, page: &Page) -> Result<Vec<RecordBatch>, ArrowError> {
+        let mut batches = Vec::new();
+        for row in FlatRevision::from_page(page) {
+            self.push(&row);
+            if self.rows >= self.batch_size {
+                batches.push(self.take_batch()?);
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Returns the last, partially filled batch, if there is one.
+    pub fn finish(&mut self) -> Result<Option<RecordBatch>, ArrowError> {
+        if self.rows == 0 {
+            Ok(None)
+        } else {
+            self.take_batch().map(Some)
+        }
+    }
+
+    fn push(&mut self, row: &FlatRevision<'_>) {
+        self.page_id.append_value(row.page_id);
+        self.title.append_value(row.title);
+        self.namespace.append_value(row.namespace);
+        self.redirect_target.append_option(row.redirect_target);
+        self.revision_id.append_value(row.revision_id);
+        self.parent_id.append_option(row.parent_id);
+        self.timestamp.append_value(
+            row.timestamp.timestamp() * 1_000_000
+                + i64::from(row.timestamp.timestamp_subsec_micros()),
+        );
+        self.contributor_type.append_value(row.contributor_type);
+        self.username.append_option(row.username);
+        self.user_id.append_option(row.user_id);
+        self.ip.append_option(row.ip.map(|ip| ip.to_string()));
+        self.minor.append_value(row.minor);
+        self.comment.append_option(row.comment);
+        self.comment_deleted.append_value(row.comment_deleted);
+        self.origin.append_value(row.origin);
+        self.model.append_value(row.model);
+        self.format.append_value(row.format);
+        self.text.append_value(row.text);
+        self.text_deleted.append_value(row.text_deleted);
+        self.sha1.append_value(row.sha1);
+        self.rows += 1;
+    }
+
+    fn take_batch(&mut self) -> Result<RecordBatch, ArrowError> {
+        self.rows = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.page_id.finish()),
+            Arc::new(self.title.finish()),
+            Arc::new(self.namespace.finish()),
+            Arc::new(self.redirect_target.finish()),
+            Arc::new(self.revision_id.finish()),
+            Arc::new(self.parent_id.finish()),
+            Arc::new(self.timestamp.finish()),
+            Arc::new(self.contributor_type.finish()),
+            Arc::new(self.username.finish()),
+            Arc::new(self.user_id.finish()),
+            Arc::new(self.ip.finish()),
+            Arc::new(self.minor.finish()),
+            Arc::new(self.comment.finish()),
+            Arc::new(self.comment_deleted.finish()),
+            Arc::new(self.origin.finish()),
+            Arc::new(self.model.finish()),
+            Arc::new(self.format.finish()),
+            Arc::new(self.text.finish()),
+            Arc::new(self.text_deleted.finish()),
+            Arc::new(self.sha1.finish()),
+        ];
+        RecordBatch::try_new(self.schema.clone(), columns)
+    }
+}
+
+#[test]
+fn test_revision_batches() {
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let mut batches = RevisionBatches::new(3);
+    let mut batch_sizes = Vec::new();
+    for page in &pages {
+        for batch in batches.push_page(page).unwrap() {
+            batch_sizes.push(batch.num_rows());
+        }
+    }
+    let last = batches.finish().unwrap().unwrap();
+    batch_sizes.push(last.num_rows());
+    assert_eq!(batch_sizes, [3, 1]);
+    assert_eq!(last.schema(), revision_schema());
+    assert!(batches.finish().unwrap().is_none());
+}
@@ -0,0 +1,138 @@
+#[cfg(any(feature = "bz2", feature = "gz", feature = "lzma", feature = "zstd"))]
+use std::io::BufReader;
+use std::{fmt, io::BufRead, path::Path, str::FromStr};
+
+#[cfg(feature = "bz2")]
+use bzip2::read::MultiBzDecoder;
+#[cfg(feature = "gz")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "lzma")]
+use lzma::LzmaReader;
+#[cfg(feature = "zstd")]
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+use crate::Error;
+
+/// The compression format of a dump. Decoding each format requires the
+/// corresponding cargo feature (`bz2`, `gz`, `lzma` or `zstd`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    Uncompressed,
+    Bz2,
+    Gz,
+    /// An xz stream (`.xz`) or a legacy `.lzma` stream; liblzma detects which.
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Guesses the compression format from the file extension,
+    /// returning `None` if the extension is not a known compression format.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "bz2" => Some(Self::Bz2),
+            "gz" => Some(Self::Gz),
+            "7z" | "xz" => Some(Self::Xz),
+            "zst" | "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Detects the compression format from the first few bytes of a file.
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(b"\x1f\x8b") {
+            Self::Gz
+        } else if bytes.starts_with(b"BZh") {
+            Self::Bz2
+        } else if bytes.starts_with(b"\xfd7zXZ\0") {
+            Self::Xz
+        } else if bytes.starts_with(b"\x28\xb5\x2f\xfd") {
+            Self::Zstd
+        } else {
+            Self::Uncompressed
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Uncompressed => "none",
+            Self::Bz2 => "bz2",
+            Self::Gz => "gz",
+            Self::Xz => "xz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    /// Wraps `reader` in a decoder for this format.
+    /// `path` is only used in error messages.
+    pub(crate) fn decoder<'a, R: BufRead + 'a, E: std::error::Error>(
+        self,
+        reader: R,
+        #[allow(unused_variables)] path: &Path,
+    ) -> Result<Box<dyn BufRead + 'a>, Error<E>> {
+        Ok(match self {
+            Self::Uncompressed => Box::new(reader),
+            // Multistream dumps are made of many concatenated bzip2 streams,
+            // and `BzDecoder` would stop after the first one.
+            #[cfg(feature = "bz2")]
+            Self::Bz2 => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+            // Some tools produce gzip files made of several concatenated members,
+            // and `GzDecoder` would stop after the first one.
+            #[cfg(feature = "gz")]
+            Self::Gz => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+            #[cfg(feature = "lzma")]
+            Self::Xz => Box::new(BufReader::new(
+                LzmaReader::new_decompressor(reader).map_err(|source| Error::Lzma {
+                    source,
+                    path: path.into(),
+                })?,
+            )),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Box::new(BufReader::new(ZstdDecoder::with_buffer(reader).map_err(
+                |source| Error::Zstd {
+                    source,
+                    path: path.into(),
+                },
+            )?)),
+            #[allow(unreachable_patterns)]
+            _ => return Err(Error::UnsupportedCompression(self)),
+        })
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Compression {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "none" => Self::Uncompressed,
+            "bz2" => Self::Bz2,
+            "gz" => Self::Gz,
+            "xz" | "7z" => Self::Xz,
+            "zst" | "zstd" => Self::Zstd,
+            _ => return Err("Invalid compression; expected none, bz2, gz, xz or zst"),
+        })
+    }
+}
+
+#[test]
+fn test_from_magic() {
+    assert_eq!(Compression::from_magic(b"\x1f\x8b\x08\0"), Compression::Gz);
+    assert_eq!(Compression::from_magic(b"BZh91AY"), Compression::Bz2);
+    assert_eq!(Compression::from_magic(b"\xfd7zXZ\0\0"), Compression::Xz);
+    assert_eq!(
+        Compression::from_magic(b"\x28\xb5\x2f\xfd\x04"),
+        Compression::Zstd
+    );
+    assert_eq!(
+        Compression::from_magic(b"<mediawiki"),
+        Compression::Uncompressed
+    );
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use crate::Contributor;
+
+/// A set of contributors for [`ParseOptions::contributors`](crate::ParseOptions::contributors),
+/// matched against the [`Contributor`] of each revision.
+/// A revision matches if any one of the usernames, user ids or IP ranges matches,
+/// and revisions whose contributor was deleted never match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContributorFilter {
+    /// Usernames of registered users, as they are in the dump (with spaces, not underscores).
+    pub usernames: HashSet<String>,
+    /// User ids of registered users.
+    pub user_ids: HashSet<u32>,
+    /// Ranges of the IP addresses of anonymous contributors.
+    pub ip_ranges: Vec<IpRange>,
+}
+
+impl ContributorFilter {
+    /// Adds an IP address or CIDR range such as `192.0.2.0/24` to
+    /// [`ip_ranges`](Self::ip_ranges), or anything else to [`usernames`](Self::usernames),
+    /// because MediaWiki does not allow usernames that are IP addresses.
+    pub fn add_contributor(&mut self, contributor: &str) {
+        match contributor.parse() {
+            Ok(range) => self.ip_ranges.push(range),
+            Err(_) => {
+                self.usernames.insert(contributor.to_owned());
+            }
+        }
+    }
+
+    pub fn matches(&self, contributor: &Contributor) -> bool {
+        match contributor {
+            Contributor::Deleted => false,
+            Contributor::Ip { ip } => self.ip_ranges.iter().any(|range| range.contains(*ip)),
+            Contributor::User { username, id } => {
+                self.user_ids.contains(id) || self.usernames.contains(username)
+            }
+        }
+    }
+}
+
+/// An IP address and the number of leading bits of it that are matched,
+/// parsed from CIDR notation like `192.0.2.0/24` or `2001:db8::/32`,
+/// or from a single address, which matches only itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpRange {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Fails if `prefix_len` is longer than the address.
+    pub fn new(address: IpAddr, prefix_len: u8) -> Result<Self, &'static str> {
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max {
+            return Err("prefix length longer than the address");
+        }
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    /// Whether the first [`prefix_len`](Self::prefix_len) bits of `ip` are those of the range.
+    /// IPv4 addresses never match IPv6 ranges, nor the reverse.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let prefix = |bits: u128, width: u8| match width - self.prefix_len {
+            128 => 0,
+            shift => bits >> shift,
+        };
+        match (self.address, ip) {
+            (IpAddr::V4(address), IpAddr::V4(ip)) => {
+                prefix(u32::from(address).into(), 32) == prefix(u32::from(ip).into(), 32)
+            }
+            (IpAddr::V6(address), IpAddr::V6(ip)) => {
+                prefix(address.into(), 128) == prefix(ip.into(), 128)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+}
+
+impl From<IpAddr> for IpRange {
+    fn from(address: IpAddr) -> Self {
+        let prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        Self {
+            address,
+            prefix_len,
+        }
+    }
+}
+
+impl From<Ipv4Addr> for IpRange {
+    fn from(address: Ipv4Addr) -> Self {
+        IpAddr::from(address).into()
+    }
+}
+
+impl From<Ipv6Addr> for IpRange {
+    fn from(address: Ipv6Addr) -> Self {
+        IpAddr::from(address).into()
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((address, prefix_len)) => Self::new(
+                address.parse().map_err(|_| "invalid IP address")?,
+                prefix_len.parse().map_err(|_| "invalid prefix length")?,
+            ),
+            None => Ok(s
+                .parse::<IpAddr>()
+                .map_err(|_| "invalid IP address")?
+                .into()),
+        }
+    }
+}
+
+#[test]
+fn test_ip_range() {
+    let range: IpRange = "192.0.2.0/24".parse().unwrap();
+    assert!(range.contains([192, 0, 2, 255].into()));
+    assert!(!range.contains([192, 0, 3, 0].into()));
+    assert!(!range.contains("::ffff:192.0.2.1".parse().unwrap()));
+    let range: IpRange = "2001:db8::/32".parse().unwrap();
+    assert!(range.contains("2001:db8:1::1".parse().unwrap()));
+    assert!(!range.contains("2001:db9::1".parse().unwrap()));
+    assert!("0.0.0.0/0"
+        .parse::<IpRange>()
+        .unwrap()
+        .contains([127, 0, 0, 1].into()));
+    assert!("::/0"
+        .parse::<IpRange>()
+        .unwrap()
+        .contains(Ipv6Addr::LOCALHOST.into()));
+    let single: IpRange = "127.0.0.1".parse().unwrap();
+    assert_eq!(single.prefix_len(), 32);
+    assert!(single.contains([127, 0, 0, 1].into()));
+    assert!(!single.contains([127, 0, 0, 2].into()));
+    assert!("192.0.2.0/33".parse::<IpRange>().is_err());
+    assert!("Example".parse::<IpRange>().is_err());
+
+    let mut filter = ContributorFilter::default();
+    filter.add_contributor("Example");
+    filter.add_contributor("127.0.0.0/8");
+    filter.user_ids.insert(1);
+    assert!(filter.matches(&Contributor::User {
+        username: "Example".into(),
+        id: 42,
+    }));
+    assert!(filter.matches(&Contributor::User {
+        username: "Wonderfool".into(),
+        id: 1,
+    }));
+    assert!(!filter.matches(&Contributor::User {
+        username: "Other".into(),
+        id: 2,
+    }));
+    assert!(filter.matches(&Contributor::Ip {
+        ip: [127, 0, 0, 1].into(),
+    }));
+    assert!(!filter.matches(&Contributor::Deleted));
+}
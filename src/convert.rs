@@ -0,0 +1,284 @@
+//! Converting a dump to the formats written by the binary, for use as a library.
+
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use crate::{parse_with_options, Error, Page, ParseOptions};
+
+/// The output formats of the binary (`--format`).
+/// [`convert`] writes the formats that have one record per page:
+/// [`Cbor`](Self::Cbor), [`Bincode`](Self::Bincode), [`Jsonl`](Self::Jsonl)
+/// and [`MessagePack`](Self::MessagePack).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Cbor,
+    Bincode,
+    Jsonl,
+    JsonArray,
+    Csv,
+    Tsv,
+    Yaml,
+    Toml,
+    MessagePack,
+    /// One JSONL record per revision with only the contributor and the change in length.
+    Contributors,
+    #[cfg(feature = "arrow")]
+    Arrow,
+    #[cfg(feature = "avro")]
+    Avro,
+    #[cfg(feature = "parquet")]
+    Parquet,
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl OutputFormat {
+    /// All the formats enabled at compile time, in the order they are listed in errors.
+    pub const ALL: &'static [OutputFormat] = &[
+        Self::Cbor,
+        Self::Bincode,
+        Self::Jsonl,
+        Self::JsonArray,
+        Self::Csv,
+        Self::Tsv,
+        Self::Yaml,
+        Self::Toml,
+        Self::MessagePack,
+        Self::Contributors,
+        #[cfg(feature = "arrow")]
+        Self::Arrow,
+        #[cfg(feature = "avro")]
+        Self::Avro,
+        #[cfg(feature = "parquet")]
+        Self::Parquet,
+        #[cfg(feature = "protobuf")]
+        Self::Protobuf,
+        #[cfg(feature = "sqlite")]
+        Self::Sqlite,
+    ];
+
+    /// The name of the format for `--format`, which is also its [`Display`](fmt::Display).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cbor => "cbor",
+            Self::Bincode => "bincode",
+            Self::Jsonl => "jsonl",
+            Self::JsonArray => "json-array",
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::MessagePack => "messagepack",
+            Self::Contributors => "contributors",
+            #[cfg(feature = "arrow")]
+            Self::Arrow => "arrow",
+            #[cfg(feature = "avro")]
+            Self::Avro => "avro",
+            #[cfg(feature = "parquet")]
+            Self::Parquet => "parquet",
+            #[cfg(feature = "protobuf")]
+            Self::Protobuf => "protobuf",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => "sqlite",
+        }
+    }
+
+    /// Formats that need a seekable file rather than stdout.
+    pub fn requires_output_file(&self) -> bool {
+        match self {
+            #[cfg(feature = "parquet")]
+            Self::Parquet => true,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Matches the [`name`](OutputFormat::name) of a format, ignoring case.
+/// `json` is accepted as another name for [`Jsonl`](OutputFormat::Jsonl).
+impl FromStr for OutputFormat {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("json") {
+            return Ok(Self::Jsonl);
+        }
+        Self::ALL
+            .iter()
+            .find(|format| s.eq_ignore_ascii_case(format.name()))
+            .copied()
+            .ok_or_else(|| UnknownFormat(s.to_owned()))
+    }
+}
+
+/// A string that is not the name of one of [`OutputFormat::ALL`].
+/// The message lists the valid names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFormat(pub String);
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown format {:?}, expected one of ", self.0)?;
+        for (i, format) in OutputFormat::ALL.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(format.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownFormat {}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum ConvertError {
+    #[error("failed to write output")]
+    Io(#[from] io::Error),
+    #[error("failed to serialize page as CBOR")]
+    Cbor(#[from] serde_cbor::Error),
+    #[error("failed to serialize page as Bincode")]
+    Bincode(#[from] bincode::Error),
+    #[error("failed to serialize page as JSON")]
+    Json(#[from] serde_json::Error),
+    #[error("failed to serialize page as MessagePack")]
+    MessagePack(#[from] rmp_serde::encode::Error),
+    #[error("{0:?} is not supported by convert")]
+    UnsupportedFormat(OutputFormat),
+}
+
+/// Parses the dump in `reader` with `options` and writes each page to `writer` in `format`,
+/// as the binary does with `--format` and no other output options:
+/// CBOR, Bincode (with fixed-width integers) and MessagePack values one after another,
+/// or one line of JSON per page, with timestamps in RFC 3339 format.
+///
+/// Returns [`ConvertError::UnsupportedFormat`] without reading anything
+/// for the other formats, which need more than one page at a time.
+/// `writer` is flushed at the end.
+pub fn convert<R: BufRead, W: Write>(
+    reader: R,
+    mut writer: W,
+    format: OutputFormat,
+    options: &ParseOptions,
+) -> Result<(), Error<ConvertError>> {
+    if !matches!(
+        format,
+        OutputFormat::Cbor
+            | OutputFormat::Bincode
+            | OutputFormat::Jsonl
+            | OutputFormat::MessagePack
+    ) {
+        return Err(Error::Other(ConvertError::UnsupportedFormat(format)));
+    }
+    parse_with_options(
+        reader,
+        options,
+        |page| write_page(&mut writer, format, &page).map_err(Error::Other),
+        true,
+    )?;
+    writer.flush().map_err(|e| Error::Other(e.into()))
+}
+
+/// Writes one page in `format` as [`convert`] does, or returns
+/// [`ConvertError::UnsupportedFormat`] for the formats that it does not support.
+pub fn write_page<W: Write + ?Sized>(
+    writer: &mut W,
+    format: OutputFormat,
+    page: &Page,
+) -> Result<(), ConvertError> {
+    match format {
+        OutputFormat::Cbor => serde_cbor::to_writer(writer, page)?,
+        OutputFormat::Bincode => {
+            use bincode::Options;
+            bincode::DefaultOptions::new()
+                .with_fixint_encoding()
+                .allow_trailing_bytes()
+                .serialize_into(writer, page)?
+        }
+        OutputFormat::Jsonl => {
+            serde_json::to_writer(&mut *writer, page)?;
+            writeln!(writer)?
+        }
+        OutputFormat::MessagePack => rmp_serde::encode::write(writer, page)?,
+        _ => return Err(ConvertError::UnsupportedFormat(format)),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_output_format_names() {
+    for format in OutputFormat::ALL {
+        assert_eq!(&format.to_string().parse::<OutputFormat>().unwrap(), format);
+        assert_eq!(
+            &format
+                .name()
+                .to_uppercase()
+                .parse::<OutputFormat>()
+                .unwrap(),
+            format
+        );
+    }
+    assert_eq!("JSON".parse(), Ok(OutputFormat::Jsonl));
+    let error = "xml".parse::<OutputFormat>().unwrap_err();
+    assert_eq!(error, UnknownFormat("xml".into()));
+    assert!(error
+        .to_string()
+        .starts_with(r#"unknown format "xml", expected one of cbor, bincode, jsonl,"#));
+}
+
+#[test]
+fn test_convert() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let pages = crate::parse_to_vec(xml);
+    let options = ParseOptions::default();
+
+    let mut cbor = Vec::new();
+    convert(&xml[..], &mut cbor, OutputFormat::Cbor, &options).unwrap();
+    let converted: Vec<Page> = crate::read_cbor_pages(&cbor[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(converted, pages);
+
+    let mut jsonl = Vec::new();
+    convert(&xml[..], &mut jsonl, OutputFormat::Jsonl, &options).unwrap();
+    let converted: Vec<Page> = std::str::from_utf8(&jsonl)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(converted, pages);
+
+    let mut messagepack = Vec::new();
+    convert(
+        &xml[..],
+        &mut messagepack,
+        OutputFormat::MessagePack,
+        &options,
+    )
+    .unwrap();
+    let mut rest = &messagepack[..];
+    for page in &pages {
+        assert_eq!(&rmp_serde::from_read::<_, Page>(&mut rest).unwrap(), page);
+    }
+    assert!(rest.is_empty());
+
+    assert!(matches!(
+        convert(&xml[..], Vec::new(), OutputFormat::Csv, &options),
+        Err(Error::Other(ConvertError::UnsupportedFormat(
+            OutputFormat::Csv
+        )))
+    ));
+}
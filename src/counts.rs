@@ -0,0 +1,72 @@
+use std::{collections::BTreeMap, convert::TryFrom, io::BufRead};
+
+use quick_xml::{events::Event, Reader};
+use serde::{Deserialize, Serialize};
+
+use crate::{parse_text, tag::Tag, Error};
+
+/// The number of pages, revisions and redirects in a dump, returned by [`count`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counts {
+    pub pages: u64,
+    pub revisions: u64,
+    pub redirects: u64,
+    /// The number of pages in each namespace.
+    pub namespaces: BTreeMap<i32, u64>,
+}
+
+/// Counts the pages and revisions in a dump by looking at the start tags,
+/// without building a [`Page`](crate::Page) for each page.
+/// Only `<ns>` elements are parsed, for the namespace counts.
+pub fn count<R: BufRead>(reader: R) -> Result<Counts, Error> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut counts = Counts::default();
+    loop {
+        buf.clear();
+        let tag = match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(start)) => Tag::try_from(start.name()).ok(),
+            Ok(Event::Empty(empty)) if empty.name() == Tag::Redirect.as_q_name() => {
+                Some(Tag::Redirect)
+            }
+            Ok(Event::Eof) => return Ok(counts),
+            Ok(_) => None,
+            Err(_) => return Err(Error::format(&reader, "invalid XML syntax")),
+        };
+        match tag {
+            Some(Tag::Page) => counts.pages += 1,
+            Some(Tag::Revision) => counts.revisions += 1,
+            Some(Tag::Redirect) => counts.redirects += 1,
+            Some(Tag::Ns) => {
+                let namespace = parse_text(&mut reader, &mut buf, Tag::Ns)?;
+                *counts.namespaces.entry(namespace).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_count() {
+    let counts = count(&include_bytes!("../tests/fixtures/pages.xml")[..]).unwrap();
+    assert_eq!(
+        counts,
+        Counts {
+            pages: 3,
+            revisions: 4,
+            redirects: 1,
+            namespaces: [(0, 2), (10, 1)].iter().copied().collect(),
+        }
+    );
+
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/history.xml"));
+    let counts = count(&include_bytes!("../tests/fixtures/history.xml")[..]).unwrap();
+    assert_eq!(counts.pages, pages.len() as u64);
+    assert_eq!(
+        counts.revisions,
+        pages
+            .iter()
+            .map(|page| page.revisions.len() as u64)
+            .sum::<u64>()
+    );
+}
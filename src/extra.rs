@@ -0,0 +1,68 @@
+use std::{collections::BTreeMap, io::BufRead};
+
+use quick_xml::{events::Event, Reader};
+
+use crate::{decode_cdata, read_event, Error};
+
+/// Collects the text of the elements named in [`ParseOptions::extra_elements`](crate::ParseOptions::extra_elements),
+/// which are not part of the export schema, while a `<page>` or `<revision>` is parsed.
+pub(crate) struct Extra<'o> {
+    names: &'o [String],
+    pub(crate) values: BTreeMap<String, String>,
+}
+
+impl<'o> Extra<'o> {
+    pub(crate) fn new(names: &'o [String]) -> Self {
+        Self {
+            names,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// If `event` is the start of one of the listed elements,
+    /// returns its name and whether it is empty.
+    pub(crate) fn listed(&self, event: &Event) -> Option<(&'o str, bool)> {
+        let (start, is_empty) = match event {
+            Event::Start(start) => (start, false),
+            Event::Empty(start) => (start, true),
+            _ => return None,
+        };
+        let names = self.names;
+        names
+            .iter()
+            .find(|name| name.as_bytes() == start.name().as_ref())
+            .map(|name| (name.as_str(), is_empty))
+    }
+
+    /// Reads the text of the element `name`, whose start tag has been read, up to its end tag.
+    /// The text of any elements inside it is included, without their tags.
+    /// If the element occurs more than once, the last one is kept.
+    pub(crate) fn read<R: BufRead, E: std::error::Error>(
+        &mut self,
+        reader: &mut Reader<R>,
+        buf: &mut Vec<u8>,
+        name: &str,
+        is_empty: bool,
+    ) -> Result<(), Error<E>> {
+        let mut text = String::new();
+        let mut depth = 0usize;
+        if !is_empty {
+            loop {
+                match read_event(reader, buf)? {
+                    Event::Text(more) => {
+                        text.push_str(&more.unescape().map_err(|_| Error::FailedToDecode {
+                            position: reader.buffer_position(),
+                        })?)
+                    }
+                    Event::CData(cdata) => text.push_str(decode_cdata(reader, &cdata)?),
+                    Event::Start(_) => depth += 1,
+                    Event::End(_) if depth == 0 => break,
+                    Event::End(_) => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+        self.values.insert(name.to_owned(), text);
+        Ok(())
+    }
+}
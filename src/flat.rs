@@ -0,0 +1,74 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{Contributor, Page, Revision};
+
+/// A revision together with the fields of its page,
+/// for output formats with one row per revision.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlatRevision<'a> {
+    pub page_id: u32,
+    pub title: &'a str,
+    pub namespace: i32,
+    pub redirect_target: Option<&'a str>,
+    pub revision_id: u32,
+    pub parent_id: Option<u32>,
+    pub timestamp: DateTime<Utc>,
+    /// `"deleted"`, `"ip"`, or `"user"`.
+    pub contributor_type: &'static str,
+    pub username: Option<&'a str>,
+    pub user_id: Option<u32>,
+    pub ip: Option<IpAddr>,
+    pub minor: bool,
+    pub comment: Option<&'a str>,
+    pub comment_deleted: bool,
+    pub origin: u32,
+    pub model: &'a str,
+    pub format: &'a str,
+    pub text: &'a str,
+    pub text_deleted: bool,
+    pub sha1: &'a str,
+}
+
+impl<'a> FlatRevision<'a> {
+    pub fn new(page: &'a Page, revision: &'a Revision) -> Self {
+        let (contributor_type, username, user_id, ip) = match &revision.contributor {
+            Contributor::Deleted => ("deleted", None, None, None),
+            Contributor::Ip { ip } => ("ip", None, None, Some(*ip)),
+            Contributor::User { username, id } => {
+                ("user", Some(username.as_str()), Some(*id), None)
+            }
+        };
+        Self {
+            page_id: page.id,
+            title: &page.title,
+            namespace: page.namespace,
+            redirect_target: page.redirect_target.as_deref(),
+            revision_id: revision.id,
+            parent_id: revision.parent_id,
+            timestamp: revision.timestamp,
+            contributor_type,
+            username,
+            user_id,
+            ip,
+            minor: revision.minor,
+            comment: revision.comment.as_visible(),
+            comment_deleted: revision.comment.is_deleted(),
+            origin: revision.origin,
+            model: &revision.model,
+            format: &revision.format,
+            text: &revision.text,
+            text_deleted: revision.text_deleted,
+            sha1: revision.sha1.as_deref().unwrap_or_default(),
+        }
+    }
+
+    /// One row for each revision of `page`.
+    pub fn from_page(page: &'a Page) -> impl Iterator<Item = FlatRevision<'a>> + 'a {
+        page.revisions
+            .iter()
+            .map(move |revision| FlatRevision::new(page, revision))
+    }
+}
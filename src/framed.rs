@@ -0,0 +1,143 @@
+//! Reading the length-delimited records written by the binary with `--framed`.
+//!
+//! Each record is a little-endian `u32` giving the length in bytes of the serialized value,
+//! followed by the value itself in CBOR or MessagePack. Because the length is known
+//! before decoding, a record that cannot be decoded can be skipped and reading can continue
+//! with the next one.
+
+use std::io::{self, Read};
+
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+/// The serialization format of the records in a framed stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Cbor,
+    MessagePack,
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FrameError {
+    #[error("failed to read framed record")]
+    Io(#[from] io::Error),
+    /// The stream ended inside the length or the body of the record that starts at `position`.
+    #[error("truncated record at position {position}")]
+    Truncated { position: u64 },
+    /// The body of the record that starts at `position` could not be deserialized.
+    /// The records after it can still be read.
+    #[error("failed to decode record at position {position}")]
+    Decode {
+        position: u64,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl FrameFormat {
+    fn decode<T: DeserializeOwned>(
+        self,
+        bytes: &[u8],
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(match self {
+            FrameFormat::Cbor => serde_cbor::from_slice(bytes)?,
+            FrameFormat::MessagePack => rmp_serde::from_slice(bytes)?,
+        })
+    }
+}
+
+/// Reads the length-delimited records in `reader`, as written by `--framed`,
+/// and deserializes each one from `format`, for instance as a [`Page`](crate::Page)
+/// or, with `--batch`, a `Vec<Page>`.
+///
+/// A record that cannot be decoded is returned as [`FrameError::Decode`] and the iterator
+/// continues with the next record. The iterator ends after an I/O error or a truncated record.
+pub fn read_framed<R: Read, T: DeserializeOwned>(
+    mut reader: R,
+    format: FrameFormat,
+) -> impl Iterator<Item = Result<T, FrameError>> {
+    let mut position = 0;
+    let mut body = Vec::new();
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let record_start = position;
+        let result = read_record(&mut reader, &mut body, &mut position);
+        match result {
+            Ok(false) => {
+                done = true;
+                None
+            }
+            Ok(true) => Some(format.decode(&body).map_err(|source| FrameError::Decode {
+                position: record_start,
+                source,
+            })),
+            Err(e) => {
+                done = true;
+                Some(Err(match e.kind() {
+                    io::ErrorKind::UnexpectedEof => FrameError::Truncated {
+                        position: record_start,
+                    },
+                    _ => FrameError::Io(e),
+                }))
+            }
+        }
+    })
+}
+
+/// Reads the next record into `body`, or returns `false` at the end of the stream.
+fn read_record<R: Read>(
+    reader: &mut R,
+    body: &mut Vec<u8>,
+    position: &mut u64,
+) -> io::Result<bool> {
+    let mut len = [0; 4];
+    let mut read = 0;
+    while read < len.len() {
+        match reader.read(&mut len[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let len = u32::from_le_bytes(len);
+    body.clear();
+    reader.by_ref().take(len.into()).read_to_end(body)?;
+    if body.len() < len as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    *position += 4 + u64::from(len);
+    Ok(true)
+}
+
+#[test]
+fn test_read_framed() {
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let mut framed = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let mut record = serde_cbor::to_vec(page).unwrap();
+        if i == 1 {
+            record.truncate(record.len() / 2);
+        }
+        framed.extend((record.len() as u32).to_le_bytes());
+        framed.extend(record);
+    }
+    let results: Vec<Result<crate::Page, _>> =
+        read_framed(&framed[..], FrameFormat::Cbor).collect();
+    assert_eq!(results.len(), pages.len());
+    assert!(matches!(results[1], Err(FrameError::Decode { .. })));
+    for (i, (result, page)) in results.iter().zip(&pages).enumerate() {
+        if i != 1 {
+            assert_eq!(result.as_ref().unwrap(), page);
+        }
+    }
+
+    framed.truncate(framed.len() - 1);
+    let last = read_framed::<_, crate::Page>(&framed[..], FrameFormat::Cbor).last();
+    assert!(matches!(last, Some(Err(FrameError::Truncated { .. }))));
+}
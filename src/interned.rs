@@ -0,0 +1,130 @@
+use std::{borrow::Cow, convert::Infallible, fmt, ops::Deref, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The content models and formats used by Wikimedia wikis.
+/// Revisions almost always have one of these, so they are stored without allocating.
+const KNOWN: &[&str] = &[
+    "wikitext",
+    "text/x-wiki",
+    "Scribunto",
+    "text/plain",
+    "css",
+    "sanitized-css",
+    "text/css",
+    "javascript",
+    "text/javascript",
+    "json",
+    "application/json",
+    "text",
+    "flow-board",
+    "MassMessageListContent",
+    "proofread-page",
+    "proofread-index",
+    "GadgetDefinition",
+    "JsonSchema",
+    "Tabular.JsonConfig",
+    "Map.JsonConfig",
+    "wikibase-item",
+    "wikibase-property",
+    "wikibase-lexeme",
+    "wikibase-entityschema",
+    "unknown/unknown",
+];
+
+/// A string that is usually one of a small set of values,
+/// used for the `model` and `format` of a [`Revision`](crate::Revision).
+/// Known values are borrowed from a static table instead of being allocated for every revision.
+/// It serializes and deserializes as a string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interned(Cow<'static, str>);
+
+impl Interned {
+    pub fn new(s: &str) -> Self {
+        match KNOWN.iter().find(|known| **known == s) {
+            Some(known) => Self(Cow::Borrowed(known)),
+            None => Self(Cow::Owned(s.into())),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the value was found in the table of known values.
+    pub fn is_known(&self) -> bool {
+        matches!(self.0, Cow::Borrowed(_))
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Interned {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Interned {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl FromStr for Interned {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<str> for Interned {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Interned {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = Cow::<'de, str>::deserialize(deserializer)?;
+        Ok(Self::new(&s))
+    }
+}
+
+#[test]
+fn test_interned() {
+    let model = Interned::new("wikitext");
+    assert!(model.is_known());
+    assert_eq!(model, "wikitext");
+    let custom = Interned::new("my-model");
+    assert!(!custom.is_known());
+    assert_eq!(serde_json::to_string(&model).unwrap(), r#""wikitext""#);
+    let deserialized: Interned = serde_json::from_str(r#""text/x-wiki""#).unwrap();
+    assert!(deserialized.is_known());
+    assert_eq!(deserialized.as_str(), "text/x-wiki");
+}
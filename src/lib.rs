@@ -1,30 +1,115 @@
 use std::{
     borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     convert::{Infallible, TryFrom},
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Read},
     net::IpAddr,
+    ops::Range,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
 };
 
-#[cfg(feature = "bz2")]
-use bzip2::read::BzDecoder;
 use chrono::{DateTime, Utc};
 #[cfg(feature = "lzma")]
-use lzma::{LzmaError, LzmaReader};
+use lzma::LzmaError;
 use memchr::memmem;
-use quick_xml::{events::Event, name::QName, Reader};
+use quick_xml::{
+    events::{BytesCData, Event},
+    name::QName,
+    Reader,
+};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "async")]
+pub use asynchronous::parse_async;
+mod borrowed;
+pub use borrowed::{parse_borrowed, PageRef, RevisionRef};
+mod canonical;
+pub use canonical::{canonicalize, CanonicalizeOptions};
+mod cbor;
+pub use cbor::read_cbor_pages;
+mod checkpoint;
+pub use checkpoint::{parse_with_checkpoints, resume_after_page, resume_from, Checkpoint};
+mod contributors;
+pub use contributors::{ContributorFilter, IpRange};
+mod compression;
+pub use compression::Compression;
+mod convert;
+pub use convert::{convert, write_page, ConvertError, OutputFormat, UnknownFormat};
+mod counts;
+pub use counts::{count, Counts};
+#[cfg(feature = "arrow")]
+mod columnar;
+#[cfg(feature = "arrow")]
+pub use columnar::{revision_schema, RevisionBatches};
+mod extra;
+use extra::Extra;
+mod flat;
+pub use flat::FlatRevision;
+mod framed;
+pub use framed::{read_framed, FrameError, FrameFormat};
+mod interned;
+pub use interned::Interned;
+mod location;
+pub use location::line_and_column;
+mod logitem;
+pub use logitem::LogItem;
+#[cfg(feature = "bz2")]
+mod multistream;
+#[cfg(feature = "bz2")]
+pub use multistream::{find_page_multistream, read_index, seek_to_offset, IndexEntry};
+mod options;
+pub use options::{ParseOptions, Redirects};
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "parallel")]
+pub use parallel::parse_parallel;
+mod parser;
+pub use parser::Parser;
+mod protection;
+pub use protection::ProtectionLevels;
+mod raw;
+pub use raw::{parse_slice_with_raw, parse_with_raw};
+mod revisions;
+pub use revisions::{revisions, PageInfo, Revisions};
+mod sha1;
+pub use sha1::{hex_to_sha1_base36, sha1_to_hex};
+mod siteinfo;
+pub use siteinfo::{DumpHeader, Namespaces, SiteInfo};
+mod split;
 mod tag;
-use tag::Tag;
+mod text_cache;
+pub use tag::Tag;
+use text_cache::TextCache;
+mod upload;
+pub use upload::Upload;
+mod threads;
+pub use threads::{ThreadNode, ThreadTree, ThreadingInfo};
+mod title;
+pub use title::{normalize_title, TitleCase};
+mod wikibase;
+pub use wikibase::{is_wikibase_model, EntitySummary};
+mod timestamp;
+use timestamp::DumpTimestamp;
+pub use timestamp::{FormattedTimestamp, TimestampFormat};
 
+/// New variants may be added, so `match`es on this enum need a wildcard arm;
+/// [`Error::position`] and the other accessors cover the common cases.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error<E: std::error::Error + 'static = Infallible> {
-    #[error("invalid XML (schema or format) at position {position}")]
-    Format { position: usize },
+    /// The XML was not well-formed or did not follow the export schema.
+    /// `context` describes what the parser expected, such as `expected <text> in <revision>`.
+    #[error("invalid XML (schema or format) at position {position}: {context}")]
+    Format {
+        position: usize,
+        context: &'static str,
+    },
     #[error(
         "expected tag {}, got tag {} at position {position}",
         expected.as_str(),
@@ -35,8 +120,22 @@ pub enum Error<E: std::error::Error + 'static = Infallible> {
         actual: Tag,
         position: usize,
     },
+    /// The input ended inside a `<page>`, which usually means that the dump was truncated.
+    #[error("unexpected end of file at position {position}")]
+    UnexpectedEof { position: usize },
     #[error("failed to unescape or decode UTF-8 at position {position}")]
     FailedToDecode { position: usize },
+    /// The text of an element could not be parsed as a number or other value.
+    #[error("invalid {} {value:?} at position {position}", tag.as_str())]
+    Value {
+        tag: Tag,
+        value: String,
+        position: usize,
+    },
+    /// The text of a `<timestamp>` was neither an RFC 3339 date and time
+    /// nor a 14-digit MediaWiki timestamp (`20040304103112`).
+    #[error("invalid timestamp {value:?} at position {position}")]
+    Timestamp { value: String, position: usize },
     #[error("failed to open XML file: {0}")]
     File(#[source] quick_xml::Error),
     #[error("Failed to {action} at {}", path.display())]
@@ -48,8 +147,34 @@ pub enum Error<E: std::error::Error + 'static = Infallible> {
     #[error("Failed to decode LZMA at {}", path.display())]
     #[cfg(feature = "lzma")]
     Lzma { source: LzmaError, path: Box<Path> },
+    #[error("Failed to decode zstd at {}", path.display())]
+    #[cfg(feature = "zstd")]
+    Zstd {
+        source: std::io::Error,
+        path: Box<Path>,
+    },
+    #[error("Failed to build thread pool")]
+    #[cfg(feature = "parallel")]
+    ThreadPool(#[source] rayon::ThreadPoolBuildError),
+    #[error("{0} decompression was not enabled at compile time")]
+    UnsupportedCompression(Compression),
     #[error("Unexpected tag: {}", String::from_utf8_lossy(.0))]
     UnexpectedTag(Box<[u8]>),
+    /// The SHA-1 of the text of a revision did not match its `<sha1>`,
+    /// found because [`ParseOptions::verify_sha1`] was set.
+    #[error("SHA-1 of revision {revision_id} is {actual}, expected {expected}")]
+    Sha1Mismatch {
+        revision_id: u32,
+        expected: String,
+        actual: String,
+    },
+    /// The text of a revision with a Wikibase content model was not a JSON object,
+    /// found because [`ParseOptions::wikibase_entities`] was set.
+    #[error("invalid entity JSON in revision {revision_id}")]
+    Entity {
+        revision_id: u32,
+        source: serde_json::Error,
+    },
     /// Return `Err(Error::ShortCircuit)` from the `page_processor` callback of [`parse`] or [`parse_from_file`]
     /// to stop parsing pages early even though there was no error.
     #[error("Done deserializing")]
@@ -62,9 +187,10 @@ pub enum Error<E: std::error::Error + 'static = Infallible> {
 }
 
 impl<E: std::error::Error> Error<E> {
-    fn format<R: BufRead>(reader: &Reader<R>) -> Self {
+    fn format<R: BufRead>(reader: &Reader<R>, context: &'static str) -> Self {
         Self::Format {
             position: reader.buffer_position(),
+            context,
         }
     }
 
@@ -90,7 +216,7 @@ impl<E: std::error::Error> Error<E> {
     #[allow(clippy::wrong_self_convention)]
     fn from_infallible(e: Error<Infallible>) -> Error<E> {
         match e {
-            Error::Format { position } => Error::Format { position },
+            Error::Format { position, context } => Error::Format { position, context },
             Error::Tag {
                 expected,
                 actual: tag,
@@ -100,7 +226,18 @@ impl<E: std::error::Error> Error<E> {
                 actual: tag,
                 position,
             },
+            Error::UnexpectedEof { position } => Error::UnexpectedEof { position },
             Error::FailedToDecode { position } => Error::FailedToDecode { position },
+            Error::Value {
+                tag,
+                value,
+                position,
+            } => Error::Value {
+                tag,
+                value,
+                position,
+            },
+            Error::Timestamp { value, position } => Error::Timestamp { value, position },
             Error::File(e) => Error::File(e),
             Error::ShortCircuit => Error::ShortCircuit,
             Error::Io {
@@ -114,14 +251,67 @@ impl<E: std::error::Error> Error<E> {
             },
             #[cfg(feature = "lzma")]
             Error::Lzma { source, path } => Error::Lzma { source, path },
+            #[cfg(feature = "zstd")]
+            Error::Zstd { source, path } => Error::Zstd { source, path },
+            #[cfg(feature = "parallel")]
+            Error::ThreadPool(e) => Error::ThreadPool(e),
+            Error::UnsupportedCompression(c) => Error::UnsupportedCompression(c),
             Error::UnexpectedTag(e) => Error::UnexpectedTag(e),
+            Error::Sha1Mismatch {
+                revision_id,
+                expected,
+                actual,
+            } => Error::Sha1Mismatch {
+                revision_id,
+                expected,
+                actual,
+            },
+            Error::Entity {
+                revision_id,
+                source,
+            } => Error::Entity {
+                revision_id,
+                source,
+            },
             Error::Other(_) => unreachable!(),
         }
     }
 
+    /// The byte position in the XML at which the error was detected, if known.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Error::Format { position, .. }
+            | Error::Tag { position, .. }
+            | Error::UnexpectedEof { position }
+            | Error::FailedToDecode { position }
+            | Error::Value { position, .. }
+            | Error::Timestamp { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// The line and column of [`Error::position`] in `input`, the XML that was parsed,
+    /// found with [`line_and_column`]. Returns `None` if the position is unknown
+    /// or `input` could not be read up to it.
+    pub fn line_and_column<R: BufRead>(&self, input: R) -> Option<(usize, usize)> {
+        line_and_column(input, self.position()?).ok()
+    }
+
+    pub fn is_short_circuit(&self) -> bool {
+        matches!(self, Error::ShortCircuit)
+    }
+
+    /// The error returned by the `page_processor` callback, if this is one.
+    pub fn as_other(&self) -> Option<&E> {
+        match self {
+            Error::Other(e) => Some(e),
+            _ => None,
+        }
+    }
+
     pub fn to_infallible(e: Error<E>) -> Result<Error<Infallible>, E> {
         Ok(match e {
-            Error::Format { position } => Error::Format { position },
+            Error::Format { position, context } => Error::Format { position, context },
             Error::Tag {
                 expected,
                 actual: tag,
@@ -131,7 +321,18 @@ impl<E: std::error::Error> Error<E> {
                 actual: tag,
                 position,
             },
+            Error::UnexpectedEof { position } => Error::UnexpectedEof { position },
             Error::FailedToDecode { position } => Error::FailedToDecode { position },
+            Error::Value {
+                tag,
+                value,
+                position,
+            } => Error::Value {
+                tag,
+                value,
+                position,
+            },
+            Error::Timestamp { value, position } => Error::Timestamp { value, position },
             Error::File(e) => Error::File(e),
             Error::ShortCircuit => Error::ShortCircuit,
             Error::Io {
@@ -145,50 +346,321 @@ impl<E: std::error::Error> Error<E> {
             },
             #[cfg(feature = "lzma")]
             Error::Lzma { source, path } => Error::Lzma { source, path },
+            #[cfg(feature = "zstd")]
+            Error::Zstd { source, path } => Error::Zstd { source, path },
+            #[cfg(feature = "parallel")]
+            Error::ThreadPool(e) => Error::ThreadPool(e),
+            Error::UnsupportedCompression(c) => Error::UnsupportedCompression(c),
             Error::UnexpectedTag(e) => Error::UnexpectedTag(e),
+            Error::Sha1Mismatch {
+                revision_id,
+                expected,
+                actual,
+            } => Error::Sha1Mismatch {
+                revision_id,
+                expected,
+                actual,
+            },
+            Error::Entity {
+                revision_id,
+                source,
+            } => Error::Entity {
+                revision_id,
+                source,
+            },
             Error::Other(other) => return Err(other),
         })
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Page {
+    /// Normalized with [`normalize_title`] if [`ParseOptions::normalize_titles`] is set.
     pub title: String,
+    /// The title as it is in the dump, if normalizing it changed it.
+    #[serde(default)]
+    pub raw_title: Option<String>,
     pub namespace: i32,
     pub id: u32,
+    /// The target of a redirect, which is empty if `<redirect>` has no `title` attribute,
+    /// as in dumps from before version 0.6 of the export schema.
     pub redirect_target: Option<String>,
     pub restrictions: Option<String>,
     pub revisions: Vec<Revision>,
+    /// The `<upload>` elements of a file page, present in dumps made with `--uploads`.
+    #[serde(default)]
+    pub uploads: Vec<Upload>,
+    /// The text of the elements in the `<page>` named in [`ParseOptions::extra_elements`], by name.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+    /// A `<sha1>` after the revisions of the page, which some dumps have.
+    /// Unlike [`Revision::sha1`], it is not checked by [`ParseOptions::verify_sha1`].
+    #[serde(default)]
+    pub sha1: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// What to do with a page after its [`PageHeader`] has been read,
+/// returned by the `on_page_header` callback of [`parse_with_page_filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageAction {
+    /// Parse the revisions of the page and pass it to the page processor.
+    Keep,
+    /// Skip to the end of the page without parsing its revisions.
+    Skip,
+}
+
+/// The elements of a `<page>` before its revisions, passed to the revision processor
+/// of [`parse_revisions`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageHeader {
+    pub title: String,
+    pub namespace: i32,
+    pub id: u32,
+    pub redirect_target: Option<String>,
+    pub restrictions: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Revision {
     pub id: u32,
     pub parent_id: Option<u32>,
     pub timestamp: DateTime<Utc>,
     pub contributor: Contributor,
+    /// 0 in dumps from before `<origin>` was added in version 0.11 of the export schema.
     pub origin: u32,
     pub minor: bool,
     pub comment: Comment,
-    pub model: String,  // Could be converted to integer using hashmap.
-    pub format: String, // Could be converted to integer using hashmap.
-    pub text: String,
-    pub sha1: String,
+    /// `wikitext` in dumps from before `<model>` was added in version 0.8 of the export schema.
+    pub model: Interned,
+    /// `text/x-wiki` in dumps from before `<format>` was added in version 0.8 of the export schema.
+    pub format: Interned,
+    /// Shared with the other revisions of the page that have the same text
+    /// if [`ParseOptions::text_cache_capacity`] is set.
+    pub text: Arc<str>,
+    /// The length of the text in bytes according to the `bytes` attribute of `<text>`,
+    /// which is present even in stub dumps that leave out the text.
+    #[serde(default)]
+    pub text_bytes: Option<u64>,
+    /// Whether the text was deleted or suppressed (`<text deleted="deleted" />`),
+    /// as opposed to being empty.
+    #[serde(default)]
+    pub text_deleted: bool,
+    /// Set by [`Revision::truncate_text`]; the parser itself never truncates.
+    #[serde(default)]
+    pub text_truncated: bool,
+    /// The length of the text minus that of the revision before it in the dump,
+    /// set if [`ParseOptions::size_deltas`] is set. `None` for the first revision of a page
+    /// and if the text of either revision was deleted.
+    #[serde(default)]
+    pub size_delta: Option<i64>,
+    /// A summary of the JSON of the Wikibase entity in the text,
+    /// set if [`ParseOptions::wikibase_entities`] is set and the model is a Wikibase model.
+    #[serde(default)]
+    pub entity: Option<EntitySummary>,
+    /// The `<discussionthreadinginfo>` of a LiquidThreads thread page.
+    #[serde(default)]
+    pub threading_info: Option<ThreadingInfo>,
+    /// `None` if `<sha1>` is missing, as it is in dumps from before version 0.6 of the export schema,
+    /// or empty (`<sha1/>` or `<sha1></sha1>`), as it sometimes is for deleted text.
+    #[serde(default)]
+    pub sha1: Option<String>,
+    /// The text of the elements in the `<revision>` named in [`ParseOptions::extra_elements`], by name.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(untagged)]
+impl Page {
+    /// The protection levels in [`restrictions`](Self::restrictions), if there are any.
+    pub fn protection_levels(&self) -> Option<ProtectionLevels> {
+        self.restrictions
+            .as_deref()
+            .map(ProtectionLevels::parse)
+            .filter(|levels| !levels.is_empty())
+    }
+
+    /// Calls [`Revision::truncate_text`] on every revision.
+    pub fn truncate_text(&mut self, max_bytes: usize) {
+        for revision in &mut self.revisions {
+            revision.truncate_text(max_bytes);
+        }
+    }
+}
+
+impl Revision {
+    /// Shortens `text` to at most `max_bytes` bytes, cutting at a character boundary,
+    /// and sets `text_truncated` if anything was removed.
+    pub fn truncate_text(&mut self, max_bytes: usize) {
+        if self.text.len() > max_bytes {
+            let mut end = max_bytes;
+            while !self.text.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.text = self.text[..end].into();
+            self.text_truncated = true;
+        }
+    }
+
+    /// The length of the text in bytes from the `bytes` attribute of `<text>` if it is present,
+    /// which is right even if the text was skipped, kept raw or truncated,
+    /// or else the length of `text`.
+    pub fn text_len(&self) -> u64 {
+        self.text_bytes.unwrap_or(self.text.len() as u64)
+    }
+
+    /// The SHA-1 of `text` in the form used in `<sha1>`:
+    /// base 36, padded with zeros to 31 digits.
+    pub fn text_sha1(&self) -> String {
+        hex_to_sha1_base36(&sha1_smol::Sha1::from(self.text.as_bytes()).digest().bytes())
+    }
+
+    /// Parses the text as the JSON of a Wikibase entity if [`model`](Self::model) is a Wikibase model
+    /// and the text was not deleted.
+    pub fn entity_summary(&self) -> Option<Result<EntitySummary, serde_json::Error>> {
+        (is_wikibase_model(&self.model) && !self.text_deleted)
+            .then(|| EntitySummary::from_json(&self.text))
+    }
+
+    /// Compares [`Revision::text_sha1`] to `sha1`.
+    /// Revisions whose text was deleted or that have no SHA-1 are not checked.
+    pub fn verify_sha1<E: std::error::Error>(&self) -> Result<(), Error<E>> {
+        let Some(expected) = self.sha1.as_ref().filter(|_| !self.text_deleted) else {
+            return Ok(());
+        };
+        let actual = self.text_sha1();
+        if actual == *expected {
+            Ok(())
+        } else {
+            Err(Error::Sha1Mismatch {
+                revision_id: self.id,
+                expected: expected.clone(),
+                actual,
+            })
+        }
+    }
+}
+
+/// The edit summary of a revision, upload or log item.
+///
+/// It is serialized with a `type` field that names the variant, and a `text` field for a visible comment:
+/// `{"type":"visible","text":"typo"}`, `{"type":"absent"}` or `{"type":"deleted"}` in JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "CommentRepr", into = "CommentRepr")]
+#[non_exhaustive]
 pub enum Comment {
-    DeletedOrAbsent(bool),
+    /// The comment was deleted or suppressed (`<comment deleted="deleted" />`).
+    Deleted,
+    /// There is no `<comment>`, which is how MediaWiki writes an empty edit summary.
+    Absent,
+    /// The text of `<comment>`, which is empty for `<comment />`.
     Visible(String),
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-// #[serde(untagged)]
+/// The serialized form of [`Comment`]. An internally tagged enum cannot have a newtype variant
+/// that holds a string, and adjacently tagged enums cannot be deserialized from MessagePack.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CommentRepr {
+    Deleted,
+    Absent,
+    Visible { text: String },
+}
+
+impl From<CommentRepr> for Comment {
+    fn from(comment: CommentRepr) -> Self {
+        match comment {
+            CommentRepr::Deleted => Comment::Deleted,
+            CommentRepr::Absent => Comment::Absent,
+            CommentRepr::Visible { text } => Comment::Visible(text),
+        }
+    }
+}
+
+impl From<Comment> for CommentRepr {
+    fn from(comment: Comment) -> Self {
+        match comment {
+            Comment::Deleted => CommentRepr::Deleted,
+            Comment::Absent => CommentRepr::Absent,
+            Comment::Visible(text) => CommentRepr::Visible { text },
+        }
+    }
+}
+
+impl Comment {
+    pub fn as_visible(&self) -> Option<&str> {
+        match self {
+            Comment::Visible(comment) => Some(comment),
+            _ => None,
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        matches!(self, Comment::Deleted)
+    }
+
+    pub fn is_absent(&self) -> bool {
+        matches!(self, Comment::Absent)
+    }
+}
+
+/// The contributor of a revision or log item.
+///
+/// It is serialized with a `type` field that names the variant, followed by the fields of the variant:
+/// `{"type":"user","username":"Wonderfool","id":1}`, `{"type":"ip","ip":"127.0.0.1"}`
+/// or `{"type":"deleted"}` in JSON, and the corresponding maps in CBOR and MessagePack.
+/// The IP address is a string in every format.
+/// Deserializing this representation requires a self-describing format, so Bincode can only write it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum Contributor {
     Deleted,
-    Ip { ip: IpAddr },
-    User { username: String, id: u32 },
+    Ip {
+        #[serde(with = "ip_string")]
+        ip: IpAddr,
+    },
+    User {
+        username: String,
+        id: u32,
+    },
+}
+
+/// Serializes an IP address as a string even in formats that are not human-readable,
+/// where serde would write it as bytes, because an internally tagged enum
+/// is deserialized from a buffer that only accepts the string form.
+mod ip_string {
+    use std::net::IpAddr;
+
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ip: &IpAddr, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(ip)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<IpAddr, D::Error> {
+        let ip = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        ip.parse().map_err(D::Error::custom)
+    }
+}
+
+impl Contributor {
+    pub fn is_deleted(&self) -> bool {
+        matches!(self, Contributor::Deleted)
+    }
+
+    pub fn as_ip(&self) -> Option<IpAddr> {
+        match self {
+            Contributor::Ip { ip } => Some(*ip),
+            _ => None,
+        }
+    }
+
+    /// The username and user id of a registered user.
+    pub fn as_user(&self) -> Option<(&str, u32)> {
+        match self {
+            Contributor::User { username, id } => Some((username, *id)),
+            _ => None,
+        }
+    }
 }
 
 #[test]
@@ -210,49 +682,156 @@ fn test_contributor_deserialize() {
     });
 }
 
+#[test]
+fn test_contributor_serialize() {
+    assert_eq!(
+        serde_json::to_string(&Contributor::Deleted).unwrap(),
+        r#"{"type":"deleted"}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Contributor::User {
+            username: "Wonderfool".into(),
+            id: 1,
+        })
+        .unwrap(),
+        r#"{"type":"user","username":"Wonderfool","id":1}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Contributor::Ip {
+            ip: std::net::IpAddr::from([127, 0, 0, 1]),
+        })
+        .unwrap(),
+        r#"{"type":"ip","ip":"127.0.0.1"}"#
+    );
+}
+
+#[test]
+fn test_comment_serialize() {
+    let comments = [
+        (Comment::Deleted, r#"{"type":"deleted"}"#),
+        (Comment::Absent, r#"{"type":"absent"}"#),
+        (
+            Comment::Visible("".into()),
+            r#"{"type":"visible","text":""}"#,
+        ),
+        (
+            Comment::Visible("typo".into()),
+            r#"{"type":"visible","text":"typo"}"#,
+        ),
+    ];
+    for (comment, json) in comments {
+        assert_eq!(serde_json::to_string(&comment).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Comment>(json).unwrap(), comment);
+        let cbor = serde_cbor::to_vec(&comment).unwrap();
+        assert_eq!(serde_cbor::from_slice::<Comment>(&cbor).unwrap(), comment);
+        let msgpack = rmp_serde::to_vec(&comment).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Comment>(&msgpack).unwrap(), comment);
+    }
+}
+
+/// Reads the next event, reporting the end of the input as [`Error::UnexpectedEof`]
+/// because every caller is in the middle of an element.
+/// Comments and processing instructions are skipped.
+fn read_event<'b, R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &'b mut Vec<u8>,
+) -> Result<Event<'b>, Error<E>> {
+    read_event_skipping(reader, buf, false)
+}
+
+/// Like [`read_event`], but for an event that should be a tag:
+/// whitespace after a comment or processing instruction is skipped as well,
+/// because the whitespace before it has already been skipped by [`skip_text`].
+fn read_tag_event<'b, R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &'b mut Vec<u8>,
+) -> Result<Event<'b>, Error<E>> {
+    read_event_skipping(reader, buf, true)
+}
+
+fn read_event_skipping<'b, R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &'b mut Vec<u8>,
+    skip_whitespace: bool,
+) -> Result<Event<'b>, Error<E>> {
+    let eof = |reader: &Reader<R>| Error::UnexpectedEof {
+        position: reader.buffer_position(),
+    };
+    match reader.read_event_into(buf) {
+        Ok(Event::Comment(_) | Event::PI(_)) => {}
+        Ok(Event::Eof) | Err(quick_xml::Error::UnexpectedEof(_)) => return Err(eof(reader)),
+        Ok(event) => return Ok(event),
+        Err(_) => return Err(Error::format(reader, "invalid XML syntax")),
+    }
+    // `buf` stays borrowed by the event returned above, so the events after a comment,
+    // which are rare, are read into another buffer and copied.
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Comment(_) | Event::PI(_)) => {}
+            Ok(Event::Text(text))
+                if skip_whitespace && text.iter().all(u8::is_ascii_whitespace) => {}
+            Ok(Event::Eof) | Err(quick_xml::Error::UnexpectedEof(_)) => return Err(eof(reader)),
+            Ok(event) => return Ok(event.into_owned()),
+            Err(_) => return Err(Error::format(reader, "invalid XML syntax")),
+        }
+    }
+}
+
 fn get_start_tag<R: BufRead, E: std::error::Error>(
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
 ) -> Result<(Tag, bool), Error<E>> {
-    match reader.read_event_into(buf) {
-        Ok(Event::Start(start)) => Ok((
+    match read_tag_event(reader, buf)? {
+        Event::Start(start) => Ok((
             Tag::try_from(start.name()).map_err(Error::from_infallible)?,
             false,
         )),
-        Ok(Event::Empty(start)) => Ok((
+        Event::Empty(start) => Ok((
             Tag::try_from(start.name()).map_err(Error::from_infallible)?,
             true,
         )),
-        _ => Err(Error::format(reader)),
+        _ => Err(Error::format(reader, "expected a start tag")),
     }
 }
 
+/// Like [`get_start_tag`], but also returns the keys and unescaped values of the attributes.
 #[allow(clippy::type_complexity)]
-fn get_start_tag_and_attribute<R: BufRead, E: std::error::Error>(
+/// Elements listed in `extra` are read into it and skipped.
+fn get_start_tag_and_attributes<R: BufRead, E: std::error::Error>(
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
-) -> Result<(Tag, Option<(Vec<u8>, String)>, bool), Error<E>> {
-    let event = reader.read_event_into(buf);
+    extra: &mut Extra,
+) -> Result<(Tag, Vec<(Vec<u8>, String)>, bool), Error<E>> {
+    let mut event = read_tag_event(reader, buf)?;
+    while let Some((name, is_empty)) = extra.listed(&event) {
+        drop(event);
+        extra.read(reader, buf, name, is_empty)?;
+        skip_text(reader, buf)?;
+        event = read_tag_event(reader, buf)?;
+    }
     let (tag, is_empty) = match &event {
-        Ok(Event::Start(start)) => (start, false),
-        Ok(Event::Empty(start)) => (start, true),
-        _ => return Err(Error::format(reader)),
+        Event::Start(start) => (start, false),
+        Event::Empty(start) => (start, true),
+        _ => return Err(Error::format(reader, "expected a start tag")),
     };
-    let key_value = tag
+    let attributes = tag
         .attributes()
-        .next()
         .map(|attr_result| {
-            let attr = attr_result.map_err(|_| Error::format(reader))?;
-            if let Ok(value) = std::str::from_utf8(&attr.value).map(String::from) {
-                Ok((attr.key.as_ref().to_vec(), value))
-            } else {
-                Err(Error::format(reader))
-            }
+            let attr = attr_result.map_err(|_| Error::format(reader, "invalid attribute"))?;
+            let value = attr
+                .unescape_value()
+                .map_err(|_| Error::FailedToDecode {
+                    position: reader.buffer_position(),
+                })?
+                .into_owned();
+            Ok((attr.key.as_ref().to_vec(), value))
         })
-        .transpose()?;
+        .collect::<Result<_, _>>()?;
     Ok((
         Tag::try_from(tag.name()).map_err(Error::from_infallible)?,
-        key_value,
+        attributes,
         is_empty,
     ))
 }
@@ -263,7 +842,7 @@ fn expect_tag_start<R: BufRead, E: std::error::Error>(
     expected_tag: Tag,
 ) -> Result<(), Error<E>> {
     let (Event::Start(start) | Event::Empty(start)) = event else {
-        return Err(Error::format(reader));
+        return Err(Error::format(reader, "expected a start tag"));
     };
     let tag = Tag::try_from(start.name()).map_err(Error::from_infallible)?;
     if tag == expected_tag {
@@ -273,15 +852,48 @@ fn expect_tag_start<R: BufRead, E: std::error::Error>(
     }
 }
 
-fn expect_tag_start_from_reader<'a, 'b, R: BufRead, E: std::error::Error>(
-    reader: &'a mut Reader<R>,
+fn expect_tag_start_from_reader<'b, R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
     buf: &'b mut Vec<u8>,
     expected_tag: Tag,
 ) -> Result<Event<'b>, Error<E>> {
-    let event = reader
-        .read_event_into(buf)
-        .map_err(|_| Error::format(reader))?;
-    expect_tag_start(&reader, &event, expected_tag).map(|_| event)
+    let event = read_tag_event(reader, buf)?;
+    expect_tag_start(reader, &event, expected_tag).map(|_| event)
+}
+
+/// Skips to the end tag named `name`, after its start tag has been read.
+fn skip_element<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    name: QName,
+) -> Result<(), Error<E>> {
+    match reader.read_to_end_into(name, buf) {
+        Ok(_) => Ok(()),
+        Err(quick_xml::Error::UnexpectedEof(_)) => Err(Error::UnexpectedEof {
+            position: reader.buffer_position(),
+        }),
+        Err(_) => Err(Error::format(
+            reader,
+            "invalid XML while skipping an element",
+        )),
+    }
+}
+
+/// Skips to the end of the element whose start tag has just been read, whatever its name.
+fn skip_open_element<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error<E>> {
+    let mut depth = 1;
+    while depth > 0 {
+        buf.clear();
+        match read_event(reader, buf)? {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    Ok(())
 }
 
 fn expect_tag_end<R: BufRead, E: std::error::Error>(
@@ -289,11 +901,8 @@ fn expect_tag_end<R: BufRead, E: std::error::Error>(
     buf: &mut Vec<u8>,
     expected_tag: Tag,
 ) -> Result<(), Error<E>> {
-    let Event::End(end) = reader
-        .read_event_into(buf)
-        .map_err(|_| Error::format(reader))?
-    else {
-        return Err(Error::format(reader));
+    let Event::End(end) = read_tag_event(reader, buf)? else {
+        return Err(Error::format(reader, "expected an end tag"));
     };
     let tag = Tag::try_from(end.name()).map_err(Error::from_infallible)?;
     if tag == expected_tag {
@@ -307,16 +916,20 @@ pub fn skip_text<R: BufRead, E: std::error::Error>(
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
 ) -> Result<(), Error<E>> {
-    let text = reader
-        .read_event_into(buf)
-        .map_err(|_| Error::format(reader))?;
+    let text = read_event(reader, buf)?;
     if matches!(text, Event::Text(_)) {
         Ok(())
     } else {
-        Err(Error::format(reader))
+        Err(Error::format(
+            reader,
+            "expected whitespace between elements",
+        ))
     }
 }
 
+/// Calls `f` on the text of an element whose start tag has been read,
+/// then reads the end tag.
+/// The text may be made of character data and CDATA sections, which are not unescaped.
 fn map_unescaped_text<
     R: BufRead,
     T,
@@ -328,32 +941,69 @@ fn map_unescaped_text<
     expected_tag: Tag,
     mut f: F,
 ) -> Result<T, Error<E>> {
-    match reader
-        .read_event_into(buf)
-        .map_err(|_| Error::format(reader))?
-    {
+    let text = match read_event(reader, buf)? {
         Event::Text(text) => {
             let text = text.unescape().map_err(|_| Error::FailedToDecode {
                 position: reader.buffer_position(),
             })?;
-            let res = f(text);
-            let Event::End(end) = reader
-                .read_event_into(buf)
-                .map_err(|_| Error::format(reader))?
-            else {
-                return Err(Error::format(reader));
-            };
-            let tag = Tag::try_from(end.name()).map_err(Error::from_infallible)?;
-            if tag == expected_tag {
-                res
-            } else {
-                Err(Error::tag(reader, expected_tag, tag))
+            // After a text event, the `<` of the next tag has been read,
+            // so a `/` means that the text can be passed to `f` without copying it.
+            if reader
+                .get_mut()
+                .fill_buf()
+                .is_ok_and(|rest| rest.starts_with(b"/"))
+            {
+                let res = f(text);
+                expect_tag_end(reader, buf, expected_tag)?;
+                return res;
+            }
+            text.into_owned()
+        }
+        Event::CData(cdata) => decode_cdata(reader, &cdata)?.to_owned(),
+        _ => return Err(Error::format(reader, "expected text")),
+    };
+    let text = read_remaining_text(reader, buf, text, expected_tag)?;
+    f(Cow::Owned(text))
+}
+
+/// Appends the character data and CDATA sections up to the end tag to `text`.
+fn read_remaining_text<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    mut text: String,
+    expected_tag: Tag,
+) -> Result<String, Error<E>> {
+    loop {
+        match read_event(reader, buf)? {
+            Event::Text(more) => {
+                text.push_str(&more.unescape().map_err(|_| Error::FailedToDecode {
+                    position: reader.buffer_position(),
+                })?)
             }
+            Event::CData(cdata) => text.push_str(decode_cdata(reader, &cdata)?),
+            Event::End(end) => {
+                let tag = Tag::try_from(end.name()).map_err(Error::from_infallible)?;
+                return if tag == expected_tag {
+                    Ok(text)
+                } else {
+                    Err(Error::tag(reader, expected_tag, tag))
+                };
+            }
+            _ => return Err(Error::format(reader, "expected text or an end tag")),
         }
-        _ => Err(Error::format(reader)),
     }
 }
 
+/// The contents of a CDATA section, which are literal text.
+fn decode_cdata<'c, R: BufRead, E: std::error::Error>(
+    reader: &Reader<R>,
+    cdata: &'c BytesCData,
+) -> Result<&'c str, Error<E>> {
+    std::str::from_utf8(cdata).map_err(|_| Error::FailedToDecode {
+        position: reader.buffer_position(),
+    })
+}
+
 fn parse_text<R: BufRead, T: FromStr, E: std::error::Error>(
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
@@ -361,387 +1011,3108 @@ fn parse_text<R: BufRead, T: FromStr, E: std::error::Error>(
 ) -> Result<T, Error<E>> {
     let position = reader.buffer_position();
     map_unescaped_text(reader, buf, tag, |text| {
-        text.as_ref()
-            .parse()
-            .map_err(|_| Error::Format { position })
+        text.as_ref().parse().map_err(|_| {
+            let value = text.into_owned();
+            if tag == Tag::Timestamp {
+                Error::Timestamp { value, position }
+            } else {
+                Error::Value {
+                    tag,
+                    value,
+                    position,
+                }
+            }
+        })
     })
 }
 
+/// Parses the text of a `<timestamp>`, in RFC 3339 or the 14-digit MediaWiki format.
+fn parse_timestamp<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<DateTime<Utc>, Error<E>> {
+    parse_text(reader, buf, Tag::Timestamp).map(|DumpTimestamp(timestamp)| timestamp)
+}
+
+/// Like [`read_text`], but leaves entities such as `&lt;` as they are in the XML
+/// and keeps the `<![CDATA[` and `]]>` around CDATA sections.
+fn read_raw_text<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    expected_tag: Tag,
+) -> Result<String, Error<E>> {
+    let mut text = String::new();
+    loop {
+        match read_event(reader, buf)? {
+            Event::Text(raw) => {
+                text.push_str(
+                    std::str::from_utf8(&raw).map_err(|_| Error::FailedToDecode {
+                        position: reader.buffer_position(),
+                    })?,
+                )
+            }
+            Event::CData(cdata) => {
+                text.push_str("<![CDATA[");
+                text.push_str(decode_cdata(reader, &cdata)?);
+                text.push_str("]]>");
+            }
+            Event::End(end) => {
+                let tag = Tag::try_from(end.name()).map_err(Error::from_infallible)?;
+                return if tag == expected_tag {
+                    Ok(text)
+                } else {
+                    Err(Error::tag(reader, expected_tag, tag))
+                };
+            }
+            _ => return Err(Error::format(reader, "expected text or an end tag")),
+        }
+    }
+}
+
 fn read_text<R: BufRead, E: std::error::Error>(
     reader: &mut Reader<R>,
     buf: &mut Vec<u8>,
     tag: Tag,
 ) -> Result<String, Error<E>> {
-    let text = if let Event::Text(t) = reader
-        .read_event_into(buf)
-        .map_err(|_| Error::format(reader))?
-    {
-        t.unescape().map_err(|_| Error::format(reader))?.into()
-    } else {
-        return Err(Error::format(reader));
-    };
-    if let Event::End(name) = reader
-        .read_event_into(buf)
-        .map_err(|_| Error::format(reader))?
-    {
-        if name.name() == tag.as_q_name() {
-            Ok(text)
-        } else {
-            Err(Error::format(reader))
+    map_unescaped_text(reader, buf, tag, |text| Ok(text.into_owned()))
+}
+
+/// Like [`read_text`], but returns an empty string for an element written with
+/// a start tag and an end tag and nothing between them, such as `<sha1></sha1>`.
+fn read_possibly_empty_text<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    tag: Tag,
+) -> Result<String, Error<E>> {
+    let text = match read_event(reader, buf)? {
+        Event::Text(text) => text
+            .unescape()
+            .map_err(|_| Error::FailedToDecode {
+                position: reader.buffer_position(),
+            })?
+            .into_owned(),
+        Event::CData(cdata) => decode_cdata(reader, &cdata)?.to_owned(),
+        Event::End(end) => {
+            let actual = Tag::try_from(end.name()).map_err(Error::from_infallible)?;
+            return if actual == tag {
+                Ok(String::new())
+            } else {
+                Err(Error::tag(reader, tag, actual))
+            };
         }
+        _ => return Err(Error::format(reader, "expected text")),
+    };
+    read_remaining_text(reader, buf, text, tag)
+}
+
+/// Reads the elements of a non-empty `<contributor>` up to and including the end tag.
+fn read_contributor_contents<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<Contributor, Error<E>> {
+    skip_text(reader, buf)?;
+
+    let (tag, _) = get_start_tag(reader, buf)?;
+    let contributor = if tag == Tag::Username {
+        let username = read_text(reader, buf, Tag::Username)?;
+        skip_text(reader, buf)?;
+
+        expect_tag_start_from_reader(reader, buf, Tag::Id)?;
+        let id: u32 = parse_text(reader, buf, Tag::Id)?;
+        skip_text(reader, buf)?;
+        Contributor::User { username, id }
+    } else if tag == Tag::Ip {
+        let ip = parse_text(reader, buf, Tag::Ip)?;
+        skip_text(reader, buf)?;
+        Contributor::Ip { ip }
     } else {
-        Err(Error::format(reader))
-    }
+        return Err(Error::format(
+            reader,
+            "expected <username> or <ip> in <contributor>",
+        ));
+    };
+
+    expect_tag_end(reader, buf, Tag::Contributor)?;
+
+    Ok(contributor)
 }
 
-// Search for <page> containing <title> with given title.
-pub fn find_page(title_to_find: &str, xml: &[u8]) -> Result<Option<Page>, Error<Infallible>> {
+/// The `<title>` element of a page with the given title, as it appears in a dump.
+fn title_tag(title: &str) -> String {
     // quick_xml::escape::escape can't be used because it escapes ' to &apos;,
     // but the MediaWiki dump has literal apostrophes.
-    let title_tag = format!(
+    format!(
         "<title>{}</title>",
-        title_to_find
+        title
             .replace('&', "&amp;")
             .replace('"', "&quot;")
             .replace('<', "&lt;")
-            .replace('>', "&gt")
-    );
+            .replace('>', "&gt;")
+    )
+}
+
+/// Search for <page> containing <title> with given title.
+///
+/// `xml` may be any part of a dump, such as a window of a memory-mapped file,
+/// but the page is only found if `xml` contains all of it:
+/// if the `<page>` start tag before the title is not in `xml`, this returns [`Error::Format`],
+/// and if the page is cut off at the end, [`Error::UnexpectedEof`].
+/// A page whose `<title>` straddles the start or end of `xml` is not found.
+/// Use [`find_page_in_reader`] to search a whole dump without holding it in memory.
+pub fn find_page(title_to_find: &str, xml: &[u8]) -> Result<Option<Page>, Error<Infallible>> {
+    let title_tag = title_tag(title_to_find);
     if let Some(title_tag_index) = memmem::find(xml, title_tag.as_ref()) {
-        if let Some(page_tag_index) = memmem::rfind(&xml[..title_tag_index], b"<page>") {
-            let mut found_page = None;
-            parse(
-                &xml[page_tag_index..],
-                |page| {
-                    if page.title == title_to_find {
-                        found_page = Some(page);
-                    }
-                    Err(Error::ShortCircuit)
-                },
-                false,
-            )?;
-            return Ok(found_page);
-        }
+        let page_tag_index =
+            memmem::rfind(&xml[..title_tag_index], b"<page>").ok_or(Error::Format {
+                position: title_tag_index,
+                context: "no <page> start tag before the <title>",
+            })?;
+        return parse_found_page(title_to_find, &xml[page_tag_index..])
+            .map_err(|e| split::with_offset(e, page_tag_index));
     }
     Ok(None)
 }
 
-pub fn parse<R: BufRead, F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
-    reader: R,
-    mut page_processor: F,
-    skip_header: bool,
-) -> Result<(), Error<E>> {
-    // Bigger than maximum revision length (2 MiB).
-    let mut buf = Vec::with_capacity(3 * 1024 * 1024);
-    let mut reader = Reader::from_reader(reader);
+/// Like [`find_page`], but searches a stream, so that the whole dump doesn't have to be in memory.
+/// The input is searched one buffer of `reader` at a time.
+/// The end of each buffer is kept when the next one is read, so that a `<title>` that straddles
+/// the two is still found, along with the `<page>` start tag before it.
+pub fn find_page_in_reader<R: BufRead>(
+    title_to_find: &str,
+    mut reader: R,
+) -> Result<Option<Page>, Error<Infallible>> {
+    let title_tag = title_tag(title_to_find);
+    let title_finder = memmem::Finder::new(title_tag.as_bytes());
+    let overlap = title_tag.len() - 1;
+    let mut window = Vec::new();
+    // The position of the start of `window` in the input.
+    let mut position = 0;
+    loop {
+        let chunk = reader.fill_buf().map_err(|_| Error::UnexpectedEof {
+            position: position + window.len(),
+        })?;
+        if chunk.is_empty() {
+            return Ok(None);
+        }
+        let search_start = window.len().saturating_sub(overlap);
+        window.extend_from_slice(chunk);
+        let chunk_len = chunk.len();
+        reader.consume(chunk_len);
 
-    // skip_text(&mut reader, &mut buf)?;
-    // Skip over initial mediawiki tag.
-    if skip_header {
-        expect_tag_start_from_reader(&mut reader, &mut buf, Tag::MediaWiki)?;
-        skip_text(&mut reader, &mut buf)?;
-        expect_tag_start_from_reader(&mut reader, &mut buf, Tag::SiteInfo)?;
-        reader
-            .read_to_end_into(QName(b"siteinfo"), &mut buf)
-            .map_err(|_| Error::format(&reader))?;
-        skip_text(&mut reader, &mut buf)?;
+        if let Some(title_tag_index) = title_finder.find(&window[search_start..]) {
+            let title_tag_index = search_start + title_tag_index;
+            let page_tag_index =
+                memmem::rfind(&window[..title_tag_index], b"<page>").ok_or(Error::Format {
+                    position: position + title_tag_index,
+                    context: "no <page> start tag before the <title>",
+                })?;
+            return parse_found_page(title_to_find, (&window[page_tag_index..]).chain(reader))
+                .map_err(|e| split::with_offset(e, position + page_tag_index));
+        }
+
+        // Keep the start of the last page if its title has not ended,
+        // and otherwise only as much as could be the start of the title tag.
+        let keep_from = match memmem::rfind(&window, b"<page>") {
+            Some(page_tag_index)
+                if memmem::find(&window[page_tag_index..], b"</title>").is_none() =>
+            {
+                page_tag_index
+            }
+            _ => window.len().saturating_sub(overlap),
+        };
+        window.drain(..keep_from);
+        position += keep_from;
     }
-    buf.clear();
+}
+
+/// Finds a page in the dump at `path` without parsing the pages before it.
+///
+/// With the `bz2` feature, if the dump is a multistream `.xml.bz2` file with its index next to it
+/// (see [`find_page_multistream`]), only the stream that contains the page is decompressed.
+/// With the `memmap` feature, an uncompressed dump is mapped into memory and searched
+/// with [`find_page`]. Otherwise the dump is decompressed and searched
+/// with [`find_page_in_reader`].
+pub fn find_page_from_file<P: AsRef<Path>>(
+    path: P,
+    title: &str,
+) -> Result<Option<Page>, Error<Infallible>> {
+    let path = path.as_ref();
+    #[cfg(feature = "bz2")]
+    if let Some(index) = multistream::sibling_index(path) {
+        return find_page_multistream(path, index, title);
+    }
+    #[cfg(feature = "memmap")]
+    if let Some(xml) = map_file(path)? {
+        return find_page(title, &xml);
+    }
+    find_page_in_reader(title, open_file(path, None)?)
+}
+
+/// Parses the page at the start of `xml`, for [`find_page`] and [`find_page_in_reader`].
+fn parse_found_page<R: BufRead>(
+    title_to_find: &str,
+    xml: R,
+) -> Result<Option<Page>, Error<Infallible>> {
+    let mut found_page = None;
+    parse(
+        xml,
+        |page| {
+            if page.title == title_to_find {
+                found_page = Some(page);
+            }
+            Err(Error::ShortCircuit)
+        },
+        false,
+    )?;
+    Ok(found_page)
+}
+
+/// Finds the pages with any of the given titles in one pass through `xml`,
+/// stopping as soon as all of them have been found.
+/// The revisions of the other pages are not parsed.
+///
+/// The titles are compared after entities such as `&amp;` are unescaped,
+/// so they are given as in [`Page::title`].
+/// A redirect is returned under its own title, not that of its target.
+/// Titles that are not found are left out of the map.
+pub fn find_pages(
+    titles: &HashSet<String>,
+    xml: &[u8],
+) -> Result<HashMap<String, Page>, Error<Infallible>> {
+    let mut found = HashMap::with_capacity(titles.len());
+    if titles.is_empty() {
+        return Ok(found);
+    }
+    let pattern = titles
+        .iter()
+        .map(|title| regex::escape(title))
+        .collect::<Vec<_>>()
+        .join("|");
+    let options = ParseOptions {
+        // If there are too many titles for a regex, every page is parsed.
+        title: regex::Regex::new(&format!("^(?:{})$", pattern)).ok(),
+        ..ParseOptions::default()
+    };
+    parse_with_options(
+        xml,
+        &options,
+        |page| {
+            if titles.contains(&page.title) {
+                found.insert(page.title.clone(), page);
+            }
+            if found.len() == titles.len() {
+                Err(Error::ShortCircuit)
+            } else {
+                Ok(())
+            }
+        },
+        true,
+    )?;
+    Ok(found)
+}
+
+/// Parses only the pages in the main namespace of a complete, uncompressed dump.
+/// Because `<` is always escaped in text, `<ns>0</ns>` only occurs as an element,
+/// so `memmem` can jump from one main-namespace page to the next
+/// without parsing the pages in between.
+///
+/// CDATA sections and XML comments can contain `<ns>0</ns>` literally,
+/// so if either occurs in `xml`, this falls back to parsing every page.
+pub fn parse_articles<F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
+    xml: &[u8],
+    mut page_processor: F,
+) -> Result<(), Error<E>> {
+    if memmem::find(xml, b"<![CDATA[").is_some() || memmem::find(xml, b"<!--").is_some() {
+        return parse_filtered(xml, &[0], page_processor, true);
+    }
+
+    let ns_tag = b"<ns>0</ns>";
+    let ns_finder = memmem::Finder::new(ns_tag);
+    let options = ParseOptions::default();
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+    let mut search_start = 0;
+    while let Some(ns_tag_index) = ns_finder.find(&xml[search_start..]) {
+        let ns_tag_index = search_start + ns_tag_index;
+        let page_tag_index = memmem::rfind(&xml[search_start..ns_tag_index], b"<page>")
+            .map(|i| search_start + i)
+            .ok_or(Error::Format {
+                position: ns_tag_index,
+                context: "no <page> start tag before <ns>0</ns>",
+            })?;
+        let mut reader = Reader::from_reader(&xml[page_tag_index..]);
+        let found_page = read_page::<_, Infallible>(&mut reader, &mut buf, &options)
+            .map_err(|e| split::with_offset(Error::from_infallible(e), page_tag_index))?;
+        match found_page.map(&mut page_processor) {
+            Some(Err(Error::ShortCircuit)) => return Ok(()),
+            Some(Err(e)) => return Err(e),
+            Some(Ok(())) => {}
+            None => {
+                return Err(Error::Format {
+                    position: page_tag_index,
+                    context: "expected a page at the <page> start tag",
+                })
+            }
+        }
+        search_start = ns_tag_index + ns_tag.len();
+    }
+    Ok(())
+}
 
-    // let stdout = std::io::stdout();
-    // let mut stdout = stdout.lock();
+/// Reads the `<mediawiki>` start tag, skipping the whitespace and XML declaration
+/// that may come before it. `quick_xml` removes a byte order mark at the start of the input.
+fn read_root_start<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<Event<'static>, Error<E>> {
+    loop {
+        buf.clear();
+        match read_tag_event(reader, buf)? {
+            Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {}
+            Event::Decl(_) => {}
+            event => {
+                let event = event.into_owned();
+                expect_tag_start(reader, &event, Tag::MediaWiki)?;
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/// Skips over the `<mediawiki>` start tag and the `<siteinfo>` element.
+fn read_header<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error<E>> {
+    read_root_start(reader, buf)?;
+    skip_siteinfo(reader, buf)
+}
 
-    let mut restrictions_buffer = Vec::<u8>::new();
+/// Skips over the `<siteinfo>` element after the `<mediawiki>` start tag.
+fn skip_siteinfo<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error<E>> {
+    skip_text(reader, buf)?;
+    expect_tag_start_from_reader(reader, buf, Tag::SiteInfo)?;
+    reader
+        .read_to_end_into(QName(b"siteinfo"), buf)
+        .map_err(|_| Error::format(reader, "invalid XML in <siteinfo>"))?;
+    skip_text(reader, buf)?;
+    buf.clear();
+    Ok(())
+}
 
-    // page elements
+/// After the `</mediawiki>` end tag, skips to the `<siteinfo>` of the next document
+/// for [`ParseOptions::multi_document`], or returns `false` at the end of the input.
+fn read_next_document<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<bool, Error<E>> {
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(start)) if start.name() == QName(b"page") => (),
-            Ok(Event::End(end)) if end.name() == QName(b"mediawiki") => return Ok(()),
-            _ => return Err(Error::format(&reader)),
+        buf.clear();
+        match reader.read_event_into(buf) {
+            Ok(Event::Eof) => return Ok(false),
+            Ok(Event::Text(text)) if text.iter().all(u8::is_ascii_whitespace) => {}
+            Ok(Event::Decl(_) | Event::Comment(_) | Event::PI(_)) => {}
+            Ok(Event::Start(start)) if start.name() == Tag::MediaWiki.as_q_name() => break,
+            Ok(_) => {
+                return Err(Error::format(
+                    reader,
+                    "expected <mediawiki> or the end of the input after </mediawiki>",
+                ))
+            }
+            Err(_) => return Err(Error::format(reader, "invalid XML syntax")),
         }
-        skip_text(&mut reader, &mut buf)?;
+    }
+    skip_siteinfo(reader, buf)?;
+    Ok(true)
+}
+
+/// Like [`read_header`], but parses the attributes of `<mediawiki>` and the `<siteinfo>` element.
+fn read_dump_header<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<DumpHeader, Error<E>> {
+    let event = read_root_start(reader, buf)?;
+    let (Event::Start(start) | Event::Empty(start)) = &event else {
+        return Err(Error::format(reader, "expected the <mediawiki> start tag"));
+    };
+    let (lang, version) = siteinfo::parse_root_attributes(reader, start)?;
+    skip_text(reader, buf)?;
+    expect_tag_start_from_reader(reader, buf, Tag::SiteInfo)?;
+    let siteinfo = siteinfo::parse_siteinfo(reader, buf)?;
+    skip_text(reader, buf)?;
+    buf.clear();
+    Ok(DumpHeader {
+        lang,
+        version,
+        siteinfo,
+    })
+}
 
-        expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Title)?;
-        let title = read_text(&mut reader, &mut buf, Tag::Title)?;
-        skip_text(&mut reader, &mut buf)?;
+/// Reads the next `<page>` element, or returns `None` at the `</mediawiki>` end tag.
+/// Pages that are rejected by `options` are skipped without parsing their revisions.
+fn read_page<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    options: &ParseOptions,
+) -> Result<Option<Page>, Error<E>> {
+    Ok(
+        read_page_with_range(reader, buf, options, &mut |_| PageAction::Keep, &mut |_| {})?
+            .map(|(page, _)| page),
+    )
+}
 
-        expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Ns)?;
-        let namespace: i32 = parse_text(&mut reader, &mut buf, Tag::Ns)?;
-        skip_text(&mut reader, &mut buf)?;
+/// Consumes the UTF-8 byte order mark at the start of `reader`, if there is one, and returns its length.
+/// quick-xml skips it without counting it in [`Reader::buffer_position`],
+/// so this must be added to the positions from [`read_page_with_range`] to get positions in the input.
+fn skip_bom<R: BufRead, E: std::error::Error>(reader: &mut R) -> Result<usize, Error<E>> {
+    const BOM: &[u8] = b"\xEF\xBB\xBF";
+    let len = if reader
+        .fill_buf()
+        .map_err(|e| Error::from_io("read", e, "-"))?
+        .starts_with(BOM)
+    {
+        BOM.len()
+    } else {
+        0
+    };
+    reader.consume(len);
+    Ok(len)
+}
 
-        expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Id)?;
-        let id: u32 = parse_text(&mut reader, &mut buf, Tag::Id)?;
-        skip_text(&mut reader, &mut buf)?;
+/// Like [`read_page`], but also returns the positions of the start of `<page>`
+/// and the end of `</page>` in the input of `reader`.
+/// Pages for which `on_page_header` returns [`PageAction::Skip`] are skipped as well,
+/// after those rejected by `options`, and the range of each skipped page is passed to `on_skipped_page`.
+fn read_page_with_range<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    options: &ParseOptions,
+    on_page_header: &mut impl FnMut(&PageHeader) -> PageAction,
+    on_skipped_page: &mut impl FnMut(Range<usize>),
+) -> Result<Option<(Page, Range<usize>)>, Error<E>> {
+    let mut extra = Extra::new(&options.extra_elements);
+    while let Some((mut header, next_tag, start)) = read_page_header(reader, buf, &mut extra)? {
+        let mut raw_title = None;
+        if let Some(case) = options.normalize_titles {
+            let title = normalize_title(&header.title, header.namespace, case);
+            if title != header.title {
+                raw_title = Some(std::mem::replace(&mut header.title, title));
+            }
+        }
+        if !options.accepts_page(
+            &header.title,
+            header.namespace,
+            header.redirect_target.is_some(),
+        ) || on_page_header(&header) == PageAction::Skip
+        {
+            skip_element(reader, buf, QName(b"page"))?;
+            on_skipped_page(start..reader.buffer_position());
+            skip_text(reader, buf)?;
+            continue;
+        }
 
-        let (tag_start, attribute, is_empty) = get_start_tag_and_attribute(&mut reader, &mut buf)?;
-        let ((tag_start, _), redirect_target) = {
-            if tag_start == Tag::Redirect {
-                if !is_empty {
-                    return Err(Error::format(&reader));
+        let mut revisions: Vec<Revision> = Vec::new();
+        let mut uploads = Vec::new();
+        let mut text_cache = TextCache::new(options.text_cache_capacity);
+        // The length of the previous revision, or `None` if its text was deleted.
+        let mut previous_len: Option<Option<i64>> = None;
+        let (sha1, end) = read_page_revisions(
+            reader,
+            buf,
+            next_tag,
+            &mut uploads,
+            &mut extra,
+            options,
+            |mut revision| {
+                if options.verify_sha1 && !options.skip_text && !options.raw_text {
+                    revision.verify_sha1()?;
+                }
+                if options.wikibase_entities && !options.skip_text && !options.raw_text {
+                    if let Some(entity) = revision.entity_summary() {
+                        revision.entity = Some(entity.map_err(|source| Error::Entity {
+                            revision_id: revision.id,
+                            source,
+                        })?);
+                    }
+                }
+                if options.size_deltas {
+                    let len = (!revision.text_deleted).then(|| revision.text_len() as i64);
+                    revision.size_delta = match (previous_len.replace(len), len) {
+                        (Some(Some(previous)), Some(len)) => Some(len - previous),
+                        _ => None,
+                    };
+                }
+                if !options.accepts_timestamp(revision.timestamp)
+                    || !options.accepts_contributor(&revision.contributor)
+                {
+                    return Ok(());
+                }
+                if options.latest_only {
+                    if revisions
+                        .first()
+                        .is_some_and(|latest| latest.timestamp > revision.timestamp)
+                    {
+                        return Ok(());
+                    }
+                    revisions.clear();
                 }
+                text_cache.share(&mut revision);
+                revisions.push(revision);
+                Ok(())
+            },
+        )?;
+        if options.accepts_revisions(&revisions) {
+            let PageHeader {
+                title,
+                namespace,
+                id,
+                redirect_target,
+                restrictions,
+            } = header;
+            let page = Page {
+                title,
+                raw_title,
+                namespace,
+                id,
+                redirect_target,
+                restrictions,
+                revisions,
+                uploads,
+                extra: std::mem::take(&mut extra.values),
+                sha1,
+            };
+            return Ok(Some((page, start..end)));
+        }
+        on_skipped_page(start..end);
+    }
+    Ok(None)
+}
 
-                if let Some((_, title)) = attribute {
-                    skip_text(&mut reader, &mut buf)?;
-                    (get_start_tag(&mut reader, &mut buf)?, Some(title))
-                } else {
-                    return Err(Error::format(&reader));
+/// The contexts of the errors for something other than `<page>` or `</mediawiki>` between pages,
+/// after which no page is open, so [`parse_resilient`] must not skip to the end of one.
+/// After [`STRAY_START_TAG`], the element that was started is still open.
+const STRAY_START_TAG: &str = "expected <page> or </mediawiki>, got the start of another element";
+const STRAY_EVENT: &str = "expected <page> or </mediawiki>";
+
+/// Reads a `<page>` up to its first `<revision>` or `<upload>`,
+/// or returns `None` at the `</mediawiki>` end tag.
+/// Also returns the tag of the first revision or upload if its start tag has been read,
+/// and the position of the `<` of `<page>`.
+/// `extra` is cleared and the elements listed in it after `<id>` are read into it.
+#[allow(clippy::type_complexity)]
+fn read_page_header<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    extra: &mut Extra,
+) -> Result<Option<(PageHeader, Option<Tag>, usize)>, Error<E>> {
+    extra.values.clear();
+    buf.clear();
+    // The event holds the tag without its angle brackets.
+    let start = match read_tag_event(reader, buf)? {
+        Event::Start(start) if start.name() == QName(b"page") => {
+            reader.buffer_position() - start.len() - 2
+        }
+        Event::End(end) if end.name() == QName(b"mediawiki") => return Ok(None),
+        Event::Start(_) => return Err(Error::format(reader, STRAY_START_TAG)),
+        _ => return Err(Error::format(reader, STRAY_EVENT)),
+    };
+    skip_text(reader, buf)?;
+
+    expect_tag_start_from_reader(reader, buf, Tag::Title)?;
+    let title = read_text(reader, buf, Tag::Title)?;
+    skip_text(reader, buf)?;
+
+    expect_tag_start_from_reader(reader, buf, Tag::Ns)?;
+    let namespace: i32 = parse_text(reader, buf, Tag::Ns)?;
+    skip_text(reader, buf)?;
+
+    expect_tag_start_from_reader(reader, buf, Tag::Id)?;
+    let id: u32 = parse_text(reader, buf, Tag::Id)?;
+    skip_text(reader, buf)?;
+
+    let (tag_start, attributes, is_empty) = get_start_tag_and_attributes(reader, buf, extra)?;
+    let (tag_start, redirect_target) = {
+        if tag_start == Tag::Redirect {
+            // Before version 0.6 of the export schema, `<redirect />` had no target,
+            // and some malformed dumps have `<redirect></redirect>`.
+            let mut title = String::new();
+            for (key, value) in attributes {
+                if key != b"title" {
+                    return Err(Error::format(
+                        reader,
+                        "expected no attribute but title on <redirect>",
+                    ));
                 }
-            } else {
-                ((tag_start, is_empty), None)
+                title = value;
             }
-        };
-
-        let restrictions = {
-            if tag_start == Tag::Restrictions {
-                Some(read_text(&mut reader, &mut buf, Tag::Restrictions)?)
-            } else if tag_start == Tag::Revision {
-                None
-            } else {
-                return Err(Error::format(&reader));
+            if !is_empty {
+                skip_element(reader, buf, Tag::Redirect.as_q_name())?;
             }
-        };
-        skip_text(&mut reader, &mut buf)?;
+            skip_text(reader, buf)?;
+            let (tag_start, _, _) = get_start_tag_and_attributes(reader, buf, extra)?;
+            (tag_start, Some(title))
+        } else {
+            (tag_start, None)
+        }
+    };
+
+    let (restrictions, next_tag) = {
+        if tag_start == Tag::Restrictions {
+            (Some(read_text(reader, buf, Tag::Restrictions)?), None)
+        } else if tag_start == Tag::Revision || tag_start == Tag::Upload {
+            (None, Some(tag_start))
+        } else {
+            return Err(Error::format(
+                reader,
+                "expected <restrictions>, <revision> or <upload> in <page>",
+            ));
+        }
+    };
+    skip_text(reader, buf)?;
 
-        let mut read_revision_start = restrictions.is_some();
+    Ok(Some((
+        PageHeader {
+            title,
+            namespace,
+            id,
+            redirect_target,
+            restrictions,
+        },
+        next_tag,
+        start,
+    )))
+}
 
-        // revision elements
-        let mut revisions = Vec::new();
-        loop {
-            buf.clear();
-            if read_revision_start {
-                match reader.read_event_into(&mut buf) {
-                    Ok(Event::Start(start)) if start.name() == QName(b"revision") => {
-                        skip_text(&mut reader, &mut buf)?;
+/// Reads the `<revision>` and `<upload>` elements of a page up to and including `</page>`,
+/// passing each revision to `on_revision` as soon as it has been parsed.
+/// `next_tag` is the tag of the first revision or upload if its start tag has already been read.
+/// The elements listed in `extra` between the revisions are read into it.
+/// Only [`ParseOptions::skip_text`] and [`ParseOptions::extra_elements`] are used from `options`.
+/// Returns the page-level `<sha1>` if there is one, and the position after `</page>`.
+/// Other elements that are not part of the export schema are skipped.
+fn read_page_revisions<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    mut next_tag: Option<Tag>,
+    uploads: &mut Vec<Upload>,
+    extra: &mut Extra,
+    options: &ParseOptions,
+    mut on_revision: impl FnMut(Revision) -> Result<(), Error<E>>,
+) -> Result<(Option<String>, usize), Error<E>> {
+    let mut sha1 = None;
+    loop {
+        buf.clear();
+        let tag = match next_tag.take() {
+            Some(tag) => tag,
+            None => {
+                let event = read_tag_event(reader, buf)?;
+                if let Some((name, is_empty)) = extra.listed(&event) {
+                    drop(event);
+                    extra.read(reader, buf, name, is_empty)?;
+                    skip_text(reader, buf)?;
+                    continue;
+                }
+                let tag = match event {
+                    Event::Start(start) if start.name() == QName(b"revision") => Tag::Revision,
+                    Event::Start(start) if start.name() == QName(b"upload") => Tag::Upload,
+                    Event::Start(start) if start.name() == QName(b"sha1") => Tag::Sha1,
+                    Event::Empty(start) if start.name() == QName(b"sha1") => {
+                        sha1 = Some(String::new());
+                        skip_text(reader, buf)?;
+                        continue;
+                    }
+                    Event::End(end) if end.name() == QName(b"page") => {
+                        let end = reader.buffer_position();
+                        skip_text(reader, buf)?;
+                        return Ok((sha1, end));
+                    }
+                    // Page-level elements that are not part of the export schema,
+                    // which some dumps add after the revisions, are skipped,
+                    // but known elements out of place are still an error.
+                    Event::Start(start) if Tag::from_bytes(start.name().as_ref()).is_err() => {
+                        let name = start.name().as_ref().to_vec();
+                        skip_element(reader, buf, QName(&name))?;
+                        skip_text(reader, buf)?;
+                        continue;
                     }
-                    Ok(Event::End(end)) if end.name() == QName(b"page") => {
-                        skip_text(&mut reader, &mut buf)?;
-                        break;
+                    Event::Empty(start) if Tag::from_bytes(start.name().as_ref()).is_err() => {
+                        skip_text(reader, buf)?;
+                        continue;
                     }
-                    _ => return Err(Error::format(&reader)),
+                    _ => {
+                        return Err(Error::format(
+                            reader,
+                            "expected <revision>, <upload> or </page>",
+                        ))
+                    }
+                };
+                if tag == Tag::Sha1 {
+                    sha1 = Some(read_text(reader, buf, Tag::Sha1)?);
+                    skip_text(reader, buf)?;
+                    continue;
                 }
-            } else {
-                read_revision_start = true;
+                skip_text(reader, buf)?;
+                tag
             }
+        };
 
-            expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Id)?;
-            let id: u32 = parse_text(&mut reader, &mut buf, Tag::Id)?;
-            skip_text(&mut reader, &mut buf)?;
+        if tag == Tag::Upload {
+            uploads.push(upload::parse_upload(reader, buf)?);
+            skip_text(reader, buf)?;
+        } else {
+            on_revision(read_revision(reader, buf, options)?)?;
+        }
+    }
+}
 
-            let (tag, _) = get_start_tag(&mut reader, &mut buf)?;
-            let (tag, parent_id) = if tag == Tag::ParentId {
-                let parent_id = parse_text(&mut reader, &mut buf, Tag::ParentId)?;
-                skip_text(&mut reader, &mut buf)?;
-                let (tag, _) = get_start_tag(&mut reader, &mut buf)?;
-                (tag, Some(parent_id))
-            } else {
-                (tag, None)
-            };
+/// Reads the elements of a `<revision>` up to and including the end tag
+/// and the whitespace after it.
+fn read_revision<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    options: &ParseOptions,
+) -> Result<Revision, Error<E>> {
+    let mut extra = Extra::new(&options.extra_elements);
+    expect_tag_start_from_reader(reader, buf, Tag::Id)?;
+    let id: u32 = parse_text(reader, buf, Tag::Id)?;
+    skip_text(reader, buf)?;
+
+    let (tag, _) = get_start_tag(reader, buf)?;
+    let (tag, parent_id) = if tag == Tag::ParentId {
+        let parent_id = parse_text(reader, buf, Tag::ParentId)?;
+        skip_text(reader, buf)?;
+        let (tag, _) = get_start_tag(reader, buf)?;
+        (tag, Some(parent_id))
+    } else {
+        (tag, None)
+    };
+
+    if tag != Tag::Timestamp {
+        return Err(Error::format(reader, "expected <timestamp> in <revision>"));
+    }
+    let timestamp = parse_timestamp(reader, buf)?;
+    skip_text(reader, buf)?;
 
-            if tag != Tag::Timestamp {
-                return Err(Error::format(&reader));
+    let contributor = {
+        let (tag, attributes, is_empty) = get_start_tag_and_attributes(reader, buf, &mut extra)?;
+        if tag != Tag::Contributor {
+            return Err(Error::format(
+                reader,
+                "expected <contributor> after <timestamp>",
+            ));
+        }
+        if is_empty {
+            if attributes
+                .iter()
+                .any(|(key, value)| key == b"deleted" && value == "deleted")
+            {
+                Contributor::Deleted
+            } else {
+                return Err(Error::format(
+                    reader,
+                    "expected deleted=\"deleted\" on an empty <contributor />",
+                ));
             }
-            let timestamp = parse_text(&mut reader, &mut buf, Tag::Timestamp)?;
-            skip_text(&mut reader, &mut buf)?;
-
-            let contributor = {
-                let (tag, attribute, is_empty) =
-                    get_start_tag_and_attribute(&mut reader, &mut buf)?;
-                if tag != Tag::Contributor {
-                    return Err(Error::format(&reader));
-                }
-                if is_empty {
-                    if let Some((key, value)) = attribute {
-                        if key == b"deleted" && value.as_bytes() == b"deleted" {
-                            Contributor::Deleted
-                        } else {
-                            return Err(Error::format(&reader));
-                        }
-                    } else {
-                        return Err(Error::format(&reader));
-                    }
-                } else {
-                    skip_text(&mut reader, &mut buf)?;
-
-                    let (tag, _) = get_start_tag(&mut reader, &mut buf)?;
-                    let contributor = if tag == Tag::Username {
-                        let username = read_text(&mut reader, &mut buf, Tag::Username)?;
-                        skip_text(&mut reader, &mut buf)?;
-
-                        expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Id)?;
-                        let id: u32 = parse_text(&mut reader, &mut buf, Tag::Id)?;
-                        skip_text(&mut reader, &mut buf)?;
-                        Contributor::User { username, id }
-                    } else if tag == Tag::Ip {
-                        let ip = parse_text(&mut reader, &mut buf, Tag::Ip)?;
-                        skip_text(&mut reader, &mut buf)?;
-                        Contributor::Ip { ip }
-                    } else {
-                        return Err(Error::format(&reader));
-                    };
+        } else {
+            read_contributor_contents(reader, buf)?
+        }
+    };
+    skip_text(reader, buf)?;
 
-                    expect_tag_end(&mut reader, &mut buf, Tag::Contributor)?;
+    // Older versions of the export schema leave out `<origin>` (added in 0.11),
+    // `<model>` and `<format>` (added in 0.8, where they come after `<sha1>` until 0.10)
+    // and `<sha1>` (added in 0.6), and `<comment>` is left out if the comment is empty.
+    let mut next = read_revision_child(reader, buf, &mut extra)?;
+    // `<minor />` is normally empty, but `<minor></minor>` means the same.
+    let minor = next.as_ref().is_some_and(|child| child.tag == Tag::Minor);
+    if minor {
+        if next.as_ref().is_some_and(|child| !child.is_empty) {
+            skip_element(reader, buf, Tag::Minor.as_q_name())?;
+        }
+        skip_text(reader, buf)?;
+        next = read_revision_child(reader, buf, &mut extra)?;
+    }
 
-                    contributor
-                }
-            };
-            skip_text(&mut reader, &mut buf)?;
-            dbg!();
-
-            let event = reader
-                .read_event_into(&mut buf)
-                .map_err(|_| Error::format(&reader))?;
-            let (event, minor) = if let Event::Empty(empty) = &event {
-                if empty.name() == QName(b"minor") {
-                    skip_text(&mut reader, &mut buf)?;
-                    (
-                        reader
-                            .read_event_into(&mut buf)
-                            .map_err(|_| Error::format(&reader))?,
-                        true,
-                    )
-                } else {
-                    (event, false)
-                }
+    let origin = parse_optional(reader, buf, &mut next, Tag::Origin, &mut extra)?.unwrap_or(0);
+
+    let comment = match &next {
+        Some(child) if child.tag == Tag::Comment => {
+            let comment = if !child.is_empty {
+                Comment::Visible(parse_text(reader, buf, Tag::Comment)?)
+            } else if child.deleted {
+                Comment::Deleted
             } else {
-                (event, false)
+                Comment::Visible(String::new())
             };
+            skip_text(reader, buf)?;
+            next = read_revision_child(reader, buf, &mut extra)?;
+            comment
+        }
+        _ => Comment::Absent,
+    };
 
-            expect_tag_start(&reader, &event, Tag::Origin)?;
-            dbg!();
-            let origin: u32 = parse_text(&mut reader, &mut buf, Tag::Origin)?;
-            dbg!();
-            skip_text(&mut reader, &mut buf)?;
-            dbg!(origin);
-
-            let event = expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Comment)?;
-            let (event, comment) = if let Event::Start(start) = &event {
-                if start.name() == QName(b"comment") {
-                    let comment = parse_text(&mut reader, &mut buf, Tag::Comment)?;
-                    skip_text(&mut reader, &mut buf)?;
-                    (
-                        reader
-                            .read_event_into(&mut buf)
-                            .map_err(|_| Error::format(&reader))?,
-                        Comment::Visible(comment),
-                    )
-                } else {
-                    (event, Comment::DeletedOrAbsent(false))
-                }
-            } else if let Event::Empty(empty) = &event {
-                if empty.name() == QName(b"comment") {
-                    let mut attributes = empty.attributes();
-                    if let (Some(Ok(attr)), None) = (attributes.next(), attributes.next()) {
-                        if attr.key == QName(b"deleted") && attr.value.as_ref() == b"deleted" {
-                            skip_text(&mut reader, &mut buf)?;
-                            (
-                                reader
-                                    .read_event_into(&mut buf)
-                                    .map_err(|_| Error::format(&reader))?,
-                                Comment::DeletedOrAbsent(true),
-                            )
-                        } else {
-                            dbg!(&event);
-                            return Err(Error::format(&reader));
-                        }
-                    } else {
-                        return Err(Error::format(&reader));
-                    }
-                } else {
-                    return Err(Error::format(&reader));
-                }
+    let mut model = parse_optional(reader, buf, &mut next, Tag::Model, &mut extra)?;
+    let mut format = parse_optional(reader, buf, &mut next, Tag::Format, &mut extra)?;
+
+    // Stub dumps have an empty `<text>` with the length and the id of the text
+    // in attributes, and deleted text is `<text deleted="deleted" />`.
+    let (text, text_bytes, text_deleted) = match &next {
+        Some(child) if child.tag == Tag::Text => {
+            let text = if child.is_empty {
+                String::new()
+            } else if options.skip_text {
+                skip_element(reader, buf, Tag::Text.as_q_name())?;
+                String::new()
+            } else if options.raw_text {
+                read_raw_text(reader, buf, Tag::Text)?
             } else {
-                return Err(Error::format(&reader));
+                parse_text(reader, buf, Tag::Text)?
             };
+            (text, child.bytes, child.deleted)
+        }
+        _ => return Err(Error::format(reader, "expected <text> in <revision>")),
+    };
+    skip_text(reader, buf)?;
+    next = read_revision_child(reader, buf, &mut extra)?;
 
-            expect_tag_start(&reader, &event, Tag::Model)?;
-            let model = parse_text(&mut reader, &mut buf, Tag::Model)?;
-            skip_text(&mut reader, &mut buf)?;
-
-            expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Format)?;
-            let format = parse_text(&mut reader, &mut buf, Tag::Format)?;
-            skip_text(&mut reader, &mut buf)?;
+    // MediaWiki runs the hook that LiquidThreads uses to add `<discussionthreadinginfo>`
+    // after writing `<sha1>`, but some dumps have it before.
+    let mut threading_info = read_optional_threading_info(reader, buf, &mut next, &mut extra)?;
 
-            let (tag, is_empty) = get_start_tag(&mut reader, &mut buf)?;
-            if tag != Tag::Text {
-                return Err(Error::format(&reader));
-            }
-            let text = if is_empty {
+    let sha1 = match &next {
+        // The SHA-1 is sometimes empty (`<sha1/>` or `<sha1></sha1>`) for deleted text.
+        Some(child) if child.tag == Tag::Sha1 => {
+            let sha1 = if child.is_empty {
                 String::new()
             } else {
-                parse_text(&mut reader, &mut buf, Tag::Text)?
+                read_possibly_empty_text(reader, buf, Tag::Sha1)?
             };
-            skip_text(&mut reader, &mut buf)?;
+            skip_text(reader, buf)?;
+            next = read_revision_child(reader, buf, &mut extra)?;
+            Some(sha1).filter(|sha1| !sha1.is_empty())
+        }
+        _ => None,
+    };
 
-            expect_tag_start_from_reader(&mut reader, &mut buf, Tag::Sha1)?;
-            let sha1 = parse_text(&mut reader, &mut buf, Tag::Sha1)?;
-            skip_text(&mut reader, &mut buf)?;
+    if model.is_none() {
+        model = parse_optional(reader, buf, &mut next, Tag::Model, &mut extra)?;
+    }
+    if format.is_none() {
+        format = parse_optional(reader, buf, &mut next, Tag::Format, &mut extra)?;
+    }
+    if threading_info.is_none() {
+        threading_info = read_optional_threading_info(reader, buf, &mut next, &mut extra)?;
+    }
+    if next.is_some() {
+        return Err(Error::format(
+            reader,
+            "unexpected element at the end of <revision>",
+        ));
+    }
+    skip_text(reader, buf)?;
 
-            expect_tag_end(&mut reader, &mut buf, Tag::Revision)?;
-            skip_text(&mut reader, &mut buf)?;
+    // Before there were content models, everything was wikitext.
+    let model = model.unwrap_or_else(|| Interned::new("wikitext"));
+    let format = format.unwrap_or_else(|| Interned::new("text/x-wiki"));
+    Ok(Revision {
+        id,
+        parent_id,
+        timestamp,
+        contributor,
+        origin,
+        minor,
+        comment,
+        model,
+        format,
+        text: text.into(),
+        text_bytes,
+        text_deleted,
+        text_truncated: false,
+        size_delta: None,
+        entity: None,
+        threading_info,
+        sha1,
+        extra: extra.values,
+    })
+}
 
-            revisions.push(Revision {
-                id,
-                parent_id,
-                timestamp,
-                contributor,
-                origin,
-                minor,
-                comment,
-                model,
-                format,
-                text,
-                sha1,
-            });
-        }
+/// A start tag in a `<revision>`, with the attributes that the parser uses.
+struct RevisionChild {
+    tag: Tag,
+    is_empty: bool,
+    /// Whether the element has `deleted="deleted"`.
+    deleted: bool,
+    /// The `bytes` attribute of `<text>`.
+    bytes: Option<u64>,
+}
 
-        match page_processor(Page {
-            title,
-            namespace,
-            id,
-            redirect_target,
-            restrictions,
-            revisions,
-        }) {
-            Err(Error::ShortCircuit) => return Ok(()),
-            Err(e) => return Err(e),
-            _ => {}
+/// Reads the next start tag in a `<revision>`, or returns `None` at the `</revision>` end tag.
+/// Elements listed in `extra` are read into it and skipped.
+fn read_revision_child<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    extra: &mut Extra,
+) -> Result<Option<RevisionChild>, Error<E>> {
+    let mut event = read_tag_event(reader, buf)?;
+    while let Some((name, is_empty)) = extra.listed(&event) {
+        drop(event);
+        extra.read(reader, buf, name, is_empty)?;
+        skip_text(reader, buf)?;
+        event = read_tag_event(reader, buf)?;
+    }
+    let (start, is_empty) = match &event {
+        Event::Start(start) => (start, false),
+        Event::Empty(start) => (start, true),
+        Event::End(end) if end.name() == Tag::Revision.as_q_name() => return Ok(None),
+        _ => return Err(Error::format(reader, "expected an element or </revision>")),
+    };
+    let tag = Tag::try_from(start.name()).map_err(Error::from_infallible)?;
+    let deleted = start
+        .try_get_attribute("deleted")
+        .map_err(|_| Error::format(reader, "invalid deleted attribute"))?
+        .is_some_and(|attr| attr.value.as_ref() == b"deleted");
+    let bytes = start
+        .try_get_attribute("bytes")
+        .map_err(|_| Error::format(reader, "invalid bytes attribute"))?
+        .map(|attr| {
+            std::str::from_utf8(&attr.value)
+                .ok()
+                .and_then(|bytes| bytes.parse().ok())
+                .ok_or_else(|| Error::format(reader, "invalid bytes attribute"))
+        })
+        .transpose()?;
+    Ok(Some(RevisionChild {
+        tag,
+        is_empty,
+        deleted,
+        bytes,
+    }))
+}
+
+/// If `next` is the start of a non-empty `tag` element, parses its text
+/// and replaces `next` with the start tag after it.
+fn parse_optional<R: BufRead, T: FromStr, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    next: &mut Option<RevisionChild>,
+    tag: Tag,
+    extra: &mut Extra,
+) -> Result<Option<T>, Error<E>> {
+    match next {
+        Some(child) if child.tag == tag && !child.is_empty => {
+            let value = parse_text(reader, buf, tag)?;
+            skip_text(reader, buf)?;
+            *next = read_revision_child(reader, buf, extra)?;
+            Ok(Some(value))
         }
-        // serde_cbor::to_writer(&mut stdout, &page).expect("failed to write CBOR to stdout");
+        _ => Ok(None),
+    }
+}
 
-        restrictions_buffer.clear();
+fn read_optional_threading_info<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    next: &mut Option<RevisionChild>,
+    extra: &mut Extra,
+) -> Result<Option<ThreadingInfo>, Error<E>> {
+    match next {
+        Some(child) if child.tag == Tag::DiscussionThreadingInfo && !child.is_empty => {
+            let threading_info = threads::parse_threading_info(reader, buf)?;
+            skip_text(reader, buf)?;
+            *next = read_revision_child(reader, buf, extra)?;
+            Ok(Some(threading_info))
+        }
+        _ => Ok(None),
     }
 }
 
-pub fn parse_from_file<
-    P: AsRef<Path>,
+pub fn parse<R: BufRead, F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
+    reader: R,
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    Parser::new()
+        .skip_header(skip_header)
+        .run(reader, page_processor)
+}
+
+/// Like [`parse`], but skips the pages rejected by `options`
+/// without parsing their revisions.
+pub fn parse_with_options<
+    R: BufRead,
     F: FnMut(Page) -> Result<(), Error<E>>,
     E: std::error::Error,
 >(
-    path: P,
-    page_processor: F,
+    reader: R,
+    options: &ParseOptions,
+    mut page_processor: F,
     skip_header: bool,
 ) -> Result<(), Error<E>> {
-    let path = path.as_ref();
-    let file = File::open(path).map_err(|e| Error::from_io("open", e, path))?;
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+    let mut reader = Reader::from_reader(reader);
 
-    match path.extension().and_then(|s| s.to_str()) {
-        #[cfg(feature = "bz2")]
-        Some("bz2") => parse(
-            BufReader::new(BzDecoder::new(file)),
-            page_processor,
-            skip_header,
-        ),
-        #[cfg(feature = "lzma")]
-        Some("7z") => parse(
-            BufReader::new(
-                LzmaReader::new_decompressor(file).map_err(|source| Error::Lzma {
-                    source,
-                    path: path.into(),
-                })?,
-            ),
-            page_processor,
-            skip_header,
-        ),
-        _ => parse(BufReader::new(file), page_processor, skip_header),
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    loop {
+        while let Some(page) = read_page(&mut reader, &mut buf, options)? {
+            match page_processor(page) {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            }
+        }
+        if !(options.multi_document && read_next_document(&mut reader, &mut buf)?) {
+            return Ok(());
+        }
+    }
+}
+
+/// Like [`parse_with_options`], but also passes `page_processor` the byte range of each page
+/// in the input, from the `<` of `<page>` to just after `</page>`, for building an index
+/// of the positions of pages like the one that comes with multistream dumps.
+///
+/// The positions are counted in the bytes that `reader` yields, so for a compressed dump
+/// they are positions in the decompressed XML, not in the file.
+pub fn parse_with_ranges<
+    R: BufRead,
+    F: FnMut(Page, Range<usize>) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: R,
+    options: &ParseOptions,
+    mut page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+    let bom = skip_bom(&mut reader)?;
+    let mut reader = Reader::from_reader(reader);
+
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    loop {
+        while let Some((page, range)) = read_page_with_range(
+            &mut reader,
+            &mut buf,
+            options,
+            &mut |_| PageAction::Keep,
+            &mut |_| {},
+        )? {
+            match page_processor(page, range.start + bom..range.end + bom) {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            }
+        }
+        if !(options.multi_document && read_next_document(&mut reader, &mut buf)?) {
+            return Ok(());
+        }
+    }
+}
+
+/// Like [`parse_with_options`], but calls `on_page_header` with the elements of each page
+/// before its revisions, once the page has been accepted by `options`.
+/// If it returns [`PageAction::Skip`], the rest of the page is skipped without parsing its revisions
+/// and the page is not passed to `page_processor`.
+pub fn parse_with_page_filter<
+    R: BufRead,
+    H: FnMut(&PageHeader) -> PageAction,
+    F: FnMut(Page) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: R,
+    options: &ParseOptions,
+    mut on_page_header: H,
+    mut page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+    let mut reader = Reader::from_reader(reader);
+
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    loop {
+        while let Some((page, _)) = read_page_with_range(
+            &mut reader,
+            &mut buf,
+            options,
+            &mut on_page_header,
+            &mut |_| {},
+        )? {
+            match page_processor(page) {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            }
+        }
+        if !(options.multi_document && read_next_document(&mut reader, &mut buf)?) {
+            return Ok(());
+        }
+    }
+}
+
+/// Like [`parse_with_options`], but when a page cannot be parsed, passes the error to `on_error`,
+/// and if it returns `true`, skips to the end of the page and continues with the next one.
+/// An element between pages that is not a page is reported and skipped in the same way.
+/// Errors that parsing cannot recover from, such as the end of the input inside a page,
+/// and errors returned by `page_processor` are returned without calling `on_error`.
+pub fn parse_resilient<
+    R: BufRead,
+    F: FnMut(Page) -> Result<(), Error<E>>,
+    G: FnMut(&Error) -> bool,
+    E: std::error::Error,
+>(
+    reader: R,
+    options: &ParseOptions,
+    mut page_processor: F,
+    mut on_error: G,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+    let mut reader = Reader::from_reader(reader);
+
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    loop {
+        match read_page::<_, Infallible>(&mut reader, &mut buf, options) {
+            Ok(Some(page)) => match page_processor(page) {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            },
+            Ok(None) => {
+                if !(options.multi_document && read_next_document(&mut reader, &mut buf)?) {
+                    return Ok(());
+                }
+            }
+            Err(
+                e @ (Error::Format { .. }
+                | Error::Tag { .. }
+                | Error::FailedToDecode { .. }
+                | Error::Value { .. }
+                | Error::Timestamp { .. }
+                | Error::UnexpectedTag(_)
+                | Error::Sha1Mismatch { .. }
+                | Error::Entity { .. }),
+            ) => {
+                if !on_error(&e) {
+                    return Err(Error::from_infallible(e));
+                }
+                match e {
+                    // The error was between pages, so there is no page to skip to the end of.
+                    Error::Format { context, .. } if context == STRAY_START_TAG => {
+                        skip_open_element(&mut reader, &mut buf)?
+                    }
+                    Error::Format { context, .. } if context == STRAY_EVENT => {}
+                    _ => skip_element(&mut reader, &mut buf, QName(b"page"))?,
+                }
+                skip_text(&mut reader, &mut buf)?;
+            }
+            Err(e) => return Err(Error::from_infallible(e)),
+        }
+    }
+}
+
+/// Like [`parse`], but passes each revision to `revision_processor` as soon as it has been parsed,
+/// along with the header of its page, so that the revisions of a page are never all in memory.
+/// `<upload>` elements are skipped.
+pub fn parse_revisions<
+    R: BufRead,
+    F: FnMut(&PageHeader, Revision) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: R,
+    mut revision_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::with_capacity(ParseOptions::DEFAULT_BUFFER_CAPACITY);
+    let mut reader = Reader::from_reader(reader);
+
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    let mut uploads = Vec::new();
+    let options = ParseOptions::default();
+    let mut extra = Extra::new(&[]);
+    while let Some((header, next_tag, _)) = read_page_header(&mut reader, &mut buf, &mut extra)? {
+        uploads.clear();
+        match read_page_revisions(
+            &mut reader,
+            &mut buf,
+            next_tag,
+            &mut uploads,
+            &mut extra,
+            &options,
+            |revision| revision_processor(&header, revision),
+        ) {
+            Err(Error::ShortCircuit) => return Ok(()),
+            Err(e) => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Like [`parse`], but only passes pages in the namespaces in `allowed_namespaces`
+/// to `page_processor`. The revisions of other pages are skipped without being parsed.
+pub fn parse_filtered<R: BufRead, F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
+    reader: R,
+    allowed_namespaces: &[i32],
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let options = ParseOptions {
+        namespaces: Some(allowed_namespaces.to_vec()),
+        ..ParseOptions::default()
+    };
+    parse_with_options(reader, &options, page_processor, skip_header)
+}
+
+/// Like [`parse`], but parses the `<siteinfo>` at the start of the dump instead of skipping it.
+/// The [`SiteInfo`] is passed to `page_processor` along with each page and then returned.
+pub fn parse_with_siteinfo<
+    R: BufRead,
+    F: FnMut(&SiteInfo, Page) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: R,
+    mut page_processor: F,
+) -> Result<SiteInfo, Error<E>> {
+    parse_with_header(reader, |header, page| {
+        page_processor(&header.siteinfo, page)
+    })
+    .map(|header| header.siteinfo)
+}
+
+/// Like [`parse_with_siteinfo`], but also parses the `xml:lang` and `version` attributes
+/// of the `<mediawiki>` root element.
+pub fn parse_with_header<
+    R: BufRead,
+    F: FnMut(&DumpHeader, Page) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: R,
+    mut page_processor: F,
+) -> Result<DumpHeader, Error<E>> {
+    let mut buf = Vec::with_capacity(ParseOptions::DEFAULT_BUFFER_CAPACITY);
+    let mut reader = Reader::from_reader(reader);
+
+    let header = read_dump_header(&mut reader, &mut buf)?;
+
+    while let Some(page) = read_page(&mut reader, &mut buf, &ParseOptions::default())? {
+        match page_processor(&header, page) {
+            Err(Error::ShortCircuit) => break,
+            Err(e) => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(header)
+}
+
+/// Like [`parse`], but for dumps of the log (`pages-logging.xml`),
+/// which contain `<logitem>` elements instead of `<page>` elements.
+pub fn parse_logitems<
+    R: BufRead,
+    F: FnMut(LogItem) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: R,
+    mut log_item_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::new();
+    let mut reader = Reader::from_reader(reader);
+
+    if skip_header {
+        read_header(&mut reader, &mut buf)?;
+    }
+
+    while let Some(log_item) = logitem::read_log_item(&mut reader, &mut buf)? {
+        match log_item_processor(log_item) {
+            Err(Error::ShortCircuit) => return Ok(()),
+            Err(e) => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// An iterator over the pages of a dump, created by [`pages`].
+pub struct Pages<R> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    skip_header: bool,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for Pages<R> {
+    type Item = Result<Page, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = if std::mem::take(&mut self.skip_header) {
+            read_header(&mut self.reader, &mut self.buf)
+                .and_then(|()| read_page(&mut self.reader, &mut self.buf, &ParseOptions::default()))
+        } else {
+            read_page(&mut self.reader, &mut self.buf, &ParseOptions::default())
+        };
+        match result {
+            Ok(Some(page)) => Some(Ok(page)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Like [`parse`], but yields the pages one at a time instead of passing them to a callback.
+/// The iterator ends at the `</mediawiki>` end tag, or after the first error.
+pub fn pages<R: BufRead>(reader: R, skip_header: bool) -> Pages<R> {
+    Pages {
+        reader: Reader::from_reader(reader),
+        buf: Vec::with_capacity(ParseOptions::DEFAULT_BUFFER_CAPACITY),
+        skip_header,
+        done: false,
+    }
+}
+
+pub fn parse_from_file<
+    P: AsRef<Path>,
+    F: FnMut(Page) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    path: P,
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    #[cfg(feature = "memmap")]
+    if let Some(xml) = map_file(path.as_ref())? {
+        return parse(&xml[..], page_processor, skip_header);
+    }
+    parse(open_file(path, None)?, page_processor, skip_header)
+}
+
+/// Maps an uncompressed dump into memory, so that it can be parsed
+/// or searched with [`find_page`] and [`parse_articles`] without being read into a `Vec`.
+/// Returns `None` if the file is compressed according to its extension or magic number.
+/// Requires the `memmap` feature.
+///
+/// The pages of the file count toward the resident set size once they have been read,
+/// but unlike a `Vec`, they can be dropped by the operating system when memory is short.
+/// The file must not be modified while it is mapped.
+#[cfg(feature = "memmap")]
+pub fn map_file<P: AsRef<Path>, E: std::error::Error>(
+    path: P,
+) -> Result<Option<memmap2::Mmap>, Error<E>> {
+    let path = path.as_ref();
+    if Compression::from_extension(path).is_some() {
+        return Ok(None);
+    }
+    let file = File::open(path).map_err(|e| Error::from_io("open", e, path))?;
+    // Safety: the map is only read, and modifying a dump while it is being parsed
+    // is not supported anyway.
+    let xml = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| Error::from_io("map", e, path))?;
+    if Compression::from_magic(&xml) == Compression::Uncompressed {
+        Ok(Some(xml))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Like [`parse_from_file`], but for a reader that may be compressed, such as stdin.
+/// If `compression` is `None`, it is detected from the magic number at the start of the input.
+/// Errors from the decoder report the path as `-`.
+pub fn parse_compressed<
+    R: BufRead,
+    F: FnMut(Page) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: R,
+    compression: Option<Compression>,
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    parse(
+        decompress(reader, compression)?,
+        page_processor,
+        skip_header,
+    )
+}
+
+/// Opens a dump file for parsing, decompressing it with `compression`.
+/// If `compression` is `None`, the extension is trusted if it names a compression format,
+/// and otherwise the magic number at the start of the file is used so that misnamed files still work.
+pub fn open_file<P: AsRef<Path>, E: std::error::Error>(
+    path: P,
+    compression: Option<Compression>,
+) -> Result<Box<dyn BufRead>, Error<E>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| Error::from_io("open", e, path))?;
+    decompress_with_path(
+        BufReader::new(file),
+        compression.or_else(|| Compression::from_extension(path)),
+        path,
+    )
+}
+
+/// Wraps a reader that may be compressed, such as stdin, in a decoder for `compression`.
+/// If `compression` is `None`, it is detected from the magic number at the start of the input.
+/// Errors from the decoder report the path as `-`.
+pub fn decompress<'a, R: BufRead + 'a, E: std::error::Error>(
+    reader: R,
+    compression: Option<Compression>,
+) -> Result<Box<dyn BufRead + 'a>, Error<E>> {
+    decompress_with_path(reader, compression, Path::new("-"))
+}
+
+/// Like [`decompress`], but errors from the decoder report `path`,
+/// for a file that has been opened some other way than with [`open_file`].
+pub fn decompress_with_path<'a, R: BufRead + 'a, E: std::error::Error>(
+    mut reader: R,
+    compression: Option<Compression>,
+    path: &Path,
+) -> Result<Box<dyn BufRead + 'a>, Error<E>> {
+    let compression = match compression {
+        Some(compression) => compression,
+        None => Compression::from_magic(
+            reader
+                .fill_buf()
+                .map_err(|e| Error::from_io("read", e, path))?,
+        ),
+    };
+    compression.decoder(reader, path)
+}
+
+#[cfg(test)]
+fn parse_to_vec(xml: &[u8]) -> Vec<Page> {
+    let mut pages = Vec::new();
+    parse::<_, _, Infallible>(
+        xml,
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    pages
+}
+
+#[test]
+fn test_enum_accessors() {
+    fn comments() -> [Comment; 3] {
+        [
+            Comment::Deleted,
+            Comment::Absent,
+            Comment::Visible("typo".into()),
+        ]
+    }
+    fn contributors() -> [Contributor; 3] {
+        [
+            Contributor::Deleted,
+            Contributor::Ip {
+                ip: IpAddr::from([127, 0, 0, 1]),
+            },
+            Contributor::User {
+                username: "Wonderfool".into(),
+                id: 1,
+            },
+        ]
+    }
+    let [deleted, absent, visible] = comments();
+    assert!(deleted.is_deleted() && !deleted.is_absent() && deleted.as_visible().is_none());
+    assert!(absent.is_absent() && !absent.is_deleted() && absent.as_visible().is_none());
+    assert_eq!(visible.as_visible(), Some("typo"));
+    let [deleted, ip, user] = contributors();
+    assert!(deleted.is_deleted() && deleted.as_ip().is_none() && deleted.as_user().is_none());
+    assert_eq!(ip.as_ip(), Some(IpAddr::from([127, 0, 0, 1])));
+    assert_eq!(user.as_user(), Some(("Wonderfool", 1)));
+
+    let error = Error::<std::fmt::Error>::Format {
+        position: 5,
+        context: "expected <page>",
+    };
+    assert_eq!(error.position(), Some(5));
+    assert!(Error::<std::fmt::Error>::ShortCircuit.is_short_circuit());
+    assert!(Error::Other(std::fmt::Error).as_other().is_some());
+}
+
+#[test]
+fn test_truncated_dump() {
+    let xml = include_str!("../tests/fixtures/pages.xml");
+    let truncated = &xml[..xml.find("much more").unwrap()];
+    let mut pages = 0;
+    let result = parse::<_, _, Infallible>(
+        truncated.as_bytes(),
+        |_| {
+            pages += 1;
+            Ok(())
+        },
+        true,
+    );
+    assert!(matches!(result, Err(Error::UnexpectedEof { .. })));
+    assert_eq!(pages, 0);
+}
+
+#[test]
+fn test_parse_articles() {
+    fn article_titles(xml: &[u8], fast: bool) -> Vec<String> {
+        let mut titles = Vec::new();
+        let mut push_title = |page: Page| {
+            titles.push(page.title);
+            Ok(())
+        };
+        if fast {
+            parse_articles::<_, Infallible>(xml, push_title).unwrap();
+        } else {
+            parse(
+                xml,
+                |page| {
+                    if page.namespace == 0 {
+                        push_title(page)?;
+                    }
+                    Ok(())
+                },
+                true,
+            )
+            .unwrap();
+        }
+        titles
+    }
+    let xml = include_str!("../tests/fixtures/pages.xml");
+    let expected = article_titles(xml.as_bytes(), false);
+    assert_eq!(expected, ["dictionary", "dictionnary"]);
+    assert_eq!(article_titles(xml.as_bytes(), true), expected);
+
+    // Errors have positions in the whole input, not in the page.
+    let timestamp = xml.rfind("<timestamp>").unwrap() + "<timestamp>".len();
+    let broken = format!("{}yesterday {}", &xml[..timestamp], &xml[timestamp..]);
+    let error = parse_articles::<_, Infallible>(broken.as_bytes(), |_| Ok(())).unwrap_err();
+    let expected =
+        parse_filtered::<_, _, Infallible>(broken.as_bytes(), &[0], |_| Ok(()), true).unwrap_err();
+    assert!(
+        matches!(
+            (&error, &expected),
+            (Error::Timestamp { position, .. }, Error::Timestamp { position: expected, .. })
+                if position == expected
+        ),
+        "{:?} {:?}",
+        error,
+        expected
+    );
+}
+
+#[test]
+fn test_pages_iterator() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let all = pages(&xml[..], true)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(all, parse_to_vec(xml));
+
+    let titles = pages(&xml[..], true)
+        .filter_map(Result::ok)
+        .filter(|page| page.namespace == 0)
+        .take(1)
+        .map(|page| page.title)
+        .collect::<Vec<_>>();
+    assert_eq!(titles, ["dictionary"]);
+
+    let mut truncated = pages(&xml[..xml.len() / 2], true);
+    assert!(truncated.any(|page| page.is_err()));
+    assert!(truncated.next().is_none());
+}
+
+#[test]
+fn test_find_page_escaped_title() {
+    let xml = std::str::from_utf8(include_bytes!("../tests/fixtures/pages.xml")).unwrap();
+    for title in ["Foo > Bar", "Foo < Bar & \"Baz\""] {
+        let escaped = quick_xml::escape::escape(title);
+        let xml = xml.replace(
+            "<title>dictionnary</title>",
+            &format!("<title>{}</title>", escaped),
+        );
+        let page = find_page(title, xml.as_bytes()).unwrap().unwrap();
+        assert_eq!(page.title, title);
+        assert_eq!(page.id, 30);
+    }
+}
+
+#[test]
+fn test_find_pages() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let titles = |titles: &[&str]| titles.iter().map(|&title| title.to_owned()).collect();
+    let pages = find_pages(
+        &titles(&["dictionnary", "Template:en-noun", "missing"]),
+        xml,
+    )
+    .unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages["Template:en-noun"].namespace, 10);
+    assert_eq!(
+        pages["dictionnary"].redirect_target.as_deref(),
+        Some("dictionary")
+    );
+
+    // Parsing stops after the last title is found, before the page that is cut off.
+    let third_page = memmem::rfind(xml, b"<page>").unwrap();
+    let pages = find_pages(
+        &titles(&["Template:en-noun", "dictionary"]),
+        &xml[..third_page + 10],
+    )
+    .unwrap();
+    assert_eq!(pages.len(), 2);
+
+    let xml = include_bytes!("../tests/fixtures/redirects.xml");
+    let pages = find_pages(&titles(&["R&B"]), xml).unwrap();
+    assert_eq!(
+        pages["R&B"].redirect_target.as_deref(),
+        Some("rhythm & blues")
+    );
+    assert!(find_pages(&HashSet::new(), xml).unwrap().is_empty());
+}
+
+#[test]
+fn test_find_page_from_file() {
+    let page = find_page_from_file("tests/fixtures/pages.xml", "dictionnary")
+        .unwrap()
+        .unwrap();
+    assert_eq!(page.id, 30);
+    assert_eq!(
+        find_page_from_file("tests/fixtures/pages.xml", "missing").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_find_page_in_reader() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let title_tag_index = memmem::find(xml, b"<title>dictionnary</title>").unwrap();
+    // The first buffer ends inside the title tag, or between `<page>` and `<title>`.
+    let capacities = [
+        title_tag_index + 4,
+        title_tag_index - 3,
+        title_tag_index - 7,
+    ];
+    for capacity in capacities.iter().copied().chain(16..100) {
+        let reader = BufReader::with_capacity(capacity, &xml[..]);
+        let page = find_page_in_reader("dictionnary", reader).unwrap().unwrap();
+        assert_eq!(page.id, 30, "buffer capacity {}", capacity);
+    }
+    let reader = BufReader::with_capacity(32, &xml[..]);
+    assert_eq!(find_page_in_reader("missing", reader).unwrap(), None);
+
+    // A window that starts after the `<page>` start tag or cuts the page off.
+    assert!(matches!(
+        find_page("dictionnary", &xml[title_tag_index - 2..]),
+        Err(Error::Format { .. })
+    ));
+    assert!(matches!(
+        find_page("dictionnary", &xml[..title_tag_index + 60]),
+        Err(Error::UnexpectedEof { .. })
+    ));
+}
+
+#[test]
+fn test_parse_with_siteinfo() {
+    let mut titles = Vec::new();
+    let siteinfo = parse_with_siteinfo::<_, _, Infallible>(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |siteinfo, page| {
+            titles.push(format!("{}:{}", siteinfo.dbname, page.title));
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(titles[0], "enwiktionary:dictionary");
+    assert_eq!(titles.len(), 3);
+    assert_eq!(siteinfo.sitename, "Wiktionary");
+    assert_eq!(
+        siteinfo.base,
+        "https://en.wiktionary.org/wiki/Wiktionary:Main_Page"
+    );
+    assert_eq!(siteinfo.generator, "MediaWiki 1.36.0-wmf.2");
+    assert_eq!(siteinfo.case, "case-sensitive");
+    assert_eq!(siteinfo.namespaces.0.len(), 5);
+}
+
+#[test]
+fn test_leading_bom_and_whitespace() {
+    let xml = include_bytes!("../tests/fixtures/bom.xml");
+    assert!(xml.starts_with(b"\xEF\xBB\xBF\n"));
+    let pages = parse_to_vec(xml);
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].title, "bom");
+    let siteinfo = parse_with_siteinfo::<_, _, Infallible>(&xml[..], |_, _| Ok(())).unwrap();
+    assert_eq!(siteinfo.dbname, "enwiktionary");
+
+    // Whitespace and an XML declaration without a byte order mark.
+    let xml = [
+        &b"\n  <?xml version=\"1.0\" encoding=\"utf-8\"?>\n"[..],
+        &xml[b"\xEF\xBB\xBF\n".len()..],
+    ]
+    .concat();
+    assert_eq!(parse_to_vec(&xml), pages);
+}
+
+#[test]
+fn test_multi_document() {
+    let xml = include_bytes!("../tests/fixtures/concatenated.xml");
+    let expected: Vec<_> = parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"))
+        .into_iter()
+        .chain(parse_to_vec(include_bytes!(
+            "../tests/fixtures/history.xml"
+        )))
+        .collect();
+    assert_eq!(parse_to_vec(xml), expected[..3]);
+
+    let mut options = ParseOptions {
+        multi_document: true,
+        ..ParseOptions::default()
+    };
+    let mut pages = Vec::new();
+    parse_with_options::<_, _, Infallible>(
+        &xml[..],
+        &options,
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(pages, expected);
+
+    options.namespaces = Some(vec![0]);
+    let mut titles = Vec::new();
+    parse_resilient::<_, _, _, Infallible>(
+        &xml[..],
+        &options,
+        |page| {
+            titles.push(page.title);
+            Ok(())
+        },
+        |_| false,
+        true,
+    )
+    .unwrap();
+    assert_eq!(titles, ["dictionary", "dictionnary", "history"]);
+
+    // Something other than another document after `</mediawiki>`.
+    let xml = [&xml[..], b"<page>"].concat();
+    assert!(matches!(
+        parse_with_options::<_, _, Infallible>(&xml[..], &options, |_| Ok(()), true),
+        Err(Error::Format { .. })
+    ));
+}
+
+#[test]
+fn test_page_protection_levels() {
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let levels = pages[0].protection_levels().unwrap();
+    assert_eq!(levels.get("edit"), Some("autoconfirmed"));
+    assert_eq!(levels.get("move"), Some("sysop"));
+    assert_eq!(pages[1].protection_levels(), None);
+}
+
+#[test]
+fn test_stub_dump() {
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/stub-meta-current.xml"));
+    let revisions: Vec<_> = pages.iter().flat_map(|page| &page.revisions).collect();
+    assert_eq!(revisions.len(), 2);
+    assert!(revisions.iter().all(|revision| revision.text.is_empty()));
+    assert_eq!(revisions[0].text_bytes, Some(31));
+    assert_eq!(revisions[1].text_bytes, Some(16));
+    assert_eq!(
+        parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"))[0].revisions[0].text_bytes,
+        Some(28)
+    );
+}
+
+#[test]
+fn test_deleted_text() {
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/deleted-text.xml"));
+    let revisions = &pages[0].revisions;
+    assert!(revisions[0].text_deleted);
+    assert_eq!(&*revisions[0].text, "");
+    assert_eq!(revisions[0].sha1, None);
+    assert!(!revisions[1].text_deleted);
+    assert_eq!(&*revisions[1].text, "");
+    let json = serde_json::to_string(&revisions[0]).unwrap();
+    assert_eq!(
+        &serde_json::from_str::<Revision>(&json).unwrap(),
+        &revisions[0]
+    );
+}
+
+#[test]
+fn test_parse_logitems() {
+    let mut log_items = Vec::new();
+    parse_logitems::<_, _, Infallible>(
+        &include_bytes!("../tests/fixtures/logging.xml")[..],
+        |log_item| {
+            log_items.push(log_item);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(log_items.len(), 3);
+    assert_eq!(log_items[0].log_type, "delete");
+    assert_eq!(log_items[0].logtitle.as_deref(), Some("Dictonary"));
+    assert_eq!(log_items[0].comment.as_visible(), Some("typo"));
+    assert_eq!(log_items[1].action, "move");
+    assert_eq!(
+        log_items[1].params.as_deref(),
+        Some(r#"a:1:{s:9:"4::target";s:10:"dictionary";}"#)
+    );
+    assert_eq!(log_items[2].contributor, Contributor::Deleted);
+    assert!(log_items[2].comment.is_deleted());
+    assert_eq!(log_items[2].logtitle, None);
+}
+
+#[test]
+fn test_upload() {
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/commons-upload.xml"));
+    assert_eq!(pages[0].revisions.len(), 1);
+    let uploads = &pages[0].uploads;
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].filename, "Example.jpg");
+    assert_eq!(
+        uploads[0].src,
+        "https://upload.wikimedia.org/wikipedia/commons/a/a9/Example.jpg"
+    );
+    assert_eq!(uploads[0].size, 9022);
+    assert_eq!(uploads[0].contributor.as_user(), Some(("Example", 42)));
+    assert_eq!(uploads[0].comment.as_visible(), Some("upload"));
+}
+
+#[test]
+fn test_parse_filtered() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    for allowed_namespaces in [&[0][..], &[10], &[0, 10], &[4], &[]] {
+        let mut pages = Vec::new();
+        parse_filtered::<_, _, Infallible>(
+            &xml[..],
+            allowed_namespaces,
+            |page| {
+                pages.push(page);
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        let expected: Vec<_> = parse_to_vec(xml)
+            .into_iter()
+            .filter(|page| allowed_namespaces.contains(&page.namespace))
+            .collect();
+        assert_eq!(pages, expected);
+    }
+}
+
+#[test]
+fn test_title_filter() {
+    let mut titles = Vec::new();
+    let options = ParseOptions {
+        title: Some(regex::Regex::new("^dict").unwrap()),
+        ..ParseOptions::default()
+    };
+    parse_with_options::<_, _, Infallible>(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        &options,
+        |page| {
+            titles.push(page.title);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(titles, ["dictionary", "dictionnary"]);
+}
+
+#[test]
+fn test_normalize_titles() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let mut pages = Vec::new();
+    Parser::new()
+        .normalize_titles(TitleCase::FirstLetter)
+        .title(regex::Regex::new("^D").unwrap())
+        .run::<_, _, Infallible>(&xml[..], |page| {
+            pages.push((page.title, page.raw_title));
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        pages,
+        [
+            ("Dictionary".into(), Some("dictionary".into())),
+            ("Dictionnary".into(), Some("dictionnary".into())),
+        ]
+    );
+}
+
+#[test]
+fn test_timestamp_range() {
+    let parse_history = |options: &ParseOptions| {
+        let mut pages = Vec::new();
+        parse_with_options::<_, _, Infallible>(
+            &include_bytes!("../tests/fixtures/history.xml")[..],
+            options,
+            |page| {
+                let ids: Vec<_> = page.revisions.iter().map(|revision| revision.id).collect();
+                pages.push((page.title, ids));
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        pages
+    };
+    let mut options = ParseOptions {
+        since: Some("2006-01-01T00:00:00Z".parse().unwrap()),
+        until: Some("2010-06-01T00:00:00Z".parse().unwrap()),
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_history(&options),
+        [
+            ("history".to_string(), vec![5002, 5003]),
+            ("Template:old".to_string(), vec![])
+        ]
+    );
+    options.skip_empty_pages = true;
+    assert_eq!(
+        parse_history(&options),
+        [("history".to_string(), vec![5002, 5003])]
+    );
+}
+
+#[test]
+fn test_minor_element() {
+    let xml = include_bytes!("../tests/fixtures/minor-element.xml");
+    let minor = |revisions: &[Revision]| {
+        revisions
+            .iter()
+            .map(|revision| revision.minor)
+            .collect::<Vec<_>>()
+    };
+    let pages = parse_to_vec(xml);
+    assert_eq!(minor(&pages[0].revisions), [true, false]);
+    assert_eq!(pages[0].revisions[0].origin, 6001);
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        let page = page.into_owned();
+        assert_eq!(minor(&page.revisions), [true, false]);
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_compact_timestamps() {
+    let xml = include_bytes!("../tests/fixtures/compact-timestamps.xml");
+    let timestamps = |revisions: &[Revision]| {
+        revisions
+            .iter()
+            .map(|revision| revision.timestamp.to_rfc3339())
+            .collect::<Vec<_>>()
+    };
+    let expected = ["2004-06-01T12:34:56+00:00", "2006-06-01T12:34:56+00:00"];
+    assert_eq!(timestamps(&parse_to_vec(xml)[0].revisions), expected);
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        assert_eq!(timestamps(&page.into_owned().revisions), expected);
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_parse_with_ranges() {
+    let xml = include_bytes!("../tests/fixtures/history.xml");
+    let mut ranges = Vec::new();
+    parse_with_ranges::<_, _, Infallible>(
+        &xml[..],
+        &ParseOptions::default(),
+        |page, range| {
+            ranges.push((page, range));
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        ranges.iter().map(|(page, _)| page).collect::<Vec<_>>(),
+        parse_to_vec(xml).iter().collect::<Vec<_>>()
+    );
+    // Each page can be parsed on its own after the header, as with a seek index.
+    let header = &xml[..ranges[0].1.start];
+    for (page, range) in &ranges {
+        let slice = &xml[range.clone()];
+        assert!(slice.starts_with(b"<page>"));
+        assert!(slice.ends_with(b"</page>"));
+        let mut reparsed = Vec::new();
+        parse::<_, _, Infallible>(
+            &[header, slice, b"\n</mediawiki>"].concat()[..],
+            |page| {
+                reparsed.push(page);
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(&reparsed, std::slice::from_ref(page));
+    }
+
+    // quick-xml skips a byte order mark without counting it.
+    let xml = include_bytes!("../tests/fixtures/bom.xml");
+    let mut ranges = Vec::new();
+    parse_with_ranges::<_, _, Infallible>(
+        &xml[..],
+        &ParseOptions::default(),
+        |_, range| {
+            ranges.push(range);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(ranges.len(), 1);
+    assert!(xml[ranges[0].clone()].starts_with(b"<page>"));
+    assert!(xml[ranges[0].clone()].ends_with(b"</page>"));
+}
+
+#[test]
+fn test_wikibase_entities() {
+    let xml = include_str!("../tests/fixtures/wikidata.xml");
+    let options = ParseOptions {
+        wikibase_entities: true,
+        ..ParseOptions::default()
+    };
+    let parse = |xml: &str| {
+        let mut pages = Vec::new();
+        parse_with_options::<_, _, Infallible>(
+            xml.as_bytes(),
+            &options,
+            |page| {
+                pages.push(page);
+                Ok(())
+            },
+            true,
+        )
+        .map(|()| pages)
+    };
+    let pages = parse(xml).unwrap();
+    assert_eq!(
+        pages[0].revisions[0].entity,
+        Some(EntitySummary {
+            id: Some("Q42".into()),
+            entity_type: Some("item".into()),
+            labels: 2,
+            descriptions: 0,
+            claims: 1,
+        })
+    );
+    // Only Wikibase models are parsed.
+    assert_eq!(pages[1].revisions[0].entity, None);
+    assert_eq!(parse_to_vec(xml.as_bytes())[0].revisions[0].entity, None);
+
+    let broken = xml.replacen("{&quot;type", "{type", 1);
+    assert!(matches!(
+        parse(&broken),
+        Err(Error::Entity {
+            revision_id: 4201,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_parse_with_page_filter() {
+    let xml = include_bytes!("../tests/fixtures/history.xml");
+    let all = parse_to_vec(xml);
+    let mut headers = Vec::new();
+    let mut pages = Vec::new();
+    parse_with_page_filter::<_, _, _, Infallible>(
+        &xml[..],
+        &ParseOptions::default(),
+        |header| {
+            headers.push(header.title.clone());
+            if header.id == all[0].id {
+                PageAction::Skip
+            } else {
+                PageAction::Keep
+            }
+        },
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(headers, [all[0].title.clone(), all[1].title.clone()]);
+    assert_eq!(pages, all[1..]);
+
+    // Pages rejected by the options are never passed to `on_page_header`.
+    let options = ParseOptions {
+        namespaces: Some(vec![-1]),
+        ..ParseOptions::default()
+    };
+    parse_with_page_filter::<_, _, _, Infallible>(
+        &xml[..],
+        &options,
+        |header| panic!("{:?} was not rejected", header),
+        |page| panic!("{:?} was not rejected", page),
+        true,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_empty_sha1() {
+    let xml = include_bytes!("../tests/fixtures/empty-sha1.xml");
+    let expected = [None, None, None, Some("7mtt5byxhs0ig8nltmn4r8jicjylo3s")];
+    let sha1s = |revisions: &[Revision]| {
+        revisions
+            .iter()
+            .map(|revision| revision.sha1.clone())
+            .collect::<Vec<_>>()
+    };
+    let expected = expected.map(|sha1| sha1.map(String::from));
+    let options = ParseOptions {
+        verify_sha1: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options::<_, _, Infallible>(
+        &xml[..],
+        &options,
+        |page| {
+            assert_eq!(sha1s(&page.revisions), expected);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        assert_eq!(sha1s(&page.into_owned().revisions), expected);
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_redirects_filter() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let titles = |redirects| {
+        let mut titles = Vec::new();
+        Parser::new()
+            .redirects(redirects)
+            .run::<_, _, Infallible>(&xml[..], |page| {
+                titles.push(page.title);
+                Ok(())
+            })
+            .unwrap();
+        titles
+    };
+    assert_eq!(
+        titles(Redirects::Include),
+        ["dictionary", "Template:en-noun", "dictionnary"]
+    );
+    assert_eq!(titles(Redirects::Only), ["dictionnary"]);
+    assert_eq!(
+        titles(Redirects::Exclude),
+        ["dictionary", "Template:en-noun"]
+    );
+    // `<redirect />` without a target is still a redirect.
+    let mut titles = Vec::new();
+    Parser::new()
+        .redirects(Redirects::Exclude)
+        .run::<_, _, Infallible>(
+            &include_bytes!("../tests/fixtures/redirects.xml")[..],
+            |page| {
+                titles.push(page.title);
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert!(titles.is_empty());
+    assert_eq!("only".parse(), Ok(Redirects::Only));
+    assert!("none".parse::<Redirects>().is_err());
+}
+
+#[test]
+fn test_contributors_filter() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let parse = |contributors: &[&str], skip_empty_pages| {
+        let mut filter = ContributorFilter::default();
+        for contributor in contributors {
+            filter.add_contributor(contributor);
+        }
+        let mut pages = Vec::new();
+        Parser::new()
+            .contributors(filter)
+            .skip_empty_pages(skip_empty_pages)
+            .run::<_, _, Infallible>(&xml[..], |page| {
+                pages.push((
+                    page.title,
+                    page.revisions
+                        .into_iter()
+                        .map(|revision| revision.contributor)
+                        .collect::<Vec<_>>(),
+                ));
+                Ok(())
+            })
+            .unwrap();
+        pages
+    };
+    let ip = Contributor::Ip {
+        ip: [127, 0, 0, 1].into(),
+    };
+    assert_eq!(
+        parse(&["127.0.0.0/8"], false),
+        [
+            ("dictionary".to_owned(), vec![ip.clone()]),
+            ("Template:en-noun".to_owned(), vec![]),
+            ("dictionnary".to_owned(), vec![]),
+        ]
+    );
+    assert_eq!(
+        parse(&["127.0.0.0/8", "Example"], true),
+        [
+            ("dictionary".to_owned(), vec![ip]),
+            (
+                "dictionnary".to_owned(),
+                vec![Contributor::User {
+                    username: "Example".into(),
+                    id: 42,
+                }]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_size_deltas() {
+    let parse = |xml: &[u8], latest_only| {
+        let options = ParseOptions {
+            size_deltas: true,
+            latest_only,
+            ..ParseOptions::default()
+        };
+        let mut deltas = Vec::new();
+        parse_with_options::<_, _, Infallible>(
+            xml,
+            &options,
+            |page| {
+                deltas.push(
+                    page.revisions
+                        .iter()
+                        .map(|revision| revision.size_delta)
+                        .collect::<Vec<_>>(),
+                );
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        deltas
+    };
+    let history = include_bytes!("../tests/fixtures/history.xml");
+    assert_eq!(
+        parse(history, false),
+        [vec![None, Some(0), Some(0), Some(0), Some(0)], vec![None]]
+    );
+    // The delta of the latest revision is still from the one before it.
+    assert_eq!(parse(history, true), [vec![Some(0)], vec![None]]);
+    // The first revision's text is deleted, so the second has no delta either.
+    assert_eq!(
+        parse(include_bytes!("../tests/fixtures/deleted-text.xml"), false),
+        [vec![None, None]]
+    );
+}
+
+#[test]
+fn test_latest_only() {
+    let xml = include_bytes!("../tests/fixtures/history.xml");
+    let mut options = ParseOptions {
+        latest_only: true,
+        ..ParseOptions::default()
+    };
+    for (until, expected) in [(None, 5005), (Some("2009-01-01T00:00:00Z"), 5003)] {
+        options.until = until.map(|until| until.parse().unwrap());
+        let mut revisions = Vec::new();
+        parse_with_options::<_, _, Infallible>(
+            &xml[..],
+            &options,
+            |page| {
+                revisions.push(page.revisions);
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(revisions[0].len(), 1);
+        assert_eq!(revisions[0][0].id, expected);
+        assert_eq!(
+            *revisions[0][0].text,
+            format!("Revision {}", expected - 5000)
+        );
+    }
+}
+
+#[test]
+fn test_parse_borrowed() {
+    for xml in [
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        include_bytes!("../tests/fixtures/export-0.6.xml"),
+        include_bytes!("../tests/fixtures/deleted-text.xml"),
+        include_bytes!("../tests/fixtures/threads.xml"),
+        include_bytes!("../tests/fixtures/commons-upload.xml"),
+    ] {
+        let mut pages = Vec::new();
+        let mut borrowed_texts = 0;
+        parse_borrowed::<_, _, Infallible>(xml, |page| {
+            borrowed_texts += page
+                .revisions
+                .iter()
+                .filter(|revision| matches!(revision.text, Cow::Borrowed(_)))
+                .count();
+            pages.push(page.into_owned());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(pages, parse_to_vec(xml));
+        assert!(borrowed_texts > 0 || pages.iter().all(|page| page.revisions.is_empty()));
+    }
+
+    // Errors from the reader are not mistaken for the end of the input.
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "corrupt",
+            ))
+        }
+    }
+    let xml = &include_bytes!("../tests/fixtures/pages.xml")[..1000];
+    let reader = std::io::Read::chain(xml, std::io::BufReader::new(Failing));
+    let error = parse_borrowed::<_, _, Infallible>(reader, |_| Ok(())).unwrap_err();
+    assert!(matches!(error, Error::Io { .. }), "{:?}", error);
+}
+
+#[test]
+fn test_parse_revisions() {
+    let xml = include_bytes!("../tests/fixtures/history.xml");
+    let mut revisions = Vec::new();
+    parse_revisions::<_, _, Infallible>(
+        &xml[..],
+        |header, revision| {
+            revisions.push((header.title.clone(), revision));
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    let expected: Vec<_> = parse_to_vec(xml)
+        .into_iter()
+        .flat_map(|page| {
+            let title = page.title;
+            page.revisions
+                .into_iter()
+                .map(move |revision| (title.clone(), revision))
+        })
+        .collect();
+    assert_eq!(revisions.len(), 6);
+    assert_eq!(revisions, expected);
+
+    let mut count = 0;
+    parse_revisions::<_, _, Infallible>(
+        &xml[..],
+        |_, _| {
+            count += 1;
+            if count == 2 {
+                Err(Error::ShortCircuit)
+            } else {
+                Ok(())
+            }
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn test_parse_resilient() {
+    let xml = include_str!("../tests/fixtures/pages.xml")
+        .replacen("<origin>", "<origin>x", 1)
+        .replace("<ns>10</ns>", "<ns>ten</ns>");
+    let mut titles = Vec::new();
+    let mut errors = Vec::new();
+    parse_resilient::<_, _, _, Infallible>(
+        xml.as_bytes(),
+        &ParseOptions::default(),
+        |page| {
+            titles.push(page.title);
+            Ok(())
+        },
+        |e| {
+            errors.push(e.position().unwrap());
+            true
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(titles, ["dictionnary"]);
+    assert_eq!(errors.len(), 2);
+
+    let result = parse_resilient::<_, _, _, Infallible>(
+        xml.as_bytes(),
+        &ParseOptions::default(),
+        |_| Ok(()),
+        |_| false,
+        true,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::Value { tag: Tag::Origin, ref value, .. }) if value.starts_with('x')
+    ));
+
+    // Elements between pages are skipped without skipping the pages after them.
+    let xml = include_str!("../tests/fixtures/pages.xml");
+    let first_page_end = xml.find("</page>").unwrap() + "</page>".len();
+    for stray in ["<bogus/>", "<bogus><page></page></bogus>"] {
+        let xml = format!(
+            "{}\n  {}{}",
+            &xml[..first_page_end],
+            stray,
+            &xml[first_page_end..]
+        );
+        let mut pages = 0;
+        let mut errors = 0;
+        parse_resilient::<_, _, _, Infallible>(
+            xml.as_bytes(),
+            &ParseOptions::default(),
+            |_| {
+                pages += 1;
+                Ok(())
+            },
+            |_| {
+                errors += 1;
+                true
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!((pages, errors), (3, 1), "{}", stray);
+    }
+}
+
+#[test]
+fn test_value_errors() {
+    let xml = include_str!("../tests/fixtures/pages.xml");
+    let timestamp = xml.find("<timestamp>").unwrap() + "<timestamp>".len();
+    let broken = xml.replacen("<timestamp>", "<timestamp>yesterday ", 1);
+    let error = pages(broken.as_bytes(), true)
+        .find_map(Result::err)
+        .unwrap();
+    assert!(
+        matches!(
+            &error,
+            Error::Timestamp { value, position }
+                if value.starts_with("yesterday ") && *position == timestamp
+        ),
+        "{:?}",
+        error
+    );
+    assert!(error
+        .to_string()
+        .starts_with("invalid timestamp \"yesterday "));
+
+    let broken = xml.replacen("<ns>0</ns>", "<ns>zero</ns>", 1);
+    let error = pages(broken.as_bytes(), true)
+        .find_map(Result::err)
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "invalid ns \"zero\" at position {}",
+            error.position().unwrap()
+        )
+    );
+    assert!(matches!(error, Error::Value { tag: Tag::Ns, .. }));
+}
+
+#[test]
+fn test_format_context() {
+    let xml = include_str!("../tests/fixtures/pages.xml");
+    let broken = xml.replacen("<timestamp>", "<origin>", 1);
+    let error = pages(broken.as_bytes(), true)
+        .find_map(Result::err)
+        .unwrap();
+    assert!(
+        matches!(
+            error,
+            Error::Format {
+                context: "expected <timestamp> in <revision>",
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert!(error
+        .to_string()
+        .ends_with(": expected <timestamp> in <revision>"));
+}
+
+#[test]
+fn test_error_line_and_column() {
+    // The `<contributor>` of the second revision has no `<id>`.
+    let xml = include_bytes!("../tests/fixtures/malformed.xml");
+    let error = crate::pages(&xml[..], true).find_map(Result::err).unwrap();
+    assert!(matches!(error, Error::Format { .. }));
+    let (line, column) = error.line_and_column(&xml[..]).unwrap();
+    assert_eq!(line, 31);
+    assert_eq!(column, "      </contributor>".len() + 1);
+    assert_eq!(
+        Error::<Infallible>::ShortCircuit.line_and_column(&xml[..]),
+        None
+    );
+}
+
+#[test]
+fn test_redirects_without_target() {
+    let xml = include_bytes!("../tests/fixtures/redirects.xml");
+    let targets = ["", "", "rhythm & blues"];
+    let pages = parse_to_vec(xml);
+    assert_eq!(pages.len(), 3);
+    for (page, target) in pages.iter().zip(targets.iter()) {
+        assert_eq!(
+            page.redirect_target.as_deref(),
+            Some(*target),
+            "{}",
+            page.title
+        );
+        assert_eq!(page.revisions.len(), 1);
+    }
+    let mut borrowed_targets = Vec::new();
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        borrowed_targets.push(page.redirect_target.map(Cow::into_owned));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(
+        borrowed_targets,
+        targets
+            .iter()
+            .map(|target| Some(target.to_string()))
+            .collect::<Vec<_>>()
+    );
+
+    // Only a `title` attribute is expected.
+    let xml = std::str::from_utf8(xml)
+        .unwrap()
+        .replace("<redirect />", r#"<redirect target="free" />"#);
+    let error = crate::pages(xml.as_bytes(), true)
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(
+        error,
+        Error::Format {
+            context: "expected no attribute but title on <redirect>",
+            ..
+        }
+    ));
+    let error = parse_borrowed::<_, _, Infallible>(xml.as_bytes(), |_| Ok(())).unwrap_err();
+    assert!(matches!(
+        error,
+        Error::Format {
+            context: "expected no attribute but title on <redirect>",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_extra_elements() {
+    let xml = include_bytes!("../tests/fixtures/extra-elements.xml");
+    let error = crate::pages(&xml[..], true).find_map(Result::err).unwrap();
+    assert!(matches!(error, Error::UnexpectedTag(_)));
+
+    let options = ParseOptions {
+        extra_elements: vec!["flowboard".into(), "score".into(), "archived".into()],
+        ..ParseOptions::default()
+    };
+    let mut pages = Vec::new();
+    parse_with_options::<_, _, Infallible>(
+        &xml[..],
+        &options,
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(pages.len(), 1);
+    let extra = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|&(name, text)| (name.to_owned(), text.to_owned()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    assert_eq!(
+        pages[0].extra,
+        extra(&[("archived", ""), ("flowboard", "Talk:free")])
+    );
+    assert_eq!(pages[0].revisions[0].extra, extra(&[("score", "3 & 4")]));
+    assert_eq!(
+        pages[0].revisions[0].sha1.as_deref(),
+        Some("a1s2d3f4g5h6j7k8l9z0x1c2v3b4n5m")
+    );
+}
+
+#[test]
+fn test_page_sha1() {
+    let xml = include_bytes!("../tests/fixtures/page-sha1.xml");
+    let parsed = parse_to_vec(xml);
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(
+        parsed[0].sha1.as_deref(),
+        Some("0000000000000000000000000000090")
+    );
+    assert_eq!(parsed[0].revisions.len(), 1);
+    assert_eq!(parsed[1].sha1, None);
+    let mut borrowed = Vec::new();
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        borrowed.push(page.into_owned());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(borrowed, parsed);
+
+    // Elements of the export schema are still an error out of place.
+    let xml = std::str::from_utf8(xml)
+        .unwrap()
+        .replace("<flagged />", "<title>checksum</title>");
+    assert!(pages(xml.as_bytes(), true).any(|page| page.is_err()));
+}
+
+#[test]
+fn test_missing_origin() {
+    let xml = include_bytes!("../tests/fixtures/missing-origin.xml");
+    let check = |pages: &[Page]| {
+        let fields = |revision: &Revision| {
+            (
+                revision.origin,
+                revision.model.to_string(),
+                revision.format.to_string(),
+                revision.minor,
+            )
+        };
+        let fields: Vec<Vec<_>> = pages
+            .iter()
+            .map(|page| page.revisions.iter().map(fields).collect())
+            .collect();
+        let field = |model: &str, format: &str, minor| (0, model.into(), format.into(), minor);
+        assert_eq!(
+            fields,
+            [
+                vec![
+                    field("wikitext", "text/x-wiki", false),
+                    field("wikitext", "text/x-wiki", true)
+                ],
+                vec![field("Scribunto", "text/plain", false)],
+            ]
+        );
+    };
+    check(&parse_to_vec(xml));
+    let mut pages = Vec::new();
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        pages.push(page.into_owned());
+        Ok(())
+    })
+    .unwrap();
+    check(&pages);
+}
+
+#[test]
+fn test_old_schema_versions() {
+    let xml = include_bytes!("../tests/fixtures/export-0.6.xml");
+    let pages = parse_to_vec(xml);
+    assert_eq!(pages.len(), 2);
+    let revisions = &pages[0].revisions;
+    assert_eq!(revisions.len(), 2);
+    assert!(revisions[0].minor);
+    assert_eq!(revisions[0].origin, 0);
+    assert_eq!(revisions[0].model, "wikitext");
+    assert_eq!(revisions[0].format, "text/x-wiki");
+    assert_eq!(
+        revisions[0].sha1.as_deref(),
+        Some("0k8k4z1m2j3f8h9z4x1b6v7c2n3m5l9")
+    );
+    assert_eq!(revisions[1].parent_id, Some(1000));
+    assert!(revisions[1].comment.is_absent());
+    assert_eq!(pages[1].redirect_target.as_deref(), Some("free"));
+
+    // In versions 0.8 and 0.9, `<model>` and `<format>` come after `<sha1>`.
+    let xml = r#"<mediawiki version="0.8">
+  <siteinfo>
+  </siteinfo>
+  <page>
+    <title>MediaWiki:Common.css</title>
+    <ns>8</ns>
+    <id>1</id>
+    <revision>
+      <id>2</id>
+      <timestamp>2013-01-01T00:00:00Z</timestamp>
+      <contributor>
+        <ip>127.0.0.1</ip>
+      </contributor>
+      <text xml:space="preserve" bytes="0" />
+      <sha1>phoiac9h4m842xq45sp7s6u21eteeq1</sha1>
+      <model>css</model>
+      <format>text/css</format>
+    </revision>
+  </page>
+</mediawiki>"#;
+    let pages = parse_to_vec(xml.as_bytes());
+    assert_eq!(pages[0].revisions[0].model, "css");
+    assert_eq!(pages[0].revisions[0].format, "text/css");
+}
+
+#[test]
+fn test_verify_sha1() {
+    let options = ParseOptions {
+        verify_sha1: true,
+        ..ParseOptions::default()
+    };
+    let parse = |xml: &[u8]| {
+        let mut revisions = Vec::new();
+        parse_with_options::<_, _, Infallible>(
+            xml,
+            &options,
+            |page| {
+                revisions.extend(page.revisions);
+                Ok(())
+            },
+            true,
+        )
+        .map(|()| revisions)
+    };
+
+    // The deleted text has no SHA-1 and the empty text has the right one.
+    let revisions = parse(include_bytes!("../tests/fixtures/deleted-text.xml")).unwrap();
+    assert_eq!(revisions[1].text_sha1(), "phoiac9h4m842xq45sp7s6u21eteeq1");
+
+    let mut revision = revisions[1].clone();
+    revision.text = "Hello, world!".into();
+    assert_eq!(revision.text_sha1(), "hbbvduyzg9rvcn7s9tgwxvh7c4u19y1");
+    assert!(matches!(
+        revision.verify_sha1::<Infallible>(),
+        Err(Error::Sha1Mismatch { revision_id: 4002, ref actual, .. })
+            if actual == "hbbvduyzg9rvcn7s9tgwxvh7c4u19y1"
+    ));
+
+    assert!(matches!(
+        parse(include_bytes!("../tests/fixtures/export-0.6.xml")),
+        Err(Error::Sha1Mismatch {
+            revision_id: 1000,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_cdata() {
+    let xml = include_bytes!("../tests/fixtures/cdata.xml");
+    let check = |revisions: &[Revision]| {
+        assert_eq!(
+            &*revisions[0].text,
+            "local export = {}\nif a < b and c then return \"&amp;\" end\nreturn export"
+        );
+        assert_eq!(revisions[0].comment.as_visible(), Some("use a < b && c"));
+        assert_eq!(&*revisions[1].text, "-- <b> ]]>");
+    };
+
+    let options = ParseOptions {
+        verify_sha1: true,
+        ..ParseOptions::default()
+    };
+    let mut pages = Vec::new();
+    parse_with_options::<_, _, Infallible>(
+        &xml[..],
+        &options,
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    check(&pages[0].revisions);
+
+    let mut borrowed = Vec::new();
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        borrowed.push(page.into_owned());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(borrowed, pages);
+}
+
+#[test]
+fn test_comments() {
+    let xml = include_bytes!("../tests/fixtures/comments.xml");
+    let pages = parse_to_vec(xml);
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].title, "commented");
+    let revision = &pages[0].revisions[0];
+    assert_eq!(revision.comment.as_visible(), Some("before after"));
+    assert_eq!(&*revision.text, "text");
+    assert_eq!(pages[1].revisions.len(), 1);
+
+    let mut borrowed = Vec::new();
+    parse_borrowed::<_, _, Infallible>(&xml[..], |page| {
+        borrowed.push(page.into_owned());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(borrowed, pages);
+
+    // The comment in the template contains `<ns>0</ns>`.
+    let mut titles = Vec::new();
+    parse_articles::<_, Infallible>(xml, |page| {
+        titles.push(page.title);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(titles, ["commented"]);
+
+    // An empty `<comment />` is an empty edit summary, unlike a missing `<comment>`.
+    let xml = std::str::from_utf8(xml).unwrap().replace(
+        "<comment>before<!-- hidden --> after</comment>",
+        "<comment />",
+    );
+    let pages = parse_to_vec(xml.as_bytes());
+    assert_eq!(
+        pages[0].revisions[0].comment,
+        Comment::Visible(String::new())
+    );
+    assert_eq!(pages[1].revisions[0].comment, Comment::Absent);
+}
+
+#[cfg(feature = "memmap")]
+#[test]
+fn test_map_file() {
+    let xml = map_file::<_, Infallible>("tests/fixtures/pages.xml")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        parse_to_vec(&xml),
+        parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"))
+    );
+    let page = find_page("dictionnary", &xml).unwrap().unwrap();
+    assert_eq!(page.id, 30);
+    assert!(map_file::<_, Infallible>("tests/fixtures/pages.xml.zst")
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_skip_text() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let options = ParseOptions {
+        skip_text: true,
+        verify_sha1: true,
+        ..ParseOptions::default()
+    };
+    let mut pages = Vec::new();
+    parse_with_options::<_, _, Infallible>(
+        &xml[..],
+        &options,
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    let mut expected = parse_to_vec(xml);
+    for revision in expected.iter_mut().flat_map(|page| &mut page.revisions) {
+        assert!(!revision.text.is_empty());
+        revision.text = "".into();
+    }
+    assert_eq!(pages, expected);
+}
+
+#[test]
+fn test_raw_text() {
+    let xml = include_bytes!("../tests/fixtures/cdata.xml");
+    let mut texts = Vec::new();
+    Parser::new()
+        .raw_text(true)
+        .run::<_, _, Infallible>(&xml[..], |page| {
+            texts.extend(page.revisions.into_iter().map(|revision| revision.text));
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        texts.last().map(|text| &**text),
+        Some("-- &lt;b&gt; <![CDATA[]]]]><![CDATA[>]]>")
+    );
+    let pages = parse_to_vec(xml);
+    assert_eq!(
+        &*pages.last().unwrap().revisions.last().unwrap().text,
+        "-- <b> ]]>"
+    );
+}
+
+#[test]
+fn test_truncate_text() {
+    let xml = include_str!("../tests/fixtures/pages.xml").replace("much more", "much m\u{f6}re");
+    let mut pages = parse_to_vec(xml.as_bytes());
+    let revisions = &mut pages[0].revisions;
+    let text_len = revisions[0].text.len();
+    revisions[0].truncate_text(text_len);
+    assert_eq!(&*revisions[0].text, "A reference work & more.");
+    assert!(!revisions[0].text_truncated);
+    // Cutting in the middle of "ö" backs up to the preceding character boundary.
+    revisions[1].truncate_text("A reference work & much m".len() + 1);
+    assert_eq!(&*revisions[1].text, "A reference work & much m");
+    assert!(revisions[1].text_truncated);
+}
+
+#[cfg(feature = "gz")]
+#[test]
+fn test_multi_member_gzip() {
+    let mut titles = Vec::new();
+    parse_from_file::<_, _, Infallible>(
+        "tests/fixtures/pages-multi-member.xml.gz",
+        |page| {
+            titles.push(page.title);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(titles, ["dictionary", "Template:en-noun", "dictionnary"]);
+}
+
+#[cfg(feature = "bz2")]
+#[test]
+fn test_multistream_bzip2() {
+    let mut titles = Vec::new();
+    parse_from_file::<_, _, Infallible>(
+        "tests/fixtures/pages-multistream.xml.bz2",
+        |page| {
+            titles.push(page.title);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(titles, ["dictionary", "Template:en-noun", "dictionnary"]);
+}
+
+#[cfg(feature = "gz")]
+#[test]
+fn test_compression_from_magic() {
+    let path = std::env::temp_dir().join("cbor-mediawiki-dump-misnamed-gzip.xml");
+    std::fs::copy("tests/fixtures/pages-multi-member.xml.gz", &path).unwrap();
+    let mut titles = Vec::new();
+    parse_from_file::<_, _, Infallible>(
+        &path,
+        |page| {
+            titles.push(page.title);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(titles, ["dictionary", "Template:en-noun", "dictionnary"]);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd() {
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let mut from_file = Vec::new();
+    parse_from_file::<_, _, Infallible>(
+        "tests/fixtures/pages.xml.zst",
+        |page| {
+            from_file.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(from_file, pages);
+}
+
+/// `.xz` dumps are xz streams, which the LZMA decoder reads along with legacy `.lzma` streams.
+#[cfg(feature = "lzma")]
+#[test]
+fn test_xz() {
+    let path = "tests/fixtures/pages.xml.xz";
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let mut from_file = Vec::new();
+    parse_from_file::<_, _, Infallible>(
+        path,
+        |page| {
+            from_file.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(from_file, pages);
+
+    let reader =
+        decompress::<_, Infallible>(&include_bytes!("../tests/fixtures/pages.xml.xz")[..], None)
+            .unwrap();
+    let from_magic: Vec<_> = crate::pages(reader, true)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(from_magic, pages);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_parse_compressed() {
+    let zstd = include_bytes!("../tests/fixtures/pages.xml.zst");
+    let pages = parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    for compression in [None, Some(Compression::Zstd)] {
+        let mut from_zstd = Vec::new();
+        parse_compressed::<_, _, Infallible>(
+            &zstd[..],
+            compression,
+            |page| {
+                from_zstd.push(page);
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(from_zstd, pages);
     }
 }
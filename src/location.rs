@@ -0,0 +1,43 @@
+use std::io::{self, BufRead};
+
+use memchr::{memchr_iter, memrchr};
+
+/// Finds the line and column of the byte at `position` in `input`, such as the position
+/// of an [`Error`](crate::Error), by reading `input` up to it again.
+/// Both start at 1, and the column is counted in bytes.
+/// `input` must be the same (decompressed) XML that was parsed, from its start.
+pub fn line_and_column<R: BufRead>(mut input: R, position: usize) -> io::Result<(usize, usize)> {
+    let (mut line, mut column) = (1, 1);
+    let mut remaining = position;
+    while remaining > 0 {
+        let buf = input.fill_buf()?;
+        if buf.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let chunk = &buf[..remaining.min(buf.len())];
+        line += memchr_iter(b'\n', chunk).count();
+        column = match memrchr(b'\n', chunk) {
+            Some(newline) => chunk.len() - newline,
+            None => column + chunk.len(),
+        };
+        let len = chunk.len();
+        input.consume(len);
+        remaining -= len;
+    }
+    Ok((line, column))
+}
+
+#[test]
+fn test_line_and_column() {
+    let input = b"ab\ncd\n\nef";
+    let locate = |position| line_and_column(&input[..], position).unwrap();
+    assert_eq!(locate(0), (1, 1));
+    assert_eq!(locate(2), (1, 3));
+    assert_eq!(locate(3), (2, 1));
+    assert_eq!(locate(7), (4, 1));
+    assert_eq!(locate(9), (4, 3));
+    // Reading in small chunks gives the same result.
+    let reader = io::BufReader::with_capacity(2, &input[..]);
+    assert_eq!(line_and_column(reader, 8).unwrap(), (4, 2));
+    assert!(line_and_column(&input[..], 10).is_err());
+}
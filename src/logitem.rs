@@ -0,0 +1,99 @@
+use std::{convert::TryFrom, io::BufRead};
+
+use chrono::{DateTime, Utc};
+use quick_xml::{events::Event, name::QName, Reader};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parse_text, parse_timestamp, read_contributor_contents, read_event, read_tag_event, read_text,
+    skip_text, tag::Tag, Comment, Contributor, Error,
+};
+
+/// An entry of the log in a `pages-logging.xml` dump.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogItem {
+    pub id: u32,
+    pub timestamp: DateTime<Utc>,
+    pub contributor: Contributor,
+    pub comment: Comment,
+    /// For instance `delete` or `move`.
+    #[serde(rename = "type")]
+    pub log_type: String,
+    /// For instance `delete` or `move_redir`.
+    pub action: String,
+    /// The title of the page that the action was performed on.
+    /// `logtitle` and `params` are `None` if the action was deleted.
+    pub logtitle: Option<String>,
+    pub params: Option<String>,
+}
+
+/// Reads the next `<logitem>` element, or returns `None` at the `</mediawiki>` end tag.
+pub(crate) fn read_log_item<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<Option<LogItem>, Error<E>> {
+    buf.clear();
+    match read_tag_event(reader, buf)? {
+        Event::Start(start) if start.name() == QName(b"logitem") => (),
+        Event::End(end) if end.name() == QName(b"mediawiki") => return Ok(None),
+        _ => return Err(Error::format(reader, "expected <logitem> or </mediawiki>")),
+    }
+
+    let mut id = None;
+    let mut timestamp = None;
+    let mut contributor = None;
+    let mut comment = Comment::Absent;
+    let mut log_type = None;
+    let mut action = None;
+    let mut logtitle = None;
+    let mut params = None;
+    loop {
+        let (tag, is_empty, deleted) = match read_event(reader, buf)? {
+            Event::Text(_) => continue,
+            Event::End(end) if end.name() == Tag::LogItem.as_q_name() => break,
+            Event::Start(start) => (Tag::try_from(start.name()), false, false),
+            Event::Empty(start) => (
+                Tag::try_from(start.name()),
+                true,
+                start
+                    .try_get_attribute("deleted")
+                    .map_err(|_| Error::format(reader, "invalid deleted attribute"))?
+                    .is_some_and(|attr| attr.value.as_ref() == b"deleted"),
+            ),
+            _ => return Err(Error::format(reader, "expected an element in <logitem>")),
+        };
+        let tag = tag.map_err(Error::from_infallible)?;
+        match (tag, is_empty) {
+            (Tag::Id, false) => id = Some(parse_text(reader, buf, tag)?),
+            (Tag::Timestamp, false) => timestamp = Some(parse_timestamp(reader, buf)?),
+            (Tag::Contributor, true) if deleted => contributor = Some(Contributor::Deleted),
+            (Tag::Contributor, false) => {
+                contributor = Some(read_contributor_contents(reader, buf)?)
+            }
+            (Tag::Comment, true) if deleted => comment = Comment::Deleted,
+            (Tag::Comment, true) => comment = Comment::Visible(String::new()),
+            (Tag::Comment, false) => comment = Comment::Visible(read_text(reader, buf, tag)?),
+            (Tag::Type, false) => log_type = Some(read_text(reader, buf, tag)?),
+            (Tag::Action, false) => action = Some(read_text(reader, buf, tag)?),
+            (Tag::LogTitle, false) => logtitle = Some(read_text(reader, buf, tag)?),
+            (Tag::Params, true) => params = Some(String::new()),
+            (Tag::Params, false) => params = Some(read_text(reader, buf, tag)?),
+            // Replaces `<logtitle>` and `<params>` if the action was deleted.
+            (Tag::Text, true) if deleted => {}
+            _ => return Err(Error::format(reader, "unexpected element in <logitem>")),
+        }
+    }
+    skip_text(reader, buf)?;
+
+    let missing = |context| Error::format(reader, context);
+    Ok(Some(LogItem {
+        id: id.ok_or_else(|| missing("missing <id> in <logitem>"))?,
+        timestamp: timestamp.ok_or_else(|| missing("missing <timestamp> in <logitem>"))?,
+        contributor: contributor.ok_or_else(|| missing("missing <contributor> in <logitem>"))?,
+        comment,
+        log_type: log_type.ok_or_else(|| missing("missing <type> in <logitem>"))?,
+        action: action.ok_or_else(|| missing("missing <action> in <logitem>"))?,
+        logtitle,
+        params,
+    }))
+}
@@ -1,86 +1,2045 @@
-use std::{convert::TryFrom, path::PathBuf, str::FromStr};
+use std::{
+    convert::{Infallible, TryFrom},
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+};
 
-use cbor_mediawiki_dump::{parse_from_file, Error};
+use either::Either;
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressStyle};
 
-enum Format {
-    Cbor,
-    Bincode,
-    Jsonl,
-    MessagePack,
+use cbor_mediawiki_dump::{
+    count, decompress, open_file, parse_resilient, parse_with_options, Compression, Contributor,
+    ContributorFilter, Error, FormattedTimestamp, OutputFormat, Page, ParseOptions, Revision,
+    TimestampFormat,
+};
+#[cfg(feature = "parallel")]
+use cbor_mediawiki_dump::{parse_parallel, write_page, ConvertError};
+use serde::Serialize;
+
+/// Where the XML is read from: a file, or stdin if the path is `-`.
+/// `compression` overrides the detection from the extension and magic number,
+/// and `options` selects the pages that are parsed.
+struct Input {
+    path: PathBuf,
+    compression: Option<Compression>,
+    options: ParseOptions,
+    /// Parse on this many threads with `parse_parallel` (0 for one per CPU).
+    threads: Option<usize>,
+    /// Report malformed pages on stderr and skip them instead of stopping.
+    skip_malformed_pages: bool,
+    /// Stop after this many pages have been passed to the page processor.
+    limit: Option<u64>,
+    /// Shows how much of the input has been read and how many pages have been parsed.
+    #[cfg(feature = "progress")]
+    progress: Option<ProgressBar>,
 }
 
-impl FromStr for Format {
-    type Err = &'static str;
+impl Input {
+    fn open<E: std::error::Error>(&self) -> Result<Box<dyn BufRead>, Error<E>> {
+        #[cfg(feature = "progress")]
+        if let Some(progress) = &self.progress {
+            // Count the bytes before they are decompressed, so that the progress of a compressed
+            // file can be measured against its size.
+            return if self.path.as_os_str() == "-" {
+                decompress(
+                    io::BufReader::new(progress.wrap_read(io::stdin().lock())),
+                    self.compression,
+                )
+            } else {
+                let file = File::open(&self.path).map_err(|source| Error::Io {
+                    action: "open",
+                    source,
+                    path: self.path.clone(),
+                })?;
+                cbor_mediawiki_dump::decompress_with_path(
+                    io::BufReader::new(progress.wrap_read(file)),
+                    self.compression
+                        .or_else(|| Compression::from_extension(&self.path)),
+                    &self.path,
+                )
+            };
+        }
+        if self.path.as_os_str() == "-" {
+            decompress(io::stdin().lock(), self.compression)
+        } else {
+            open_file(&self.path, self.compression)
+        }
+    }
+
+    fn parse<F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
+        &self,
+        page_processor: F,
+        skip_header: bool,
+    ) -> Result<(), Error<E>> {
+        let reader = self.open()?;
+        let page_processor = limit_pages(self.limit, page_processor);
+        #[cfg(feature = "progress")]
+        let mut pages = 0u64;
+        #[cfg(feature = "progress")]
+        let page_processor = {
+            let (pages, mut page_processor) = (&mut pages, page_processor);
+            move |page| {
+                *pages += 1;
+                // Formatting the message for every page would slow down parsing of small pages.
+                if let Some(progress) = self.progress.as_ref() {
+                    if pages.is_multiple_of(1000) {
+                        progress.set_message(format!("{} pages", pages));
+                    }
+                }
+                page_processor(page)
+            }
+        };
+        let result = match self.threads {
+            #[cfg(feature = "parallel")]
+            Some(threads) => parse_parallel(reader, &self.options, threads, page_processor),
+            _ if self.skip_malformed_pages => parse_resilient(
+                reader,
+                &self.options,
+                page_processor,
+                |e| {
+                    eprintln!("skipping malformed page: {}", e);
+                    true
+                },
+                skip_header,
+            ),
+            _ => parse_with_options(reader, &self.options, page_processor, skip_header),
+        };
+        #[cfg(feature = "progress")]
+        if let Some(progress) = &self.progress {
+            progress.finish_with_message(format!("{} pages", pages));
+        }
+        // The position of an error is a byte offset into the decompressed XML, which is easier
+        // to find in an editor as a line. Only a file can be read again to count the lines.
+        if let Err(e) = &result {
+            if self.path.as_os_str() != "-" {
+                if let Some((line, column)) =
+                    open_file::<_, Infallible>(&self.path, self.compression)
+                        .ok()
+                        .and_then(|input| e.line_and_column(input))
+                {
+                    eprintln!("error at line {}, column {} of the XML", line, column);
+                }
+            }
+        }
+        result
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(if s.eq_ignore_ascii_case("cbor") {
-            Self::Cbor
-        } else if s.eq_ignore_ascii_case("bincode") {
-            Self::Bincode
-        } else if s.eq_ignore_ascii_case("messagepack") {
-            Self::MessagePack
-        } else if s.eq_ignore_ascii_case("json") || s.eq_ignore_ascii_case("jsonl") {
-            Self::Jsonl
+/// Creates the progress bar for `--progress`: the percentage of a file that has been read
+/// and the estimated time left, or only the bytes read from stdin, whose length is unknown,
+/// with the number of pages parsed and the rate of reading in either case.
+/// The bytes are counted before decompression.
+#[cfg(feature = "progress")]
+fn progress_bar(path: &Path) -> anyhow::Result<ProgressBar> {
+    let (progress, template) = if path.as_os_str() == "-" {
+        (
+            ProgressBar::new_spinner(),
+            "{spinner} {bytes} ({bytes_per_sec}) {msg}",
+        )
+    } else {
+        (
+            ProgressBar::new(std::fs::metadata(path)?.len()),
+            "{wide_bar} {percent}% {bytes}/{total_bytes} ({bytes_per_sec}, {eta} left) {msg}",
+        )
+    };
+    progress.set_style(ProgressStyle::with_template(template)?);
+    Ok(progress)
+}
+
+/// Wraps `page_processor` so that parsing stops with [`Error::ShortCircuit`],
+/// which is not reported as an error, once it has been passed `limit` pages.
+fn limit_pages<F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error + 'static>(
+    limit: Option<u64>,
+    mut page_processor: F,
+) -> impl FnMut(Page) -> Result<(), Error<E>> {
+    let mut remaining = limit;
+    move |page| {
+        match &mut remaining {
+            Some(0) => return Err(Error::ShortCircuit),
+            Some(remaining) => *remaining -= 1,
+            None => {}
+        }
+        page_processor(page)?;
+        if remaining == Some(0) {
+            Err(Error::ShortCircuit)
         } else {
-            return Err("Invalid format");
+            Ok(())
+        }
+    }
+}
+
+/// Where the output is written: stdout, or a file that is compressed
+/// according to its extension (`.gz`, `.zst` or `.bz2`).
+enum Output {
+    Stdout(io::StdoutLock<'static>),
+    File(BufWriter<File>),
+    #[cfg(feature = "gz")]
+    Gz(flate2::write::GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+    #[cfg(feature = "bz2")]
+    Bz2(bzip2::write::BzEncoder<BufWriter<File>>),
+}
+
+impl Output {
+    fn create(path: Option<&Path>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::Stdout(io::stdout().lock())),
+        };
+        let compression = Compression::from_extension(path).unwrap_or(Compression::Uncompressed);
+        if !matches!(
+            compression,
+            Compression::Uncompressed | Compression::Gz | Compression::Zstd | Compression::Bz2
+        ) {
+            anyhow::bail!("{} compression is not supported for output", compression);
+        }
+        if cfg!(not(feature = "gz")) && compression == Compression::Gz
+            || cfg!(not(feature = "zstd")) && compression == Compression::Zstd
+            || cfg!(not(feature = "bz2")) && compression == Compression::Bz2
+        {
+            anyhow::bail!(
+                "{} compression was not enabled at compile time",
+                compression
+            );
+        }
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match compression {
+            #[cfg(feature = "gz")]
+            Compression::Gz => Self::Gz(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Self::Zstd(zstd::Encoder::new(file, 0)?),
+            #[cfg(feature = "bz2")]
+            Compression::Bz2 => Self::Bz2(bzip2::write::BzEncoder::new(
+                file,
+                bzip2::Compression::default(),
+            )),
+            _ => Self::File(file),
         })
     }
+
+    /// Writes the end of the compressed stream and flushes the file.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Stdout(mut stdout) => stdout.flush(),
+            Self::File(mut file) => file.flush(),
+            #[cfg(feature = "gz")]
+            Self::Gz(encoder) => encoder.finish()?.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.finish()?.flush(),
+            #[cfg(feature = "bz2")]
+            Self::Bz2(encoder) => encoder.finish()?.flush(),
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Stdout(stdout) => stdout,
+            Self::File(file) => file,
+            #[cfg(feature = "gz")]
+            Self::Gz(encoder) => encoder,
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder,
+            #[cfg(feature = "bz2")]
+            Self::Bz2(encoder) => encoder,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+/// Counts the bytes written through it so that output can be cut off
+/// after a certain size.
+struct CountingWriter<W> {
+    inner: W,
+    bytes_written: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            bytes_written: 0,
+        }
+    }
+
+    /// Returns `Err(Error::ShortCircuit)` once at least `max_bytes` have been written,
+    /// so that parsing stops after the page that crossed the limit.
+    fn check_limit<E: std::error::Error>(&self, max_bytes: Option<u64>) -> Result<(), Error<E>> {
+        match max_bytes {
+            Some(max_bytes) if self.bytes_written >= max_bytes => Err(Error::ShortCircuit),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes_written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes revision text to files named by the revision's SHA-1 for `--content-store`.
+struct ContentStore {
+    dir: PathBuf,
+}
+
+impl ContentStore {
+    /// Moves the text of a revision into the store, leaving `sha1` as the reference to it.
+    /// Revisions with the same SHA-1 share a file. Revisions without a SHA-1 (deleted text)
+    /// are left alone, but empty text is stored like any other.
+    ///
+    /// The text is written to a temporary file that is then renamed, so that an interrupted run
+    /// does not leave a partial file that later runs would take to be complete.
+    fn store<E: std::error::Error>(&self, revision: &mut Revision) -> Result<(), Error<E>> {
+        let Some(sha1) = &revision.sha1 else {
+            return Ok(());
+        };
+        let path = self.dir.join(sha1);
+        if !sha1.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "SHA-1 is not alphanumeric",
+            ))
+        } else if path.exists() {
+            Ok(())
+        } else {
+            let temporary = path.with_extension("tmp");
+            File::create(&temporary)
+                .and_then(|mut file| file.write_all(revision.text.as_bytes()))
+                .and_then(|()| std::fs::rename(&temporary, &path))
+        }
+        .map_err(|source| Error::Io {
+            action: "write revision text",
+            source,
+            path,
+        })?;
+        revision.text = "".into();
+        Ok(())
+    }
+}
+
+/// Changes made to each page before it is written.
+struct PageOptions {
+    truncate_text: Option<usize>,
+    content_store: Option<ContentStore>,
+}
+
+impl PageOptions {
+    fn prepare<E: std::error::Error>(&self, mut page: Page) -> Result<Page, Error<E>> {
+        // Store the text before truncating it, so that the stored text matches its SHA-1.
+        if let Some(content_store) = &self.content_store {
+            for revision in &mut page.revisions {
+                content_store.store(revision)?;
+            }
+        }
+        if let Some(max_bytes) = self.truncate_text {
+            page.truncate_text(max_bytes);
+        }
+        Ok(page)
+    }
+}
+
+/// Collects pages into arrays of up to `size` pages for `--batch`.
+struct Batch {
+    pages: Vec<Page>,
+    size: usize,
+}
+
+impl Batch {
+    fn new(size: usize) -> Self {
+        Self {
+            pages: Vec::with_capacity(size),
+            size,
+        }
+    }
+
+    /// Adds a page, returning the batch once it is full.
+    fn push(&mut self, page: Page) -> Option<Vec<Page>> {
+        self.pages.push(page);
+        if self.pages.len() >= self.size {
+            Some(std::mem::replace(
+                &mut self.pages,
+                Vec::with_capacity(self.size),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the last, partially filled batch, if there is one.
+    fn finish(self) -> Option<Vec<Page>> {
+        Some(self.pages).filter(|pages| !pages.is_empty())
+    }
+}
+
+/// Writes pages and revisions to the `pages` and `revisions` tables of an SQLite database
+/// for `--format sqlite`.
+#[cfg(feature = "sqlite")]
+struct SqliteWriter {
+    connection: rusqlite::Connection,
+    /// Rows inserted since the last commit.
+    rows: usize,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteWriter {
+    const ROWS_PER_TRANSACTION: usize = 10_000;
+
+    /// Creates the tables in a new database at `path`.
+    fn create(path: &std::path::Path) -> rusqlite::Result<Self> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE pages (
+                id INTEGER PRIMARY KEY,
+                title TEXT NOT NULL,
+                namespace INTEGER NOT NULL,
+                redirect_target TEXT,
+                restrictions TEXT
+            );
+            CREATE TABLE revisions (
+                id INTEGER PRIMARY KEY,
+                page_id INTEGER NOT NULL REFERENCES pages (id),
+                parent_id INTEGER,
+                timestamp TEXT NOT NULL,
+                contributor_kind TEXT NOT NULL,
+                username TEXT,
+                user_id INTEGER,
+                ip TEXT,
+                minor INTEGER NOT NULL,
+                comment TEXT,
+                model TEXT NOT NULL,
+                format TEXT NOT NULL,
+                sha1 TEXT,
+                text TEXT NOT NULL
+            );
+            BEGIN;",
+        )?;
+        Ok(Self {
+            connection,
+            rows: 0,
+        })
+    }
+
+    fn push_page(&mut self, page: &Page) -> rusqlite::Result<()> {
+        self.connection
+            .prepare_cached("INSERT INTO pages VALUES (?1, ?2, ?3, ?4, ?5)")?
+            .execute(rusqlite::params![
+                page.id,
+                page.title,
+                page.namespace,
+                page.redirect_target,
+                page.restrictions,
+            ])?;
+        let mut insert_revision = self.connection.prepare_cached(
+            "INSERT INTO revisions
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        )?;
+        for revision in &page.revisions {
+            let (contributor_kind, username, user_id, ip) = match &revision.contributor {
+                Contributor::User { username, id } => ("user", Some(username), Some(id), None),
+                Contributor::Ip { ip } => ("ip", None, None, Some(ip.to_string())),
+                _ => ("deleted", None, None, None),
+            };
+            insert_revision.execute(rusqlite::params![
+                revision.id,
+                page.id,
+                revision.parent_id,
+                revision
+                    .timestamp
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                contributor_kind,
+                username,
+                user_id,
+                ip,
+                revision.minor,
+                revision.comment.as_visible(),
+                revision.model.as_str(),
+                revision.format.as_str(),
+                revision.sha1,
+                revision.text,
+            ])?;
+        }
+        drop(insert_revision);
+
+        self.rows += 1 + page.revisions.len();
+        if self.rows >= Self::ROWS_PER_TRANSACTION {
+            self.connection.execute_batch("COMMIT; BEGIN;")?;
+            self.rows = 0;
+        }
+        Ok(())
+    }
+
+    /// Commits the last rows and creates the indexes.
+    fn finish(self) -> rusqlite::Result<()> {
+        self.connection.execute_batch(
+            "COMMIT;
+            CREATE INDEX pages_title ON pages (title);
+            CREATE INDEX revisions_page_id ON revisions (page_id);",
+        )
+    }
+}
+
+/// The schema of the records of `--format avro`: one revision with the fields of its page,
+/// like the columns of `--format parquet`, but with unions for the variants of
+/// [`Contributor`] and [`Comment`](cbor_mediawiki_dump::Comment).
+#[cfg(feature = "avro")]
+const AVRO_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "Revision",
+    "namespace": "org.mediawiki.dump",
+    "fields": [
+        {"name": "page_id", "type": "long"},
+        {"name": "title", "type": "string"},
+        {"name": "namespace", "type": "int"},
+        {"name": "redirect_target", "type": ["null", "string"]},
+        {"name": "revision_id", "type": "long"},
+        {"name": "parent_id", "type": ["null", "long"]},
+        {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-micros"}},
+        {"name": "contributor", "type": [
+            {"type": "record", "name": "DeletedContributor", "fields": []},
+            {"type": "record", "name": "IpContributor", "fields": [
+                {"name": "ip", "type": "string"}
+            ]},
+            {"type": "record", "name": "UserContributor", "fields": [
+                {"name": "username", "type": "string"},
+                {"name": "id", "type": "long"}
+            ]}
+        ]},
+        {"name": "minor", "type": "boolean"},
+        {"name": "comment", "type": [
+            "null",
+            "string",
+            {"type": "record", "name": "DeletedComment", "fields": []}
+        ]},
+        {"name": "origin", "type": "long"},
+        {"name": "model", "type": "string"},
+        {"name": "format", "type": "string"},
+        {"name": "text", "type": "string"},
+        {"name": "text_deleted", "type": "boolean"},
+        {"name": "sha1", "type": ["null", "string"]}
+    ]
+}"#;
+
+/// Writes one record per revision with [`AVRO_SCHEMA`] to an Avro object container file
+/// for `--format avro`. The header, with the schema, is written as soon as it is created,
+/// and the records are compressed with `codec` in blocks.
+#[cfg(feature = "avro")]
+struct AvroWriter<'a, W: Write> {
+    writer: apache_avro::Writer<'a, W>,
+}
+
+#[cfg(feature = "avro")]
+impl<'a, W: Write> AvroWriter<'a, W> {
+    fn new(
+        schema: &'a apache_avro::Schema,
+        writer: W,
+        codec: apache_avro::Codec,
+    ) -> apache_avro::AvroResult<Self> {
+        let mut writer = apache_avro::Writer::with_codec(schema, writer, codec)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    fn push_page(&mut self, page: &Page) -> apache_avro::AvroResult<()> {
+        use apache_avro::types::Value;
+
+        let optional = |value: Option<Value>| match value {
+            Some(value) => Value::Union(1, Box::new(value)),
+            None => Value::Union(0, Box::new(Value::Null)),
+        };
+        for revision in &page.revisions {
+            let contributor = match &revision.contributor {
+                Contributor::Ip { ip } => Value::Union(
+                    1,
+                    Box::new(Value::Record(vec![("ip".into(), ip.to_string().into())])),
+                ),
+                Contributor::User { username, id } => Value::Union(
+                    2,
+                    Box::new(Value::Record(vec![
+                        ("username".into(), username.as_str().into()),
+                        ("id".into(), Value::Long((*id).into())),
+                    ])),
+                ),
+                _ => Value::Union(0, Box::new(Value::Record(Vec::new()))),
+            };
+            let comment = match revision.comment.as_visible() {
+                Some(comment) => Value::Union(1, Box::new(comment.into())),
+                None if revision.comment.is_deleted() => {
+                    Value::Union(2, Box::new(Value::Record(Vec::new())))
+                }
+                None => Value::Union(0, Box::new(Value::Null)),
+            };
+            self.writer.append_value(Value::Record(vec![
+                ("page_id".into(), Value::Long(page.id.into())),
+                ("title".into(), page.title.as_str().into()),
+                ("namespace".into(), Value::Int(page.namespace)),
+                (
+                    "redirect_target".into(),
+                    optional(page.redirect_target.as_deref().map(Value::from)),
+                ),
+                ("revision_id".into(), Value::Long(revision.id.into())),
+                (
+                    "parent_id".into(),
+                    optional(revision.parent_id.map(|id| Value::Long(id.into()))),
+                ),
+                (
+                    "timestamp".into(),
+                    Value::TimestampMicros(revision.timestamp.timestamp_micros()),
+                ),
+                ("contributor".into(), contributor),
+                ("minor".into(), revision.minor.into()),
+                ("comment".into(), comment),
+                ("origin".into(), Value::Long(revision.origin.into())),
+                ("model".into(), revision.model.as_str().into()),
+                ("format".into(), revision.format.as_str().into()),
+                ("text".into(), (&*revision.text).into()),
+                ("text_deleted".into(), revision.text_deleted.into()),
+                (
+                    "sha1".into(),
+                    optional(revision.sha1.as_deref().map(Value::from)),
+                ),
+            ]))?;
+        }
+        Ok(())
+    }
+
+    fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    fn finish(self) -> apache_avro::AvroResult<W> {
+        self.writer.into_inner()
+    }
+}
+
+/// The messages of `--format protobuf`, generated from `proto/dump.proto` by the build script.
+#[cfg(feature = "protobuf")]
+mod proto {
+    include!(concat!(env!("OUT_DIR"), "/mediawiki_dump.rs"));
+}
+
+#[cfg(feature = "protobuf")]
+impl From<&Page> for proto::Page {
+    fn from(page: &Page) -> Self {
+        Self {
+            title: page.title.clone(),
+            raw_title: page.raw_title.clone(),
+            namespace: page.namespace,
+            id: page.id,
+            redirect_target: page.redirect_target.clone(),
+            restrictions: page.restrictions.clone(),
+            revisions: page.revisions.iter().map(proto::Revision::from).collect(),
+            sha1: page.sha1.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl From<&Revision> for proto::Revision {
+    fn from(revision: &Revision) -> Self {
+        use proto::{comment, contributor};
+
+        let contributor = match &revision.contributor {
+            Contributor::Ip { ip } => contributor::Kind::Ip(ip.to_string()),
+            Contributor::User { username, id } => contributor::Kind::User(contributor::User {
+                username: username.clone(),
+                id: *id,
+            }),
+            _ => contributor::Kind::Deleted(contributor::Deleted {}),
+        };
+        let comment = match revision.comment.as_visible() {
+            Some(comment) => Some(comment::Kind::Visible(comment.to_owned())),
+            None if revision.comment.is_deleted() => {
+                Some(comment::Kind::Deleted(comment::Deleted {}))
+            }
+            None => None,
+        };
+        Self {
+            id: revision.id,
+            parent_id: revision.parent_id,
+            timestamp: revision.timestamp.timestamp(),
+            contributor: Some(proto::Contributor {
+                kind: Some(contributor),
+            }),
+            origin: revision.origin,
+            minor: revision.minor,
+            comment: comment.map(|kind| proto::Comment { kind: Some(kind) }),
+            model: revision.model.to_string(),
+            format: revision.format.to_string(),
+            text: revision.text.to_string(),
+            text_bytes: revision.text_bytes,
+            text_deleted: revision.text_deleted,
+            sha1: revision.sha1.clone(),
+        }
+    }
+}
+
+/// Converts `page` to JSON with the revision timestamps rewritten in `timestamp_format`.
+fn to_json_with_timestamp_format(
+    page: &Page,
+    timestamp_format: &TimestampFormat,
+) -> serde_json::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(page)?;
+    if let Some(revisions) = value["revisions"].as_array_mut() {
+        for (revision, value) in page.revisions.iter().zip(revisions) {
+            value["timestamp"] = serde_json::to_value(timestamp_format.apply(revision.timestamp))?;
+        }
+    }
+    Ok(value)
+}
+
+/// Writes a line of JSONL containing either the single page in `pages`
+/// or, if `batched`, an array of all of them.
+fn write_json_line<W: Write>(
+    mut writer: W,
+    pages: &[Page],
+    batched: bool,
+    timestamp_format: &TimestampFormat,
+) -> Result<(), Error<Either<io::Error, serde_json::Error>>> {
+    if *timestamp_format == TimestampFormat::Rfc3339 {
+        if batched {
+            serde_json::to_writer(&mut writer, pages)
+        } else {
+            serde_json::to_writer(&mut writer, &pages[0])
+        }
+    } else {
+        pages
+            .iter()
+            .map(|page| to_json_with_timestamp_format(page, timestamp_format))
+            .collect::<serde_json::Result<Vec<_>>>()
+            .and_then(|values| {
+                if batched {
+                    serde_json::to_writer(&mut writer, &values)
+                } else {
+                    serde_json::to_writer(&mut writer, &values[0])
+                }
+            })
+    }
+    .map_err(|e| Error::Other(Either::Right(e)))?;
+    writeln!(writer).map_err(|e| Error::Other(Either::Left(e)))
+}
+
+/// Writes a record with `serialize`, preceded by its length in bytes as a little-endian `u32`
+/// if `framed` (`--framed`), so that it can be read back with `read_framed`.
+fn write_record<W: Write, E>(
+    writer: &mut W,
+    framed: bool,
+    serialize: impl FnOnce(&mut dyn Write) -> Result<(), E>,
+) -> Result<(), Either<io::Error, E>> {
+    if !framed {
+        return serialize(writer).map_err(Either::Right);
+    }
+    let mut record = Vec::new();
+    serialize(&mut record).map_err(Either::Right)?;
+    let len = u32::try_from(record.len()).map_err(|_| {
+        Either::Left(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "record is longer than 4 GiB",
+        ))
+    })?;
+    writer
+        .write_all(&len.to_le_bytes())
+        .and_then(|()| writer.write_all(&record))
+        .map_err(Either::Left)
+}
+
+/// Serializes pages on a pool of threads for `--serialize-threads`
+/// and writes the records in the order in which the pages were pushed.
+/// A record that is ready before the ones before it waits in `ready`.
+/// If too many pages are being serialized, [`push`](Self::push) waits for the oldest one,
+/// so that the parser does not get far ahead of the writer.
+#[cfg(feature = "parallel")]
+struct ParallelWriter {
+    pool: rayon::ThreadPool,
+    format: OutputFormat,
+    framed: bool,
+    sender: std::sync::mpsc::Sender<(u64, Result<Vec<u8>, ConvertError>)>,
+    receiver: std::sync::mpsc::Receiver<(u64, Result<Vec<u8>, ConvertError>)>,
+    ready: std::collections::BTreeMap<u64, Result<Vec<u8>, ConvertError>>,
+    pushed: u64,
+    written: u64,
+    max_in_flight: u64,
+}
+
+#[cfg(feature = "parallel")]
+impl ParallelWriter {
+    /// Serializes pages on `threads` threads, or one per CPU if `threads` is 0.
+    fn new(
+        threads: usize,
+        format: OutputFormat,
+        framed: bool,
+    ) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let max_in_flight = 4 * pool.current_num_threads() as u64;
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Ok(Self {
+            pool,
+            format,
+            framed,
+            sender,
+            receiver,
+            ready: Default::default(),
+            pushed: 0,
+            written: 0,
+            max_in_flight,
+        })
+    }
+
+    fn push<W: Write>(&mut self, writer: &mut W, page: Page) -> Result<(), ConvertError> {
+        let (index, sender, format, framed) =
+            (self.pushed, self.sender.clone(), self.format, self.framed);
+        self.pool.spawn(move || {
+            let mut record = Vec::new();
+            let result = write_record(&mut record, framed, |w| write_page(w, format, &page))
+                .map(|()| record)
+                .map_err(|e| e.either(ConvertError::Io, |e| e));
+            // The receiver is only gone if writing failed, and then the record is not needed.
+            let _ = sender.send((index, result));
+        });
+        self.pushed += 1;
+        self.write_ready(writer, self.max_in_flight)
+    }
+
+    /// Writes the records that are ready in order, waiting for more
+    /// until no more than `max_in_flight` pages are left.
+    fn write_ready<W: Write>(
+        &mut self,
+        writer: &mut W,
+        max_in_flight: u64,
+    ) -> Result<(), ConvertError> {
+        self.ready.extend(self.receiver.try_iter());
+        loop {
+            while let Some(record) = self.ready.remove(&self.written) {
+                writer.write_all(&record?)?;
+                self.written += 1;
+            }
+            if self.pushed - self.written <= max_in_flight {
+                return Ok(());
+            }
+            let (index, record) = self
+                .receiver
+                .recv()
+                .expect("the sender is kept until all records are written");
+            self.ready.insert(index, record);
+        }
+    }
+
+    /// Waits for the remaining pages and writes them.
+    fn finish<W: Write>(mut self, writer: &mut W) -> Result<(), ConvertError> {
+        self.write_ready(writer, 0)
+    }
+}
+
+/// Pages serialized as a map from their titles to the pages, for `--format yaml` and `--format toml`,
+/// which write a single document after all the pages have been parsed.
+struct PagesByTitle<'a>(&'a [Page]);
+
+impl Serialize for PagesByTitle<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|page| (&page.title, page)))
+    }
+}
+
+/// A line of `--format contributors`: who made a revision and how much it changed the length of the page.
+#[derive(Serialize)]
+struct ContributorRecord<'a> {
+    contributor: &'a Contributor,
+    page_id: u32,
+    revision_id: u32,
+    timestamp: FormattedTimestamp<'a>,
+    /// The [`Revision::size_delta`], which needs [`ParseOptions::size_deltas`].
+    bytes_changed: Option<i64>,
+}
+
+/// The records of `--format contributors` for the revisions of `page`, in the order of the dump.
+fn contributor_records<'a>(
+    page: &'a Page,
+    timestamp_format: &'a TimestampFormat,
+) -> impl Iterator<Item = ContributorRecord<'a>> {
+    page.revisions
+        .iter()
+        .map(move |revision| ContributorRecord {
+            contributor: &revision.contributor,
+            page_id: page.id,
+            revision_id: revision.id,
+            timestamp: timestamp_format.apply(revision.timestamp),
+            bytes_changed: revision.size_delta,
+        })
+}
+
+/// Writes pages as the elements of a single JSON array for `--format json-array`,
+/// one page at a time.
+struct JsonArrayWriter<W> {
+    writer: W,
+    pages_written: usize,
+}
+
+impl<W: Write> JsonArrayWriter<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            pages_written: 0,
+        }
+    }
+
+    fn push(
+        &mut self,
+        page: &Page,
+        timestamp_format: &TimestampFormat,
+    ) -> Result<(), Error<Either<io::Error, serde_json::Error>>> {
+        let separator = if self.pages_written == 0 { "[" } else { "," };
+        writeln!(self.writer, "{}", separator).map_err(|e| Error::Other(Either::Left(e)))?;
+        if *timestamp_format == TimestampFormat::Rfc3339 {
+            serde_json::to_writer(&mut self.writer, page)
+        } else {
+            to_json_with_timestamp_format(page, timestamp_format)
+                .and_then(|value| serde_json::to_writer(&mut self.writer, &value))
+        }
+        .map_err(|e| Error::Other(Either::Right(e)))?;
+        self.pages_written += 1;
+        Ok(())
+    }
+
+    /// Closes the array, which is `[]` if there were no pages.
+    fn finish(mut self) -> io::Result<W> {
+        if self.pages_written == 0 {
+            writeln!(self.writer, "[]")?;
+        } else {
+            writeln!(self.writer, "\n]")?;
+        }
+        Ok(self.writer)
+    }
+}
+
+/// A row of `--format csv` or `tsv`: one revision with the fields of its page.
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    page_id: u32,
+    title: &'a str,
+    namespace: i32,
+    revision_id: u32,
+    parent_id: Option<u32>,
+    timestamp: FormattedTimestamp<'a>,
+    contributor_kind: &'static str,
+    /// The username, or the IP address for an anonymous edit.
+    contributor_name: Option<String>,
+    contributor_id: Option<u32>,
+    minor: bool,
+    comment: Option<&'a str>,
+    model: &'a str,
+    format: &'a str,
+    sha1: &'a str,
+    text_len: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+}
+
+/// Writes one row per revision for `--format csv` and `tsv`.
+/// The text is left out unless `include_text` is set.
+struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+    include_text: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    const HEADERS: [&'static str; 15] = [
+        "page_id",
+        "title",
+        "namespace",
+        "revision_id",
+        "parent_id",
+        "timestamp",
+        "contributor_kind",
+        "contributor_name",
+        "contributor_id",
+        "minor",
+        "comment",
+        "model",
+        "format",
+        "sha1",
+        "text_len",
+    ];
+
+    /// Writes the header row, so that it is there even if there are no revisions.
+    fn new(writer: W, delimiter: u8, include_text: bool) -> csv::Result<Self> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .from_writer(writer);
+        if include_text {
+            writer.write_record(Self::HEADERS.iter().chain(&["text"]))?;
+        } else {
+            writer.write_record(Self::HEADERS)?;
+        }
+        Ok(Self {
+            writer,
+            include_text,
+        })
+    }
+
+    fn push_page(&mut self, page: &Page, timestamp_format: &TimestampFormat) -> csv::Result<()> {
+        for revision in &page.revisions {
+            let (contributor_kind, contributor_name, contributor_id) = match &revision.contributor {
+                Contributor::User { username, id } => ("user", Some(username.clone()), Some(*id)),
+                Contributor::Ip { ip } => ("ip", Some(ip.to_string()), None),
+                _ => ("deleted", None, None),
+            };
+            self.writer.serialize(CsvRow {
+                page_id: page.id,
+                title: &page.title,
+                namespace: page.namespace,
+                revision_id: revision.id,
+                parent_id: revision.parent_id,
+                timestamp: timestamp_format.apply(revision.timestamp),
+                contributor_kind,
+                contributor_name,
+                contributor_id,
+                minor: revision.minor,
+                comment: revision.comment.as_visible(),
+                model: &revision.model,
+                format: &revision.format,
+                sha1: revision.sha1.as_deref().unwrap_or_default(),
+                text_len: revision.text_len(),
+                text: Some(&*revision.text).filter(|_| self.include_text),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Passes the buffered rows on to the underlying writer, and flushes it.
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+}
+
+/// Prints the usage and the formats enabled at compile time for `--help`.
+/// The other options are described in the README.
+fn print_help() {
+    let formats: Vec<_> = OutputFormat::ALL.iter().map(OutputFormat::name).collect();
+    println!(
+        "Usage: {} [-f FILE] [-F FORMAT] [-o OUTPUT] [OPTIONS]\n\n\
+         Converts a MediaWiki XML dump (pages-articles.xml by default, or - for stdin) to another format.\n\n\
+         Formats (default cbor): {}\n\n\
+         See the README for the other options.",
+        env!("CARGO_PKG_NAME"),
+        formats.join(", ")
+    );
+}
+
+/// The `--contributor` and `--user-id` options, if there are any.
+fn contributor_filter(
+    args: &mut pico_args::Arguments,
+) -> Result<Option<ContributorFilter>, pico_args::Error> {
+    let contributors: Vec<String> = args.values_from_str("--contributor")?;
+    let user_ids: Vec<u32> = args.values_from_str("--user-id")?;
+    if contributors.is_empty() && user_ids.is_empty() {
+        return Ok(None);
+    }
+    let mut filter = ContributorFilter::default();
+    for contributor in &contributors {
+        filter.add_contributor(contributor);
+    }
+    filter.user_ids.extend(user_ids);
+    Ok(Some(filter))
 }
 
 fn main() -> anyhow::Result<()> {
     let mut args = pico_args::Arguments::from_env();
-    #[allow(clippy::redundant_closure)]
-    let pages_xml_path = args
-        .opt_value_from_os_str(["-f", "--file"], |p| PathBuf::try_from(p))?
-        .unwrap_or_else(|| "pages-articles.xml".into());
-    let format: Format = args
+    if args.contains(["-h", "--help"]) {
+        print_help();
+        return Ok(());
+    }
+    #[allow(clippy::redundant_closure, clippy::unnecessary_fallible_conversions)]
+    let input = Input {
+        path: args
+            .opt_value_from_os_str(["-f", "--file"], |p| PathBuf::try_from(p))?
+            .unwrap_or_else(|| "pages-articles.xml".into()),
+        compression: args.opt_value_from_str("--compression")?,
+        options: ParseOptions {
+            title: args.opt_value_from_str("--title-filter")?,
+            redirects: args.opt_value_from_str("--redirects")?.unwrap_or_default(),
+            since: args.opt_value_from_str("--since")?,
+            until: args.opt_value_from_str("--until")?,
+            contributors: contributor_filter(&mut args)?,
+            skip_empty_pages: args.contains("--skip-empty-pages"),
+            latest_only: args.contains("--latest"),
+            verify_sha1: args.contains("--verify-sha1"),
+            skip_text: args.contains("--no-text"),
+            raw_text: args.contains("--raw-text"),
+            size_deltas: args.contains("--size-deltas"),
+            wikibase_entities: args.contains("--wikibase-entities"),
+            multi_document: args.contains("--multi-document"),
+            extra_elements: args.values_from_str("--extra-element")?,
+            normalize_titles: args.opt_value_from_str("--normalize-titles")?,
+            buffer_capacity: args
+                .opt_value_from_str("--buffer-capacity")?
+                .unwrap_or(ParseOptions::DEFAULT_BUFFER_CAPACITY),
+            ..ParseOptions::default()
+        },
+        threads: args.opt_value_from_str("--threads")?,
+        skip_malformed_pages: args.contains("--skip-malformed-pages"),
+        limit: args.opt_value_from_str("--limit")?,
+        #[cfg(feature = "progress")]
+        progress: None,
+    };
+    let progress = args.contains("--progress");
+    if cfg!(not(feature = "progress")) && progress {
+        anyhow::bail!("--progress requires the progress feature");
+    }
+    #[cfg(feature = "progress")]
+    let input = Input {
+        progress: if progress {
+            Some(progress_bar(&input.path)?)
+        } else {
+            None
+        },
+        ..input
+    };
+    if cfg!(not(feature = "parallel")) && input.threads.is_some() {
+        anyhow::bail!("--threads requires the parallel feature");
+    }
+    if input.threads.is_some() && input.skip_malformed_pages {
+        anyhow::bail!("--skip-malformed-pages is not supported with --threads");
+    }
+    if input.threads.is_some() && input.options.multi_document {
+        anyhow::bail!("--multi-document is not supported with --threads");
+    }
+    if args.contains("--stats") {
+        let counts = count(input.open::<Infallible>()?)?;
+        eprintln!("pages: {}", counts.pages);
+        eprintln!("revisions: {}", counts.revisions);
+        eprintln!("redirects: {}", counts.redirects);
+        for (namespace, pages) in counts.namespaces {
+            eprintln!("pages in namespace {}: {}", namespace, pages);
+        }
+        return Ok(());
+    }
+    if args.contains("--validate") {
+        let (mut pages, mut revisions) = (0u64, 0u64);
+        let result = input.parse::<_, Infallible>(
+            |page| {
+                pages += 1;
+                revisions += page.revisions.len() as u64;
+                Ok(())
+            },
+            true,
+        );
+        if let Err(e) = result {
+            eprintln!("invalid after {} valid pages: {:?}", pages, e);
+            return Err(e.into());
+        }
+        eprintln!("valid: {} pages, {} revisions", pages, revisions);
+        return Ok(());
+    }
+    let format: OutputFormat = args
         .opt_value_from_str(["-F", "--format"])?
-        .unwrap_or(Format::Cbor);
+        .unwrap_or(OutputFormat::Cbor);
+    // The byte counts of `--format contributors` are the size deltas,
+    // which must be computed before revisions are filtered.
+    let mut input = input;
+    input.options.size_deltas |= matches!(format, OutputFormat::Contributors);
+    #[allow(clippy::redundant_closure, clippy::unnecessary_fallible_conversions)]
+    let output_path = args.opt_value_from_os_str(["-o", "--output"], |p| PathBuf::try_from(p))?;
+    if format.requires_output_file() && output_path.is_none() {
+        anyhow::bail!("--output is required for Parquet and SQLite");
+    }
+    // Concatenated dumps can repeat the ids that are the primary keys of the tables.
+    #[cfg(feature = "sqlite")]
+    if matches!(format, OutputFormat::Sqlite) && input.options.multi_document {
+        anyhow::bail!("--multi-document is not supported with --format sqlite");
+    }
+    let max_output_bytes: Option<u64> = args.opt_value_from_str("--max-output-bytes")?;
+    #[cfg(feature = "avro")]
+    let avro_codec: apache_avro::Codec = args
+        .opt_value_from_str("--avro-codec")?
+        .unwrap_or(apache_avro::Codec::Deflate(Default::default()));
+    if max_output_bytes.is_some() && format.requires_output_file() {
+        anyhow::bail!("--max-output-bytes is not supported for Parquet and SQLite");
+    }
+    let page_options = PageOptions {
+        truncate_text: args.opt_value_from_str("--truncate-text")?,
+        content_store: args.opt_value_from_os_str("--content-store", |p| {
+            Ok::<_, Infallible>(ContentStore { dir: p.into() })
+        })?,
+    };
+    if input.options.skip_text
+        && (page_options.content_store.is_some() || input.options.verify_sha1)
+    {
+        anyhow::bail!("--content-store and --verify-sha1 need the text that --no-text skips");
+    }
+    if input.options.raw_text && (page_options.content_store.is_some() || input.options.verify_sha1)
+    {
+        anyhow::bail!(
+            "--content-store and --verify-sha1 need the unescaped text, not the text kept by --raw-text"
+        );
+    }
+    if let Some(content_store) = &page_options.content_store {
+        std::fs::create_dir_all(&content_store.dir)?;
+    }
+    let timestamp_format: TimestampFormat = args
+        .opt_value_from_str("--timestamp-format")?
+        .unwrap_or_default();
+    let include_text = args.contains("--include-text");
+    if include_text && !matches!(format, OutputFormat::Csv | OutputFormat::Tsv) {
+        anyhow::bail!("--include-text is only supported for CSV and TSV");
+    }
+    let batch_size: Option<usize> = args.opt_value_from_str("--batch")?;
+    if batch_size == Some(0) {
+        anyhow::bail!("--batch must be at least 1");
+    }
+    if batch_size.is_some() && !matches!(format, OutputFormat::Cbor | OutputFormat::Jsonl) {
+        anyhow::bail!("--batch is only supported for CBOR and JSONL");
+    }
+    let framed = args.contains("--framed");
+    // Bincode is not self-describing, so the pages could not be read back from the records:
+    // the `Contributor` of a `Page` is tagged by a field inside the map.
+    if framed && !matches!(format, OutputFormat::Cbor | OutputFormat::MessagePack) {
+        anyhow::bail!("--framed is only supported for CBOR and MessagePack");
+    }
+    let serialize_threads: Option<usize> = args.opt_value_from_str("--serialize-threads")?;
+    if cfg!(not(feature = "parallel")) && serialize_threads.is_some() {
+        anyhow::bail!("--serialize-threads requires the parallel feature");
+    }
+    if serialize_threads.is_some()
+        && !matches!(
+            format,
+            OutputFormat::Cbor | OutputFormat::Bincode | OutputFormat::MessagePack
+        )
+    {
+        anyhow::bail!("--serialize-threads is only supported for CBOR, Bincode and MessagePack");
+    }
+    if serialize_threads.is_some() && (batch_size.is_some() || max_output_bytes.is_some()) {
+        anyhow::bail!("--serialize-threads is not supported with --batch or --max-output-bytes");
+    }
+    // The pages are kept in memory until the whole document is written.
+    if matches!(format, OutputFormat::Yaml | OutputFormat::Toml) && input.limit.is_none() {
+        anyhow::bail!("--format yaml and --format toml require --limit");
+    }
+
+    // Parquet and SQLite write to the output path themselves.
+    let mut output = CountingWriter::new(Output::create(
+        output_path
+            .as_deref()
+            .filter(|_| !format.requires_output_file()),
+    )?);
 
-    let stdout = std::io::stdout();
-    let mut stdout = stdout.lock();
+    #[cfg(feature = "parallel")]
+    if let Some(threads) = serialize_threads {
+        let mut writer = ParallelWriter::new(threads, format, framed)?;
+        input.parse(
+            |page| {
+                let page = page_options.prepare(page)?;
+                writer.push(&mut output, page).map_err(Error::Other)
+            },
+            true,
+        )?;
+        writer.finish(&mut output)?;
+        output.inner.finish()?;
+        return Ok(());
+    }
 
     match format {
-        Format::Cbor => {
-            parse_from_file(
-                &pages_xml_path,
-                |page| serde_cbor::to_writer(&mut stdout, &page).map_err(Error::Other),
+        OutputFormat::Cbor => {
+            let mut batch = batch_size.map(Batch::new);
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    match &mut batch {
+                        Some(batch) => {
+                            if let Some(pages) = batch.push(page) {
+                                write_record(&mut output, framed, |w| {
+                                    serde_cbor::to_writer(w, &pages)
+                                })
+                                .map_err(Error::Other)?;
+                            }
+                        }
+                        None => {
+                            write_record(&mut output, framed, |w| serde_cbor::to_writer(w, &page))
+                                .map_err(Error::Other)?
+                        }
+                    }
+                    output.check_limit(max_output_bytes)
+                },
                 true,
             )?;
+            if let Some(pages) = batch.and_then(Batch::finish) {
+                write_record(&mut output, framed, |w| serde_cbor::to_writer(w, &pages))?;
+            }
         }
-        Format::Bincode => {
+        OutputFormat::Bincode => {
             use bincode::Options;
             let options = bincode::DefaultOptions::new()
                 .with_fixint_encoding()
                 .allow_trailing_bytes();
-            parse_from_file(
-                &pages_xml_path,
-                |page| options.serialize_into(&mut stdout, &page).map_err(Error::Other),
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    write_record(&mut output, framed, |w| options.serialize_into(w, &page))
+                        .map_err(Error::Other)?;
+                    output.check_limit(max_output_bytes)
+                },
+                true,
+            )?;
+        }
+        OutputFormat::Jsonl => {
+            let mut batch = batch_size.map(Batch::new);
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    match &mut batch {
+                        Some(batch) => {
+                            if let Some(pages) = batch.push(page) {
+                                write_json_line(&mut output, &pages, true, &timestamp_format)?;
+                            }
+                        }
+                        None => write_json_line(
+                            &mut output,
+                            std::slice::from_ref(&page),
+                            false,
+                            &timestamp_format,
+                        )?,
+                    }
+                    output.check_limit(max_output_bytes)
+                },
+                true,
+            )?;
+            if let Some(pages) = batch.and_then(Batch::finish) {
+                write_json_line(&mut output, &pages, true, &timestamp_format)?;
+            }
+        }
+        OutputFormat::JsonArray => {
+            let mut writer = JsonArrayWriter::new(&mut output);
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    writer.push(&page, &timestamp_format)?;
+                    writer.writer.check_limit(max_output_bytes)
+                },
+                true,
+            )?;
+            writer.finish()?;
+        }
+        OutputFormat::Yaml | OutputFormat::Toml => {
+            let mut pages = Vec::new();
+            input.parse::<_, Infallible>(
+                |page| {
+                    pages.push(page_options.prepare(page)?);
+                    Ok(())
+                },
+                true,
+            )?;
+            let pages = PagesByTitle(&pages);
+            if matches!(format, OutputFormat::Yaml) {
+                serde_yaml::to_writer(&mut output, &pages)?;
+            } else {
+                output.write_all(toml::to_string(&pages)?.as_bytes())?;
+            }
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let delimiter = if matches!(format, OutputFormat::Tsv) {
+                b'\t'
+            } else {
+                b','
+            };
+            let mut writer = CsvWriter::new(&mut output, delimiter, include_text)?;
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    writer
+                        .push_page(&page, &timestamp_format)
+                        .map_err(Error::Other)?;
+                    // The rows are only counted once they reach the underlying writer,
+                    // but flushing after every page is slow and hurts compression.
+                    if max_output_bytes.is_some() {
+                        writer
+                            .flush()
+                            .map_err(|e| Error::Other(csv::Error::from(e)))?;
+                    }
+                    writer.get_ref().check_limit(max_output_bytes)
+                },
+                true,
+            )?;
+            writer.flush()?;
+        }
+        OutputFormat::Contributors => {
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    for record in contributor_records(&page, &timestamp_format) {
+                        serde_json::to_writer(&mut output, &record)
+                            .map_err(|e| Error::Other(Either::Right(e)))?;
+                        writeln!(output).map_err(|e| Error::Other(Either::Left(e)))?;
+                    }
+                    output.check_limit(max_output_bytes)
+                },
+                true,
+            )?;
+        }
+        OutputFormat::MessagePack => {
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    write_record(&mut output, framed, |w| rmp_serde::encode::write(w, &page))
+                        .map_err(Error::Other)?;
+                    output.check_limit(max_output_bytes)
+                },
                 true,
             )?;
         }
-        Format::Jsonl => {
-            parse_from_file(
-                &pages_xml_path,
+        #[cfg(feature = "arrow")]
+        OutputFormat::Arrow => {
+            use arrow_ipc::writer::StreamWriter;
+            use cbor_mediawiki_dump::RevisionBatches;
+
+            let mut batches = RevisionBatches::new(8192);
+            let mut writer = StreamWriter::try_new(&mut output, &batches.schema())?;
+            input.parse(
                 |page| {
-                    use std::io::Write;
-                    use either::Either;
-                    serde_json::to_writer(&mut stdout, &page).map_err(|e| Error::Other(Either::Right(e)))?;
-                    writeln!(&mut stdout).map_err(|e| Error::Other(Either::Left(e)))
+                    let page = page_options.prepare(page)?;
+                    for batch in batches.push_page(&page).map_err(Error::Other)? {
+                        writer.write(&batch).map_err(Error::Other)?;
+                    }
+                    writer.get_ref().check_limit(max_output_bytes)
                 },
                 true,
             )?;
+            if let Some(batch) = batches.finish()? {
+                writer.write(&batch)?;
+            }
+            writer.finish()?;
         }
-        Format::MessagePack => {
-            use serde::Serialize as _;
-            let mut serializer = rmp_serde::encode::Serializer::new(&mut stdout);
-            parse_from_file(
-                &pages_xml_path,
-                |page| page.serialize(&mut serializer).map_err(Error::Other),
+        #[cfg(feature = "avro")]
+        OutputFormat::Avro => {
+            let schema = apache_avro::Schema::parse_str(AVRO_SCHEMA)?;
+            let mut writer = AvroWriter::new(&schema, &mut output, avro_codec)?;
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    writer.push_page(&page).map_err(Error::Other)?;
+                    writer.get_ref().check_limit(max_output_bytes)
+                },
                 true,
             )?;
+            writer.finish()?;
+        }
+        #[cfg(feature = "protobuf")]
+        OutputFormat::Protobuf => {
+            use prost::Message;
+
+            let mut buf = Vec::new();
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    buf.clear();
+                    proto::Page::from(&page)
+                        .encode_length_delimited(&mut buf)
+                        .expect("a Vec has enough capacity");
+                    output.write_all(&buf).map_err(Error::Other)?;
+                    output.check_limit(max_output_bytes)
+                },
+                true,
+            )?;
+        }
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => {
+            use cbor_mediawiki_dump::RevisionBatches;
+            use parquet::{arrow::ArrowWriter, errors::ParquetError};
+
+            let output_path = output_path.expect("checked above");
+            let output = std::fs::File::create(&output_path)?;
+            let mut batches = RevisionBatches::new(8192);
+            let mut writer = ArrowWriter::try_new(output, batches.schema(), None)?;
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    for batch in batches
+                        .push_page(&page)
+                        .map_err(|e| Error::Other(ParquetError::from(e)))?
+                    {
+                        writer.write(&batch).map_err(Error::Other)?;
+                    }
+                    Ok(())
+                },
+                true,
+            )?;
+            if let Some(batch) = batches.finish()? {
+                writer.write(&batch)?;
+            }
+            writer.close()?;
+        }
+        #[cfg(feature = "sqlite")]
+        OutputFormat::Sqlite => {
+            let output_path = output_path.expect("checked above");
+            // Start from an empty database, as `File::create` does for Parquet.
+            match std::fs::remove_file(&output_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            let mut writer = SqliteWriter::create(&output_path)?;
+            input.parse(
+                |page| {
+                    let page = page_options.prepare(page)?;
+                    writer.push_page(&page).map_err(Error::Other)
+                },
+                true,
+            )?;
+            writer.finish()?;
         }
     }
 
+    output.inner.finish()?;
+
     Ok(())
 }
+
+#[test]
+fn test_max_output_bytes() {
+    let max_output_bytes = 100;
+    let mut output = CountingWriter::new(Vec::new());
+    let mut pages_written = 0;
+    let mut largest_page = 0;
+    cbor_mediawiki_dump::parse(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| {
+            let before = output.bytes_written;
+            serde_cbor::to_writer(&mut output, &page).map_err(Error::Other)?;
+            pages_written += 1;
+            largest_page = largest_page.max(output.bytes_written - before);
+            output.check_limit(Some(max_output_bytes))
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(pages_written, 1);
+    assert_eq!(output.bytes_written, output.inner.len() as u64);
+    assert!(output.bytes_written <= max_output_bytes + largest_page);
+}
+
+#[test]
+fn test_batch() {
+    let mut output = Vec::new();
+    let mut batch = Batch::new(2);
+    cbor_mediawiki_dump::parse(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| {
+            if let Some(pages) = batch.push(page) {
+                serde_cbor::to_writer(&mut output, &pages).map_err(Error::Other)?;
+            }
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    let last = batch.finish().unwrap();
+    serde_cbor::to_writer(&mut output, &last).unwrap();
+    let batch_sizes: Vec<_> = serde_cbor::Deserializer::from_slice(&output)
+        .into_iter::<Vec<serde_cbor::Value>>()
+        .map(|batch| batch.unwrap().len())
+        .collect();
+    assert_eq!(batch_sizes, [2, 1]);
+}
+
+#[test]
+fn test_content_store() {
+    let dir = std::env::temp_dir().join(format!("content-store-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let content_store = ContentStore { dir: dir.clone() };
+    let mut revisions = Vec::new();
+    cbor_mediawiki_dump::parse::<_, _, Infallible>(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| {
+            revisions.extend(page.revisions);
+            Err(Error::ShortCircuit)
+        },
+        true,
+    )
+    .unwrap();
+    revisions[1].text = revisions[0].text.clone();
+    revisions[1].sha1 = revisions[0].sha1.clone();
+    let text = revisions[0].text.clone();
+    for revision in &mut revisions {
+        content_store.store::<Infallible>(revision).unwrap();
+        assert_eq!(&*revision.text, "");
+    }
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+    assert_eq!(
+        std::fs::read_to_string(dir.join(revisions[0].sha1.as_ref().unwrap())).unwrap(),
+        *text
+    );
+
+    // The whole text is stored even when it is truncated in the output.
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::create_dir_all(&dir).unwrap();
+    let page_options = PageOptions {
+        truncate_text: Some(3),
+        content_store: Some(content_store),
+    };
+    let mut pages = Vec::new();
+    cbor_mediawiki_dump::parse::<_, _, Infallible>(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| {
+            pages.push(page);
+            Err(Error::ShortCircuit)
+        },
+        true,
+    )
+    .unwrap();
+    let revision = pages[0].revisions[0].clone();
+    assert!(revision.text.len() > 3);
+    page_options.prepare::<Infallible>(pages.remove(0)).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join(revision.sha1.as_ref().unwrap())).unwrap(),
+        *revision.text
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite() {
+    let path = std::env::temp_dir().join(format!("sqlite-test-{}.db", std::process::id()));
+    let mut writer = SqliteWriter::create(&path).unwrap();
+    cbor_mediawiki_dump::parse(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| writer.push_page(&page).map_err(Error::Other),
+        true,
+    )
+    .unwrap();
+    writer.finish().unwrap();
+
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    let count = |sql: &str| -> i64 { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT count(*) FROM pages"), 3);
+    assert_eq!(count("SELECT count(*) FROM revisions"), 4);
+    assert_eq!(
+        count(
+            "SELECT count(*) FROM revisions JOIN pages ON pages.id = page_id
+            WHERE title = 'dictionary'"
+        ),
+        2
+    );
+    assert_eq!(
+        count("SELECT count(*) FROM revisions WHERE contributor_kind = 'ip' AND ip = '127.0.0.1'"),
+        1
+    );
+    drop(connection);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_json_array() {
+    let mut writer = JsonArrayWriter::new(Vec::new());
+    cbor_mediawiki_dump::parse(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| writer.push(&page, &TimestampFormat::Rfc3339),
+        true,
+    )
+    .unwrap();
+    let output = writer.finish().unwrap();
+    let pages: Vec<Page> = serde_json::from_slice(&output).unwrap();
+    assert_eq!(pages.len(), 3);
+
+    let output = JsonArrayWriter::new(Vec::new()).finish().unwrap();
+    assert_eq!(output, b"[]\n");
+}
+
+#[test]
+fn test_csv() {
+    let xml = br#"<mediawiki>
+  <siteinfo>
+  </siteinfo>
+  <page>
+    <title>a, "b"</title>
+    <ns>0</ns>
+    <id>1</id>
+    <revision>
+      <id>2</id>
+      <timestamp>2004-03-04T10:31:12Z</timestamp>
+      <contributor>
+        <ip>127.0.0.1</ip>
+      </contributor>
+      <comment>line one
+line two</comment>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text bytes="7">a,b&#9;c
+d</text>
+      <sha1>abc</sha1>
+    </revision>
+  </page>
+</mediawiki>"#;
+    let to_rows = |delimiter, include_text, skip_text| {
+        let mut writer = CsvWriter::new(Vec::new(), delimiter, include_text).unwrap();
+        cbor_mediawiki_dump::parse_with_options(
+            &xml[..],
+            &ParseOptions {
+                skip_text,
+                ..ParseOptions::default()
+            },
+            |page| {
+                writer
+                    .push_page(&page, &TimestampFormat::Rfc3339)
+                    .map_err(Error::Other)
+            },
+            true,
+        )
+        .unwrap();
+        let output = writer.writer.into_inner().unwrap();
+        csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(output.as_slice())
+            .into_records()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+
+    let rows = to_rows(b',', false, false);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].len(), 15);
+    assert_eq!(&rows[0][1], r#"a, "b""#);
+    assert_eq!(&rows[0][5], "2004-03-04T10:31:12Z");
+    assert_eq!(&rows[0][6], "ip");
+    assert_eq!(&rows[0][7], "127.0.0.1");
+    assert_eq!(&rows[0][10], "line one\nline two");
+    assert_eq!(&rows[0][14], "7");
+
+    let rows = to_rows(b'\t', true, false);
+    assert_eq!(rows[0].len(), 16);
+    assert_eq!(&rows[0][15], "a,b\tc\nd");
+
+    // The length comes from the bytes attribute when the text is skipped.
+    let rows = to_rows(b',', false, true);
+    assert_eq!(&rows[0][14], "7");
+}
+
+#[test]
+fn test_output_compression() {
+    let extensions: &[&str] = &[
+        "jsonl",
+        #[cfg(feature = "gz")]
+        "jsonl.gz",
+        #[cfg(feature = "zstd")]
+        "jsonl.zst",
+        #[cfg(feature = "bz2")]
+        "jsonl.bz2",
+    ];
+    for extension in extensions {
+        let path =
+            std::env::temp_dir().join(format!("output-test-{}.{}", std::process::id(), extension));
+        let mut output = Output::create(Some(&path)).unwrap();
+        output.write_all(b"{}\n").unwrap();
+        output.finish().unwrap();
+        let mut written = Vec::new();
+        open_file::<_, Infallible>(&path, None)
+            .unwrap()
+            .read_to_end(&mut written)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, b"{}\n", "{}", extension);
+    }
+    assert!(Output::create(Some(Path::new("out.jsonl.xz"))).is_err());
+}
+
+#[test]
+fn test_limit_pages() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    for limit in [None, Some(0), Some(2), Some(100)].iter().copied() {
+        let mut titles = Vec::new();
+        parse_with_options::<_, _, Infallible>(
+            &xml[..],
+            &ParseOptions::default(),
+            limit_pages(limit, |page| {
+                titles.push(page.title);
+                Ok(())
+            }),
+            true,
+        )
+        .unwrap();
+        let all = ["dictionary", "Template:en-noun", "dictionnary"];
+        let expected = &all[..limit.map_or(all.len(), |limit| all.len().min(limit as usize))];
+        assert_eq!(titles, expected, "limit {:?}", limit);
+    }
+}
+
+#[test]
+fn test_pages_by_title() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let mut pages = Vec::new();
+    parse_with_options::<_, _, Infallible>(
+        &xml[..],
+        &ParseOptions::default(),
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    let by_title = |pages: &[Page]| {
+        pages
+            .iter()
+            .map(|page| (page.title.clone(), page.clone()))
+            .collect::<std::collections::BTreeMap<_, _>>()
+    };
+
+    let yaml = serde_yaml::to_string(&PagesByTitle(&pages)).unwrap();
+    assert!(yaml.starts_with("dictionary:\n"), "{}", yaml);
+    let from_yaml: std::collections::BTreeMap<String, Page> = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(from_yaml, by_title(&pages));
+
+    let toml = toml::to_string(&PagesByTitle(&pages)).unwrap();
+    let from_toml: std::collections::BTreeMap<String, Page> = toml::from_str(&toml).unwrap();
+    assert_eq!(from_toml, by_title(&pages));
+}
+
+#[test]
+fn test_write_record() {
+    use cbor_mediawiki_dump::{read_framed, FrameFormat};
+    let mut pages = Vec::new();
+    cbor_mediawiki_dump::parse::<_, _, Infallible>(
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    for format in [FrameFormat::Cbor, FrameFormat::MessagePack] {
+        let mut output = Vec::new();
+        for page in &pages {
+            if format == FrameFormat::Cbor {
+                write_record(&mut output, true, |w| serde_cbor::to_writer(w, page)).unwrap();
+            } else {
+                write_record(&mut output, true, |w| rmp_serde::encode::write(w, page)).unwrap();
+            }
+        }
+        let read: Vec<Page> = read_framed(&output[..], format)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, pages, "{:?}", format);
+    }
+}
+
+#[test]
+fn test_contributor_records() {
+    let timestamp_format = TimestampFormat::Unix;
+    let to_lines = |options: ParseOptions| {
+        let mut lines = Vec::new();
+        cbor_mediawiki_dump::parse_with_options::<_, _, Infallible>(
+            &include_bytes!("../tests/fixtures/pages.xml")[..],
+            &ParseOptions {
+                size_deltas: true,
+                ..options
+            },
+            |page| {
+                lines.extend(
+                    contributor_records(&page, &timestamp_format)
+                        .map(|record| serde_json::to_string(&record).unwrap()),
+                );
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+        lines
+    };
+    let lines = to_lines(ParseOptions::default());
+    assert_eq!(
+        lines[..2],
+        [
+            r#"{"contributor":{"type":"user","username":"Wonderfool","id":1},"page_id":16,"revision_id":1001,"timestamp":1078396272,"bytes_changed":null}"#,
+            r#"{"contributor":{"type":"ip","ip":"127.0.0.1"},"page_id":16,"revision_id":1002,"timestamp":1277942400,"bytes_changed":3}"#,
+        ]
+    );
+
+    // The change is from the revision before in the dump, even if that one is left out.
+    let lines = to_lines(ParseOptions {
+        latest_only: true,
+        ..ParseOptions::default()
+    });
+    assert_eq!(
+        lines[0],
+        r#"{"contributor":{"type":"ip","ip":"127.0.0.1"},"page_id":16,"revision_id":1002,"timestamp":1277942400,"bytes_changed":3}"#
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_writer() {
+    let mut pages = Vec::new();
+    for xml in [
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        include_bytes!("../tests/fixtures/history.xml"),
+        include_bytes!("../tests/fixtures/threads.xml"),
+    ] {
+        cbor_mediawiki_dump::parse::<_, _, Infallible>(
+            xml,
+            |page| {
+                pages.push(page);
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+    }
+    let pages: Vec<_> = std::iter::repeat_n(pages, 20).flatten().collect();
+    for framed in [false, true] {
+        let mut expected = Vec::new();
+        for page in &pages {
+            write_record(&mut expected, framed, |w| serde_cbor::to_writer(w, page)).unwrap();
+        }
+        let mut output = Vec::new();
+        let mut writer = ParallelWriter::new(3, OutputFormat::Cbor, framed).unwrap();
+        for page in pages.iter().cloned() {
+            writer.push(&mut output, page).unwrap();
+        }
+        writer.finish(&mut output).unwrap();
+        assert_eq!(output, expected);
+    }
+}
+
+#[cfg(feature = "avro")]
+#[test]
+fn test_avro_writer() {
+    use apache_avro::{types::Value, Codec, Reader, Schema};
+
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let mut pages = Vec::new();
+    cbor_mediawiki_dump::parse::<_, _, Infallible>(
+        &xml[..],
+        |page| {
+            pages.push(page);
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    let revisions: Vec<&Revision> = pages.iter().flat_map(|page| &page.revisions).collect();
+
+    let schema = Schema::parse_str(AVRO_SCHEMA).unwrap();
+    for codec in [
+        Codec::Null,
+        Codec::Deflate(Default::default()),
+        Codec::Snappy,
+    ] {
+        let mut writer = AvroWriter::new(&schema, Vec::new(), codec).unwrap();
+        assert!(!writer.get_ref().is_empty());
+        for page in &pages {
+            writer.push_page(page).unwrap();
+        }
+        let output = writer.finish().unwrap();
+
+        let records: Vec<Value> = Reader::new(&output[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), revisions.len());
+        for (record, revision) in records.iter().zip(&revisions) {
+            let Value::Record(fields) = record else {
+                panic!("{:?} is not a record", record)
+            };
+            let field = |name: &str| &fields.iter().find(|(field, _)| field == name).unwrap().1;
+            assert_eq!(field("revision_id"), &Value::Long(revision.id.into()));
+            let (expected_contributor, expected_comment) = (
+                match &revision.contributor {
+                    Contributor::Deleted => 0,
+                    Contributor::Ip { .. } => 1,
+                    _ => 2,
+                },
+                match revision.comment.as_visible() {
+                    Some(_) => 1,
+                    None if revision.comment.is_deleted() => 2,
+                    None => 0,
+                },
+            );
+            assert!(
+                matches!(field("contributor"), Value::Union(i, _) if *i == expected_contributor)
+            );
+            assert!(matches!(field("comment"), Value::Union(i, _) if *i == expected_comment));
+        }
+    }
+}
+
+#[cfg(feature = "protobuf")]
+#[test]
+fn test_protobuf() {
+    use prost::Message;
+
+    let mut pages = Vec::new();
+    for xml in [
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        include_bytes!("../tests/fixtures/deleted-text.xml"),
+    ] {
+        cbor_mediawiki_dump::parse::<_, _, Infallible>(
+            xml,
+            |page| {
+                pages.push(page);
+                Ok(())
+            },
+            true,
+        )
+        .unwrap();
+    }
+    let mut output = Vec::new();
+    for page in &pages {
+        proto::Page::from(page)
+            .encode_length_delimited(&mut output)
+            .unwrap();
+    }
+
+    let mut rest = &output[..];
+    for page in &pages {
+        let decoded = proto::Page::decode_length_delimited(&mut rest).unwrap();
+        assert_eq!(decoded, proto::Page::from(page));
+        assert_eq!(decoded.title, page.title);
+        for (decoded, revision) in decoded.revisions.iter().zip(&page.revisions) {
+            assert_eq!(decoded.timestamp, revision.timestamp.timestamp());
+            assert_eq!(&decoded.text, &*revision.text);
+            let contributor = decoded.contributor.as_ref().and_then(|c| c.kind.as_ref());
+            match (&revision.contributor, contributor) {
+                (Contributor::Deleted, Some(proto::contributor::Kind::Deleted(_))) => {}
+                (Contributor::Ip { ip }, Some(proto::contributor::Kind::Ip(decoded))) => {
+                    assert_eq!(&ip.to_string(), decoded)
+                }
+                (
+                    Contributor::User { username, id },
+                    Some(proto::contributor::Kind::User(decoded)),
+                ) => assert_eq!((username, *id), (&decoded.username, decoded.id)),
+                other => panic!("{:?}", other),
+            }
+            assert_eq!(
+                decoded.comment.is_none(),
+                revision.comment.as_visible().is_none() && !revision.comment.is_deleted()
+            );
+        }
+    }
+    assert!(rest.is_empty());
+}
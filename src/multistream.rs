@@ -0,0 +1,170 @@
+//! Random access to the pages of a multistream bzip2 dump,
+//! whose pages are compressed in separate streams of up to 100 pages
+//! so that a page can be decompressed without decompressing the pages before it.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use bzip2::read::MultiBzDecoder;
+
+use crate::{open_file, pages, split::skip_whitespace, Error, Page, Pages};
+
+/// A line of the index of a multistream dump (`*-multistream-index.txt.bz2`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The position in the dump of the bzip2 stream that contains the page.
+    pub offset: u64,
+    pub page_id: u32,
+    pub title: String,
+}
+
+/// Reads the index of a multistream dump, which has a line `offset:page_id:title` for every page.
+/// The index may be compressed, like the dump.
+/// A line that cannot be parsed is reported as [`Error::Format`] at the position of the line
+/// in the decompressed index.
+pub fn read_index<P: AsRef<Path>>(
+    path: P,
+) -> Result<impl Iterator<Item = Result<IndexEntry, Error>>, Error> {
+    let path = path.as_ref().to_owned();
+    let mut reader = open_file(&path, None)?;
+    let mut line = String::new();
+    let mut position = 0;
+    Ok(std::iter::from_fn(move || {
+        line.clear();
+        let line_start = position;
+        match reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(read) => {
+                position += read;
+                Some(
+                    parse_index_line(line.trim_end_matches(&['\n', '\r'][..])).ok_or(
+                        Error::Format {
+                            position: line_start,
+                            context: "expected offset:page_id:title in the index",
+                        },
+                    ),
+                )
+            }
+            Err(e) => Some(Err(Error::from_io("read", e, path.as_path()))),
+        }
+    }))
+}
+
+fn parse_index_line(line: &str) -> Option<IndexEntry> {
+    // Titles can contain colons, but offsets and page ids can't.
+    let mut fields = line.splitn(3, ':');
+    Some(IndexEntry {
+        offset: fields.next()?.parse().ok()?,
+        page_id: fields.next()?.parse().ok()?,
+        title: fields.next()?.to_owned(),
+    })
+}
+
+/// The index next to a multistream dump, named like the dump with `-index.txt.bz2`
+/// (or `-index.txt`, if it has been decompressed) in place of `.xml.bz2`,
+/// as in `enwiki-20240101-pages-articles-multistream-index.txt.bz2`.
+/// Returns `None` if there is no such file.
+pub(crate) fn sibling_index(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".xml.bz2")?;
+    ["-index.txt.bz2", "-index.txt"]
+        .iter()
+        .map(|suffix| path.with_file_name(format!("{}{}", stem, suffix)))
+        .find(|index| index.is_file())
+}
+
+/// Starts decompressing a multistream dump at `offset`, the start of one of its bzip2 streams,
+/// and returns an iterator over the pages from there to the end of the dump.
+pub fn seek_to_offset<P: AsRef<Path>>(
+    path: P,
+    offset: u64,
+) -> Result<Pages<Box<dyn BufRead>>, Error> {
+    let path = path.as_ref();
+    let mut file = File::open(path).map_err(|e| Error::from_io("open", e, path))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| Error::from_io("seek", e, path))?;
+    let mut reader = BufReader::new(MultiBzDecoder::new(BufReader::new(file)));
+    // Each stream but the first starts with the indentation of a `<page>` start tag.
+    skip_whitespace(&mut reader).map_err(|e| Error::from_io("decompress", e, path))?;
+    let mut pages = pages(Box::new(reader) as Box<dyn BufRead>, false);
+    // The `</mediawiki>` end tag at the end of the dump has no start tag in this part of it.
+    pages.reader.check_end_names(false);
+    Ok(pages)
+}
+
+/// Finds a page in a multistream dump by looking up its title in the index
+/// and decompressing only the stream that contains it.
+pub fn find_page_multistream<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    index_path: Q,
+    title: &str,
+) -> Result<Option<Page>, Error> {
+    let entry = match read_index(index_path)?
+        .find(|entry| entry.as_ref().map_or(true, |entry| entry.title == title))
+    {
+        Some(entry) => entry?,
+        None => return Ok(None),
+    };
+    for page in seek_to_offset(path, entry.offset)? {
+        let page = page?;
+        if page.id == entry.page_id {
+            return Ok(Some(page));
+        }
+    }
+    Ok(None)
+}
+
+#[test]
+fn test_multistream_index() {
+    let dump = "tests/fixtures/pages-multistream.xml.bz2";
+    let index: Vec<_> = read_index("tests/fixtures/pages-multistream-index.txt")
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(index.len(), 3);
+    assert_eq!(
+        index[1],
+        IndexEntry {
+            offset: 880,
+            page_id: 20,
+            title: "Template:en-noun".into(),
+        }
+    );
+
+    let titles: Vec<_> = seek_to_offset(dump, index[1].offset)
+        .unwrap()
+        .map(|page| page.unwrap().title)
+        .collect();
+    assert_eq!(titles, ["Template:en-noun", "dictionnary"]);
+
+    let page = find_page_multistream(
+        dump,
+        "tests/fixtures/pages-multistream-index.txt",
+        "dictionnary",
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(page.id, 30);
+    assert_eq!(
+        find_page_multistream(
+            dump,
+            "tests/fixtures/pages-multistream-index.txt",
+            "missing"
+        )
+        .unwrap(),
+        None
+    );
+
+    assert_eq!(
+        sibling_index(Path::new(dump)).as_deref(),
+        Some(Path::new("tests/fixtures/pages-multistream-index.txt"))
+    );
+    assert_eq!(sibling_index(Path::new("tests/fixtures/pages.xml")), None);
+    let page = crate::find_page_from_file(dump, "Template:en-noun")
+        .unwrap()
+        .unwrap();
+    assert_eq!(page.id, 20);
+}
@@ -0,0 +1,173 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::{Contributor, ContributorFilter, Revision, TitleCase};
+
+/// Options for [`parse_with_options`](crate::parse_with_options).
+/// The default options pass every page to the page processor, like [`parse`](crate::parse).
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// If set, pages in other namespaces are skipped.
+    pub namespaces: Option<Vec<i32>>,
+    /// If set, pages whose titles do not match are skipped.
+    pub title: Option<Regex>,
+    /// Whether to keep redirects, judging from whether [`Page::redirect_target`](crate::Page::redirect_target) is set.
+    pub redirects: Redirects,
+    /// If set, revisions before this time are left out.
+    pub since: Option<DateTime<Utc>>,
+    /// If set, revisions at or after this time are left out.
+    pub until: Option<DateTime<Utc>>,
+    /// If set, revisions by other contributors are left out.
+    /// They are still parsed, so this is no faster than keeping them.
+    pub contributors: Option<ContributorFilter>,
+    /// Skip pages that have no revisions left after filtering by `since`, `until` and `contributors`.
+    pub skip_empty_pages: bool,
+    /// Only keep the latest revision of each page (among those within `since` and `until` and by `contributors`),
+    /// which turns a full-history dump into the equivalent of `pages-articles`.
+    /// If several revisions have the same timestamp, the last one is kept.
+    pub latest_only: bool,
+    /// Check the text of each revision against its SHA-1 with [`Revision::verify_sha1`]
+    /// and fail with [`Error::Sha1Mismatch`](crate::Error::Sha1Mismatch) if they differ.
+    pub verify_sha1: bool,
+    /// The initial capacity of the buffer that the XML of each element is read into.
+    /// The buffer grows if an element is bigger, so this only avoids reallocating it.
+    pub buffer_capacity: usize,
+    /// If not 0, revisions of a page whose text is the same as that of one of
+    /// the last `text_cache_capacity` distinct texts of the page (judging from `<sha1>`)
+    /// share the text instead of each holding a copy,
+    /// which saves memory for pages with many reverts in full-history dumps.
+    pub text_cache_capacity: usize,
+    /// Skip the contents of `<text>` without unescaping them and leave [`Revision::text`] empty,
+    /// for uses that only need the metadata of pages and revisions.
+    /// [`verify_sha1`](Self::verify_sha1) has no effect, because there is no text to check.
+    pub skip_text: bool,
+    /// Keep the text of revisions as it is in the XML, with entities such as `&lt;` and `&amp;`
+    /// and CDATA sections left as they are, for comparing with the dump or handling entities elsewhere.
+    /// Comments in `<text>` are still left out.
+    /// [`verify_sha1`](Self::verify_sha1) has no effect, because the SHA-1 is of the unescaped text.
+    pub raw_text: bool,
+    /// The names of elements that are not part of the export schema, such as those that
+    /// some extensions add, that are allowed in a `<page>` after `<id>`
+    /// and in a `<revision>` after `<timestamp>`. The text of each one is stored in
+    /// [`Page::extra`](crate::Page::extra) or [`Revision::extra`], by name.
+    /// Other unknown elements are [`Error::UnexpectedTag`](crate::Error::UnexpectedTag),
+    /// except among or after the revisions of a page, where they are skipped.
+    pub extra_elements: Vec<String>,
+    /// If set, titles are normalized with [`normalize_title`](crate::normalize_title)
+    /// (use [`SiteInfo::title_case`](crate::SiteInfo::title_case) for the case of the wiki)
+    /// before they are matched against [`title`](Self::title), and the title from the dump
+    /// is kept in [`Page::raw_title`](crate::Page::raw_title) if it was changed.
+    pub normalize_titles: Option<TitleCase>,
+    /// Set [`Revision::size_delta`] to the change in the length of the text from the revision
+    /// before it in the dump. It is computed before revisions are left out by
+    /// [`since`](Self::since), [`until`](Self::until) or [`latest_only`](Self::latest_only).
+    pub size_deltas: bool,
+    /// Parse the text of revisions with a Wikibase content model (see
+    /// [`is_wikibase_model`](crate::is_wikibase_model)), such as the items of Wikidata,
+    /// set [`Revision::entity`] to a summary of it, and fail with
+    /// [`Error::Entity`](crate::Error::Entity) if it is not a JSON object.
+    /// Has no effect with [`skip_text`](Self::skip_text) or [`raw_text`](Self::raw_text).
+    pub wikibase_entities: bool,
+    /// Continue after the `</mediawiki>` end tag if another `<mediawiki>` document follows,
+    /// as in dumps that were concatenated, skipping the header of each document after the first.
+    /// Ignored by `parse_parallel`, which stops at the first `</mediawiki>`,
+    /// like [`parse_borrowed`](crate::parse_borrowed) and `parse_async`, which take no options.
+    pub multi_document: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            namespaces: None,
+            title: None,
+            redirects: Redirects::Include,
+            since: None,
+            until: None,
+            contributors: None,
+            skip_empty_pages: false,
+            latest_only: false,
+            verify_sha1: false,
+            buffer_capacity: Self::DEFAULT_BUFFER_CAPACITY,
+            text_cache_capacity: 0,
+            skip_text: false,
+            raw_text: false,
+            extra_elements: Vec::new(),
+            normalize_titles: None,
+            size_deltas: false,
+            wikibase_entities: false,
+            multi_document: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The default `buffer_capacity`, bigger than the maximum revision length (2 MiB).
+    pub const DEFAULT_BUFFER_CAPACITY: usize = 3 * 1024 * 1024;
+
+    /// Whether a page should be parsed, judging from the elements before its revisions.
+    pub(crate) fn accepts_page(&self, title: &str, namespace: i32, is_redirect: bool) -> bool {
+        self.redirects.accepts(is_redirect)
+            && self
+                .namespaces
+                .as_ref()
+                .is_none_or(|namespaces| namespaces.contains(&namespace))
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(title))
+    }
+
+    pub(crate) fn accepts_timestamp(&self, timestamp: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+
+    pub(crate) fn accepts_contributor(&self, contributor: &Contributor) -> bool {
+        self.contributors
+            .as_ref()
+            .is_none_or(|contributors| contributors.matches(contributor))
+    }
+
+    /// Whether a page should be passed to the page processor after its revisions were filtered.
+    pub(crate) fn accepts_revisions(&self, revisions: &[Revision]) -> bool {
+        !(self.skip_empty_pages && revisions.is_empty())
+    }
+}
+
+/// Which pages to keep for [`ParseOptions::redirects`].
+/// Pages that are left out are skipped before their revisions are parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Redirects {
+    /// `include`: keep redirects and other pages.
+    #[default]
+    Include,
+    /// `only`: keep only redirects, as for a map of redirects.
+    Only,
+    /// `exclude`: leave out redirects.
+    Exclude,
+}
+
+impl Redirects {
+    fn accepts(self, is_redirect: bool) -> bool {
+        match self {
+            Redirects::Include => true,
+            Redirects::Only => is_redirect,
+            Redirects::Exclude => !is_redirect,
+        }
+    }
+}
+
+impl FromStr for Redirects {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(Redirects::Include),
+            "only" => Ok(Redirects::Only),
+            "exclude" => Ok(Redirects::Exclude),
+            _ => Err("expected include, only or exclude"),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::BufRead;
+
+use rayon::prelude::*;
+
+use crate::{split::Chunk, Error, Page, ParseOptions};
+
+/// Pages are read into chunks of at least this many bytes (unless the input ends first),
+/// and each chunk is parsed on one thread.
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Like [`parse_with_options`](crate::parse_with_options), but parses pages on `threads` threads,
+/// or one per CPU if `threads` is 0. Pages are still passed to `page_processor` in order,
+/// on the calling thread.
+///
+/// The input is split into chunks by searching for `<page>` and `</page>`,
+/// which is safe because `<` is always escaped in text, as [`find_page`](crate::find_page)
+/// and [`parse_articles`](crate::parse_articles) also assume.
+/// The header, if any, is skipped.
+pub fn parse_parallel<R: BufRead, F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
+    mut reader: R,
+    options: &ParseOptions,
+    threads: usize,
+    mut page_processor: F,
+) -> Result<(), Error<E>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(Error::ThreadPool)?;
+    // Enough chunks to keep every thread busy while the slowest chunk is parsed.
+    let chunks_per_round = 2 * pool.current_num_threads();
+    let mut position = 0;
+    let mut read_error = None;
+    let mut more = true;
+    while more {
+        let mut chunks = Vec::with_capacity(chunks_per_round);
+        while chunks.len() < chunks_per_round && read_error.is_none() {
+            match read_chunk(&mut reader, &mut position, &mut read_error) {
+                Some(chunk) => chunks.push(chunk),
+                None => break,
+            }
+        }
+        more = chunks.len() == chunks_per_round && read_error.is_none();
+
+        let parsed: Vec<_> = pool.install(|| {
+            chunks
+                .par_iter()
+                .map(|chunk| chunk.parse(options))
+                .collect()
+        });
+        for pages in parsed {
+            for page in pages.map_err(Error::from_infallible)? {
+                match page_processor(page) {
+                    Err(Error::ShortCircuit) => return Ok(()),
+                    Err(e) => return Err(e),
+                    _ => {}
+                }
+            }
+        }
+    }
+    match read_error {
+        Some(e) => Err(Error::from_infallible(e)),
+        None => Ok(()),
+    }
+}
+
+/// Reads pages until the chunk reaches [`CHUNK_SIZE`] or the input ends.
+/// If reading a page fails, the error is stored in `error`
+/// and the chunk contains the pages before it.
+fn read_chunk<R: BufRead>(
+    reader: &mut R,
+    position: &mut usize,
+    error: &mut Option<Error>,
+) -> Option<Chunk> {
+    let mut chunk = Chunk::new();
+    while chunk.len() < CHUNK_SIZE {
+        match chunk.read_page(reader, position) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                *error = Some(e);
+                break;
+            }
+        }
+    }
+    if chunk.is_empty() {
+        None
+    } else {
+        Some(chunk)
+    }
+}
+
+#[test]
+fn test_parse_parallel() {
+    use std::convert::Infallible;
+
+    for xml in [
+        &include_bytes!("../tests/fixtures/pages.xml")[..],
+        include_bytes!("../tests/fixtures/history.xml"),
+        include_bytes!("../tests/fixtures/threads.xml"),
+    ] {
+        let mut pages = Vec::new();
+        parse_parallel::<_, _, Infallible>(xml, &ParseOptions::default(), 2, |page| {
+            pages.push(page);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(pages, crate::parse_to_vec(xml));
+    }
+
+    let xml = include_str!("../tests/fixtures/pages.xml");
+    let truncated = &xml[..xml.find("much more").unwrap()];
+    let result = parse_parallel::<_, _, Infallible>(
+        truncated.as_bytes(),
+        &ParseOptions::default(),
+        2,
+        |_| Ok(()),
+    );
+    assert!(matches!(result, Err(Error::UnexpectedEof { .. })));
+}
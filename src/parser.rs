@@ -0,0 +1,185 @@
+use std::io::BufRead;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::{
+    parse_with_options, ContributorFilter, Error, Page, ParseOptions, Redirects, TitleCase,
+};
+
+/// A builder for the arguments of [`parse_with_options`],
+/// so that they can be given by name instead of by position, as in
+/// `Parser::new().namespaces(&[0]).latest_only(true).run(reader, page_processor)`.
+/// The defaults are those of [`ParseOptions::default`], and the header is skipped,
+/// as with `parse(reader, page_processor, true)`.
+#[derive(Clone, Debug)]
+pub struct Parser {
+    options: ParseOptions,
+    skip_header: bool,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self {
+            options: ParseOptions::default(),
+            skip_header: true,
+        }
+    }
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from `options` instead of the default options.
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Whether the input starts with `<mediawiki>` and `<siteinfo>`, which are skipped,
+    /// rather than with the first `<page>`.
+    pub fn skip_header(mut self, skip_header: bool) -> Self {
+        self.skip_header = skip_header;
+        self
+    }
+
+    /// See [`ParseOptions::namespaces`].
+    pub fn namespaces(mut self, namespaces: &[i32]) -> Self {
+        self.options.namespaces = Some(namespaces.to_vec());
+        self
+    }
+
+    /// See [`ParseOptions::title`].
+    pub fn title(mut self, title: Regex) -> Self {
+        self.options.title = Some(title);
+        self
+    }
+
+    /// See [`ParseOptions::redirects`].
+    pub fn redirects(mut self, redirects: Redirects) -> Self {
+        self.options.redirects = redirects;
+        self
+    }
+
+    /// See [`ParseOptions::since`].
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.options.since = Some(since);
+        self
+    }
+
+    /// See [`ParseOptions::until`].
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.options.until = Some(until);
+        self
+    }
+
+    /// See [`ParseOptions::contributors`].
+    pub fn contributors(mut self, contributors: ContributorFilter) -> Self {
+        self.options.contributors = Some(contributors);
+        self
+    }
+
+    /// See [`ParseOptions::skip_empty_pages`].
+    pub fn skip_empty_pages(mut self, skip_empty_pages: bool) -> Self {
+        self.options.skip_empty_pages = skip_empty_pages;
+        self
+    }
+
+    /// See [`ParseOptions::latest_only`].
+    pub fn latest_only(mut self, latest_only: bool) -> Self {
+        self.options.latest_only = latest_only;
+        self
+    }
+
+    /// See [`ParseOptions::verify_sha1`].
+    pub fn verify_sha1(mut self, verify_sha1: bool) -> Self {
+        self.options.verify_sha1 = verify_sha1;
+        self
+    }
+
+    /// See [`ParseOptions::buffer_capacity`].
+    pub fn buffer_capacity(mut self, buffer_capacity: usize) -> Self {
+        self.options.buffer_capacity = buffer_capacity;
+        self
+    }
+
+    /// See [`ParseOptions::text_cache_capacity`].
+    pub fn text_cache_capacity(mut self, text_cache_capacity: usize) -> Self {
+        self.options.text_cache_capacity = text_cache_capacity;
+        self
+    }
+
+    /// See [`ParseOptions::skip_text`].
+    pub fn skip_text(mut self, skip_text: bool) -> Self {
+        self.options.skip_text = skip_text;
+        self
+    }
+
+    /// See [`ParseOptions::raw_text`].
+    pub fn raw_text(mut self, raw_text: bool) -> Self {
+        self.options.raw_text = raw_text;
+        self
+    }
+
+    /// See [`ParseOptions::multi_document`].
+    pub fn multi_document(mut self, multi_document: bool) -> Self {
+        self.options.multi_document = multi_document;
+        self
+    }
+
+    /// See [`ParseOptions::size_deltas`].
+    pub fn size_deltas(mut self, size_deltas: bool) -> Self {
+        self.options.size_deltas = size_deltas;
+        self
+    }
+
+    /// See [`ParseOptions::wikibase_entities`].
+    pub fn wikibase_entities(mut self, wikibase_entities: bool) -> Self {
+        self.options.wikibase_entities = wikibase_entities;
+        self
+    }
+
+    /// See [`ParseOptions::normalize_titles`].
+    pub fn normalize_titles(mut self, case: TitleCase) -> Self {
+        self.options.normalize_titles = Some(case);
+        self
+    }
+
+    /// Parses the dump in `reader` and passes each page to `page_processor`,
+    /// as [`parse_with_options`] does.
+    pub fn run<R: BufRead, F: FnMut(Page) -> Result<(), Error<E>>, E: std::error::Error>(
+        &self,
+        reader: R,
+        page_processor: F,
+    ) -> Result<(), Error<E>> {
+        parse_with_options(reader, &self.options, page_processor, self.skip_header)
+    }
+}
+
+#[test]
+fn test_parser() {
+    let xml = include_bytes!("../tests/fixtures/pages.xml");
+    let mut titles = Vec::new();
+    Parser::new()
+        .namespaces(&[0])
+        .latest_only(true)
+        .run::<_, _, std::convert::Infallible>(&xml[..], |page| {
+            assert_eq!(page.revisions.len(), 1);
+            titles.push(page.title);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(titles, ["dictionary", "dictionnary"]);
+
+    let mut pages = Vec::new();
+    Parser::new()
+        .buffer_capacity(0)
+        .run::<_, _, std::convert::Infallible>(&xml[..], |page| {
+            pages.push(page);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(pages, crate::parse_to_vec(xml));
+}
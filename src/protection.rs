@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// The groups that a page is protected for, by action, parsed from the `<restrictions>` of a page
+/// (`edit=autoconfirmed:move=sysop`) by [`Page::protection_levels`](crate::Page::protection_levels).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtectionLevels(pub HashMap<String, String>);
+
+impl ProtectionLevels {
+    /// Parses `action=group` pairs separated by colons, leaving out the pairs without `=`
+    /// or with an empty action or group. A group alone, as in dumps from old versions of MediaWiki,
+    /// protects the page from both editing and moving.
+    pub fn parse(restrictions: &str) -> Self {
+        let restrictions = restrictions.trim();
+        if !restrictions.is_empty() && !restrictions.contains(['=', ':']) {
+            return Self(
+                ["edit", "move"]
+                    .iter()
+                    .map(|action| (action.to_string(), restrictions.to_string()))
+                    .collect(),
+            );
+        }
+        Self(
+            restrictions
+                .split(':')
+                .filter_map(|pair| {
+                    let (action, group) = pair.split_once('=')?;
+                    let (action, group) = (action.trim(), group.trim());
+                    if action.is_empty() || group.is_empty() {
+                        None
+                    } else {
+                        Some((action.to_string(), group.to_string()))
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// The group needed for `action`, such as `edit` or `move`, if the page is protected from it.
+    pub fn get(&self, action: &str) -> Option<&str> {
+        self.0.get(action).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[test]
+fn test_protection_levels() {
+    let levels = ProtectionLevels::parse("edit=autoconfirmed:move=sysop");
+    assert_eq!(levels.get("edit"), Some("autoconfirmed"));
+    assert_eq!(levels.get("move"), Some("sysop"));
+    assert_eq!(levels.get("upload"), None);
+
+    let levels = ProtectionLevels::parse("sysop");
+    assert_eq!(levels.get("edit"), Some("sysop"));
+    assert_eq!(levels.get("move"), Some("sysop"));
+
+    let levels = ProtectionLevels::parse("edit=:move=sysop:upload:=sysop::create=sysop");
+    assert_eq!(levels.0.len(), 2);
+    assert_eq!(levels.get("move"), Some("sysop"));
+    assert_eq!(levels.get("create"), Some("sysop"));
+
+    assert!(ProtectionLevels::parse("").is_empty());
+}
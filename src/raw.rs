@@ -0,0 +1,247 @@
+use std::{
+    cell::Cell,
+    io::{self, BufRead, Read},
+};
+
+use quick_xml::Reader;
+
+use crate::{
+    parse_with_ranges, read_header, read_next_document, read_page_with_range, skip_bom, Error,
+    Page, PageAction, ParseOptions,
+};
+
+/// Keeps a copy of the bytes that have been consumed from `inner`,
+/// starting at the position `offset` in its input.
+/// The bytes before the position in `discard_before` are dropped at the next read.
+struct Recorder<'a, R> {
+    inner: R,
+    recorded: Vec<u8>,
+    offset: usize,
+    discard_before: &'a Cell<usize>,
+}
+
+impl<'a, R> Recorder<'a, R> {
+    fn new(inner: R, discard_before: &'a Cell<usize>) -> Self {
+        Self {
+            inner,
+            recorded: Vec::new(),
+            offset: 0,
+            discard_before,
+        }
+    }
+
+    /// Drops the recorded bytes before `position`, which must not be after the end of the recorded bytes.
+    fn discard(&mut self, position: usize) {
+        if position > self.offset {
+            self.recorded.drain(..position - self.offset);
+            self.offset = position;
+        }
+    }
+}
+
+impl<R: BufRead> Read for Recorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for Recorder<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.discard(self.discard_before.get());
+        if let Ok(available) = self.inner.fill_buf() {
+            self.recorded.extend_from_slice(&available[..amt]);
+        }
+        self.inner.consume(amt)
+    }
+}
+
+/// Like [`parse_with_options`](crate::parse_with_options), but also passes `page_processor`
+/// the XML of each page as it was in the input, from `<page>` to `</page>`,
+/// for re-emitting selected pages without any changes.
+///
+/// The bytes read from `reader` are copied into a buffer until the end of each page,
+/// so this is slower than parsing alone, and the buffer holds up to one page at a time.
+/// Use [`parse_slice_with_raw`] if the dump is already in memory.
+pub fn parse_with_raw<
+    R: BufRead,
+    F: FnMut(Page, &[u8]) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    mut reader: R,
+    options: &ParseOptions,
+    page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    // The byte order mark is not counted in the positions of pages, so it must not be recorded.
+    skip_bom(&mut reader)?;
+    let discard_before = Cell::new(0);
+    let mut reader = Reader::from_reader(Recorder::new(reader, &discard_before));
+    parse_recorded(&mut reader, options, page_processor, skip_header)
+}
+
+fn parse_recorded<
+    R: BufRead,
+    F: FnMut(Page, &[u8]) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    reader: &mut Reader<Recorder<'_, R>>,
+    options: &ParseOptions,
+    mut page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    let mut buf = Vec::with_capacity(options.buffer_capacity);
+    let discard_before = reader.get_ref().discard_before;
+
+    if skip_header {
+        read_header(reader, &mut buf)?;
+    }
+
+    loop {
+        // The pages that are skipped are dropped from the recording once they have been read.
+        while let Some((page, range)) = read_page_with_range(
+            reader,
+            &mut buf,
+            options,
+            &mut |_| PageAction::Keep,
+            &mut |range| discard_before.set(range.end),
+        )? {
+            let recorder = reader.get_mut();
+            let raw =
+                &recorder.recorded[range.start - recorder.offset..range.end - recorder.offset];
+            match page_processor(page, raw) {
+                Err(Error::ShortCircuit) => return Ok(()),
+                Err(e) => return Err(e),
+                _ => {}
+            }
+            recorder.discard(range.end);
+        }
+        if !(options.multi_document && read_next_document(reader, &mut buf)?) {
+            return Ok(());
+        }
+    }
+}
+
+/// Like [`parse_with_raw`], but for a dump that is already in memory,
+/// so the XML of each page is borrowed from `xml` instead of being copied.
+pub fn parse_slice_with_raw<
+    'a,
+    F: FnMut(Page, &'a [u8]) -> Result<(), Error<E>>,
+    E: std::error::Error,
+>(
+    xml: &'a [u8],
+    options: &ParseOptions,
+    mut page_processor: F,
+    skip_header: bool,
+) -> Result<(), Error<E>> {
+    parse_with_ranges(
+        xml,
+        options,
+        |page, range| page_processor(page, &xml[range]),
+        skip_header,
+    )
+}
+
+#[test]
+fn test_parse_with_raw() {
+    use std::{convert::Infallible, io::BufReader};
+
+    let xml = include_bytes!("../tests/fixtures/history.xml");
+    let mut from_slice = Vec::new();
+    parse_slice_with_raw::<_, Infallible>(
+        xml,
+        &ParseOptions::default(),
+        |page, raw| {
+            from_slice.push((page, raw));
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(from_slice.len(), 2);
+
+    // A small buffer makes pages span several reads.
+    let mut streamed = Vec::new();
+    parse_with_raw::<_, _, Infallible>(
+        BufReader::with_capacity(16, &xml[..]),
+        &ParseOptions::default(),
+        |page, raw| {
+            streamed.push((page, raw.to_vec()));
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(streamed.len(), from_slice.len());
+    for ((page, raw), (expected_page, expected_raw)) in streamed.iter().zip(&from_slice) {
+        assert_eq!(page, expected_page);
+        assert_eq!(raw, expected_raw);
+        assert!(raw.starts_with(b"<page>") && raw.ends_with(b"</page>"));
+    }
+
+    // The pages that are skipped are left out of the XML of the next page.
+    let options = ParseOptions {
+        title: Some(regex::Regex::new(&format!("^{}$", from_slice[1].0.title)).unwrap()),
+        ..ParseOptions::default()
+    };
+    let mut filtered = Vec::new();
+    parse_with_raw::<_, _, Infallible>(
+        &xml[..],
+        &options,
+        |_, raw| {
+            filtered.push(raw.to_vec());
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(filtered, [from_slice[1].1]);
+
+    // Only the page being read is kept in the recording, not the pages skipped before it.
+    let first_page = from_slice[0].1;
+    let pages_end = xml.len() - "</mediawiki>\n".len();
+    let mut many_pages = xml[..pages_end].to_vec();
+    for _ in 0..100 {
+        many_pages.extend_from_slice(b"\n  ");
+        many_pages.extend_from_slice(first_page);
+    }
+    many_pages.push(b'\n');
+    many_pages.extend_from_slice(&xml[pages_end..]);
+    let discard_before = Cell::new(0);
+    let mut reader = Reader::from_reader(Recorder::new(&many_pages[..], &discard_before));
+    let mut filtered = 0;
+    parse_recorded::<_, _, Infallible>(
+        &mut reader,
+        &options,
+        |_, _| {
+            filtered += 1;
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(filtered, 1);
+    assert!(reader.get_ref().recorded.len() < first_page.len());
+
+    let xml = include_bytes!("../tests/fixtures/bom.xml");
+    let mut raw_pages = Vec::new();
+    parse_with_raw::<_, _, Infallible>(
+        &xml[..],
+        &ParseOptions::default(),
+        |_, raw| {
+            raw_pages.push(raw.to_vec());
+            Ok(())
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(raw_pages.len(), 1);
+    assert!(raw_pages[0].starts_with(b"<page>") && raw_pages[0].ends_with(b"</page>"));
+}
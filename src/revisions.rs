@@ -0,0 +1,87 @@
+use std::{io::BufRead, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{pages, Error, Pages, Revision};
+
+/// The fields of a [`Page`](crate::Page) that identify it, paired with each of its revisions by [`revisions`].
+/// The title is shared by all the revisions of the page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageInfo {
+    pub id: u32,
+    pub title: Arc<str>,
+    pub namespace: i32,
+}
+
+/// An iterator over the revisions of all the pages of a dump, created by [`revisions`].
+pub struct Revisions<R> {
+    pages: Pages<R>,
+    page: Option<(PageInfo, std::vec::IntoIter<Revision>)>,
+}
+
+impl<R: BufRead> Iterator for Revisions<R> {
+    type Item = Result<(PageInfo, Revision), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((info, revisions)) = &mut self.page {
+                if let Some(revision) = revisions.next() {
+                    return Some(Ok((info.clone(), revision)));
+                }
+            }
+            let page = match self.pages.next()? {
+                Ok(page) => page,
+                Err(e) => return Some(Err(e)),
+            };
+            let info = PageInfo {
+                id: page.id,
+                title: page.title.into(),
+                namespace: page.namespace,
+            };
+            self.page = Some((info, page.revisions.into_iter()));
+        }
+    }
+}
+
+/// Like [`pages`], but yields each revision with the [`PageInfo`] of its page,
+/// in the order of the dump. Pages without revisions are skipped.
+///
+/// The revisions of a page are parsed together and moved out one at a time,
+/// so only the revisions of the current page that have not been yielded yet are kept.
+pub fn revisions<R: BufRead>(reader: R, skip_header: bool) -> Revisions<R> {
+    Revisions {
+        pages: pages(reader, skip_header),
+        page: None,
+    }
+}
+
+#[test]
+fn test_revisions() {
+    let xml = include_bytes!("../tests/fixtures/history.xml");
+    let expected: Vec<_> = crate::parse_to_vec(xml)
+        .into_iter()
+        .flat_map(|page| {
+            let (id, title, namespace) = (page.id, page.title, page.namespace);
+            page.revisions.into_iter().map(move |revision| {
+                (
+                    PageInfo {
+                        id,
+                        title: title.as_str().into(),
+                        namespace,
+                    },
+                    revision,
+                )
+            })
+        })
+        .collect();
+    let all = revisions(&xml[..], true)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(all.len(), 6);
+    assert_eq!(all, expected);
+    assert!(Arc::ptr_eq(&all[0].0.title, &all[4].0.title));
+
+    let mut truncated = revisions(&xml[..xml.len() / 2], true);
+    assert!(truncated.any(|revision| revision.is_err()));
+    assert!(truncated.next().is_none());
+}
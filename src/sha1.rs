@@ -0,0 +1,67 @@
+//! Conversion between the base-36 SHA-1 digests in `<sha1>` and the 20-byte digests
+//! that other tools and the MediaWiki API give in hexadecimal.
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Decodes a SHA-1 digest in the form used in `<sha1>`: base 36, padded with zeros to 31 digits.
+/// Uppercase digits and fewer than 31 digits are accepted.
+/// Returns `None` if there is an invalid digit or the number is too big for 20 bytes.
+pub fn sha1_to_hex(sha1: &str) -> Option<[u8; 20]> {
+    if sha1.is_empty() || sha1.len() > 31 {
+        return None;
+    }
+    let mut digest = [0u8; 20];
+    for digit in sha1.bytes() {
+        let mut carry = char::from(digit).to_digit(36)?;
+        // Multiply the big-endian number in `digest` by 36 in place and add the digit.
+        for byte in digest.iter_mut().rev() {
+            let value = u32::from(*byte) * 36 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(digest)
+}
+
+/// Encodes a SHA-1 digest in the form used in `<sha1>`: base 36, padded with zeros to 31 digits.
+pub fn hex_to_sha1_base36(digest: &[u8; 20]) -> String {
+    let mut digest = *digest;
+    let mut digits = [b'0'; 31];
+    for digit in digits.iter_mut().rev() {
+        // Divide the big-endian number in `digest` by 36 in place.
+        let mut remainder = 0u32;
+        for byte in digest.iter_mut() {
+            let value = remainder << 8 | u32::from(*byte);
+            *byte = (value / 36) as u8;
+            remainder = value % 36;
+        }
+        *digit = DIGITS[remainder as usize];
+    }
+    String::from_utf8(digits.to_vec()).expect("digits are ASCII")
+}
+
+#[test]
+fn test_sha1_base36() {
+    // The SHA-1 of the empty text, as in `<sha1>` and in hexadecimal.
+    let base36 = "phoiac9h4m842xq45sp7s6u21eteeq1";
+    let digest = [
+        0xda, 0x39, 0xa3, 0xee, 0x5e, 0x6b, 0x4b, 0x0d, 0x32, 0x55, 0xbf, 0xef, 0x95, 0x60, 0x18,
+        0x90, 0xaf, 0xd8, 0x07, 0x09,
+    ];
+    assert_eq!(sha1_to_hex(base36), Some(digest));
+    assert_eq!(sha1_to_hex(&base36.to_uppercase()), Some(digest));
+    assert_eq!(hex_to_sha1_base36(&digest), base36);
+
+    let mut small = [0; 20];
+    small[19] = 35;
+    assert_eq!(hex_to_sha1_base36(&small), format!("{:0>31}", "z"));
+    assert_eq!(sha1_to_hex("z"), Some(small));
+
+    assert_eq!(sha1_to_hex(""), None);
+    assert_eq!(sha1_to_hex("phoiac9h4m842xq45sp7s6u21etee-1"), None);
+    // 36^31 is more than 2^160.
+    assert_eq!(sha1_to_hex(&"z".repeat(31)), None);
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, convert::TryFrom, io::BufRead};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    name::QName,
+    Reader,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{read_event, read_text, tag::Tag, Error, TitleCase};
+
+/// The attributes of the `<mediawiki>` root element and the `<siteinfo>` at the start of a dump.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpHeader {
+    /// The language code of the wiki from `xml:lang`, for instance `en`.
+    pub lang: String,
+    /// The version of the export schema, for instance `0.10`.
+    pub version: String,
+    pub siteinfo: SiteInfo,
+}
+
+/// The contents of `<siteinfo>` at the start of a dump.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteInfo {
+    pub sitename: String,
+    pub dbname: String,
+    /// The URL of the main page.
+    pub base: String,
+    /// The MediaWiki version that produced the dump.
+    pub generator: String,
+    /// `first-letter` if the first letter of titles is always uppercase,
+    /// otherwise `case-sensitive`.
+    pub case: String,
+    pub namespaces: Namespaces,
+}
+
+/// The names of the namespaces in `<namespaces>` by their ids.
+/// The main namespace (0) has an empty name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Namespaces(pub HashMap<i32, String>);
+
+impl SiteInfo {
+    /// The case of titles from `case`, which is [`TitleCase::FirstLetter`]
+    /// unless it is `case-sensitive`.
+    pub fn title_case(&self) -> TitleCase {
+        self.case.parse().unwrap_or(TitleCase::FirstLetter)
+    }
+}
+
+impl Namespaces {
+    /// The name of the namespace with id `id`, for instance `Template` for 10.
+    pub fn name(&self, id: i32) -> Option<&str> {
+        self.0.get(&id).map(String::as_str)
+    }
+
+    /// The id of the namespace named `name`, for instance 10 for `Template`.
+    pub fn id(&self, name: &str) -> Option<i32> {
+        self.0
+            .iter()
+            .find(|(_, namespace)| namespace.as_str() == name)
+            .map(|(&id, _)| id)
+    }
+}
+
+/// Reads the `xml:lang` and `version` attributes of the `<mediawiki>` start tag.
+/// Missing attributes are left empty.
+pub(crate) fn parse_root_attributes<R: BufRead, E: std::error::Error>(
+    reader: &Reader<R>,
+    start: &BytesStart,
+) -> Result<(String, String), Error<E>> {
+    let attribute = |name: &str| -> Result<String, Error<E>> {
+        match start
+            .try_get_attribute(name)
+            .map_err(|_| Error::format(reader, "invalid attribute of <mediawiki>"))?
+        {
+            Some(attr) => attr
+                .unescape_value()
+                .map(String::from)
+                .map_err(|_| Error::format(reader, "invalid attribute of <mediawiki>")),
+            None => Ok(String::new()),
+        }
+    };
+    Ok((attribute("xml:lang")?, attribute("version")?))
+}
+
+/// Parses the elements of `<siteinfo>` up to and including the end tag.
+/// Unknown elements are skipped.
+pub(crate) fn parse_siteinfo<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<SiteInfo, Error<E>> {
+    let mut siteinfo = SiteInfo::default();
+    loop {
+        let name = match read_event(reader, buf)? {
+            Event::Text(_) | Event::Empty(_) => continue,
+            Event::End(end) if end.name() == Tag::SiteInfo.as_q_name() => break,
+            Event::Start(start) => start.name().as_ref().to_vec(),
+            _ => return Err(Error::format(reader, "expected an element in <siteinfo>")),
+        };
+        match Tag::try_from(name.as_slice()) {
+            Ok(tag @ Tag::SiteName) => siteinfo.sitename = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::DbName) => siteinfo.dbname = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::Base) => siteinfo.base = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::Generator) => siteinfo.generator = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::Case) => siteinfo.case = read_text(reader, buf, tag)?,
+            Ok(Tag::Namespaces) => siteinfo.namespaces = parse_namespaces(reader, buf)?,
+            _ => {
+                reader
+                    .read_to_end_into(QName(&name), buf)
+                    .map_err(|_| Error::format(reader, "invalid element in <siteinfo>"))?;
+            }
+        }
+    }
+    Ok(siteinfo)
+}
+
+fn parse_namespaces<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<Namespaces, Error<E>> {
+    let mut namespaces = HashMap::new();
+    loop {
+        match read_event(reader, buf)? {
+            Event::Text(_) => {}
+            Event::End(end) if end.name() == Tag::Namespaces.as_q_name() => break,
+            Event::Start(start) if start.name() == Tag::Namespace.as_q_name() => {
+                let key = namespace_key(reader, &start)?;
+                namespaces.insert(key, read_text(reader, buf, Tag::Namespace)?);
+            }
+            Event::Empty(start) if start.name() == Tag::Namespace.as_q_name() => {
+                namespaces.insert(namespace_key(reader, &start)?, String::new());
+            }
+            _ => {
+                return Err(Error::format(
+                    reader,
+                    "expected <namespace> in <namespaces>",
+                ))
+            }
+        }
+    }
+    Ok(Namespaces(namespaces))
+}
+
+fn namespace_key<R: BufRead, E: std::error::Error>(
+    reader: &Reader<R>,
+    start: &BytesStart,
+) -> Result<i32, Error<E>> {
+    let attr = start
+        .try_get_attribute("key")
+        .map_err(|_| Error::format(reader, "invalid key attribute of <namespace>"))?
+        .ok_or_else(|| Error::format(reader, "missing key attribute of <namespace>"))?;
+    std::str::from_utf8(&attr.value)
+        .ok()
+        .and_then(|key| key.parse().ok())
+        .ok_or_else(|| Error::format(reader, "invalid key attribute of <namespace>"))
+}
+
+#[test]
+fn test_namespaces() {
+    let mut reader = Reader::from_reader(&include_bytes!("../tests/fixtures/pages.xml")[..]);
+    let mut buf = Vec::new();
+    let header =
+        crate::read_dump_header::<_, std::convert::Infallible>(&mut reader, &mut buf).unwrap();
+    assert_eq!(header.lang, "en");
+    assert_eq!(header.version, "0.10");
+    let namespaces = &header.siteinfo.namespaces;
+    assert_eq!(namespaces.name(0), Some(""));
+    assert_eq!(namespaces.name(-2), Some("Media"));
+    assert_eq!(namespaces.name(10), Some("Template"));
+    assert_eq!(namespaces.name(4), None);
+    assert_eq!(namespaces.id("Module"), Some(828));
+    assert_eq!(namespaces.id(""), Some(0));
+}
@@ -0,0 +1,257 @@
+//! Splitting a dump into the XML of whole pages without parsing it.
+
+#[cfg(any(feature = "parallel", feature = "async"))]
+use std::io::Read;
+use std::io::{self, BufRead};
+
+#[cfg(any(feature = "parallel", feature = "async"))]
+use quick_xml::Reader;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::Error;
+#[cfg(any(feature = "parallel", feature = "async"))]
+use crate::{read_event, read_page, Page, ParseOptions};
+
+/// Appends the bytes from `position` in `reader` up to and including the next `</page>` end tag
+/// to `page_xml`, and returns the position of the `<page>` start tag,
+/// or `None` if the input ended before the next `<page>`.
+/// Because `<` is always escaped in text, searching for the tags cannot split a page.
+pub(crate) fn read_page_xml<R: BufRead, E: std::error::Error>(
+    reader: &mut R,
+    page_xml: &mut Vec<u8>,
+    position: &mut usize,
+) -> Result<Option<usize>, Error<E>> {
+    let len = page_xml.len();
+    let start_tag_end = match read_until_tag(reader, page_xml, b"<page>", *position)? {
+        Some(start_tag_end) => start_tag_end,
+        None => {
+            page_xml.truncate(len);
+            return Ok(None);
+        }
+    };
+    *position = read_until_tag(reader, page_xml, b"</page>", start_tag_end)?.ok_or(
+        Error::UnexpectedEof {
+            position: *position + page_xml.len() - len,
+        },
+    )?;
+    Ok(Some(start_tag_end - b"<page>".len()))
+}
+
+/// Consumes the ASCII whitespace at the start of `reader`, such as the indentation
+/// before a `<page>` start tag when reading from the middle of a dump.
+pub(crate) fn skip_whitespace<R: BufRead>(reader: &mut R) -> io::Result<()> {
+    loop {
+        let buf = reader.fill_buf()?;
+        let (whitespace, len) = (
+            buf.iter().take_while(|b| b.is_ascii_whitespace()).count(),
+            buf.len(),
+        );
+        reader.consume(whitespace);
+        if whitespace < len || len == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Appends bytes from `reader` to `out` up to and including the next occurrence of `tag`,
+/// and returns the position after it, or `None` if the input ended first.
+/// `tag` must end in `>`.
+fn read_until_tag<R: BufRead, E: std::error::Error>(
+    reader: &mut R,
+    out: &mut Vec<u8>,
+    tag: &[u8],
+    mut position: usize,
+) -> Result<Option<usize>, Error<E>> {
+    loop {
+        let read = reader
+            .read_until(b'>', out)
+            .map_err(|e| Error::from_io("read", e, "-"))?;
+        position += read;
+        if read == 0 {
+            return Ok(None);
+        } else if out.ends_with(tag) {
+            return Ok(Some(position));
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub(crate) async fn read_page_xml_async<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    page_xml: &mut Vec<u8>,
+    position: &mut usize,
+) -> Result<Option<usize>, Error> {
+    let len = page_xml.len();
+    let start_tag_end = match read_until_tag_async(reader, page_xml, b"<page>", *position).await? {
+        Some(start_tag_end) => start_tag_end,
+        None => {
+            page_xml.truncate(len);
+            return Ok(None);
+        }
+    };
+    *position = read_until_tag_async(reader, page_xml, b"</page>", start_tag_end)
+        .await?
+        .ok_or(Error::UnexpectedEof {
+            position: *position + page_xml.len() - len,
+        })?;
+    Ok(Some(start_tag_end - b"<page>".len()))
+}
+
+#[cfg(feature = "async")]
+async fn read_until_tag_async<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    out: &mut Vec<u8>,
+    tag: &[u8],
+    mut position: usize,
+) -> Result<Option<usize>, Error> {
+    loop {
+        let read = reader
+            .read_until(b'>', out)
+            .await
+            .map_err(|e| Error::from_io("read", e, "-"))?;
+        position += read;
+        if read == 0 {
+            return Ok(None);
+        } else if out.ends_with(tag) {
+            return Ok(Some(position));
+        }
+    }
+}
+
+/// Makes the position in an error from the reader of a single page
+/// relative to the start of the input.
+pub(crate) fn with_offset<E: std::error::Error>(error: Error<E>, offset: usize) -> Error<E> {
+    match error {
+        Error::Format { position, context } => Error::Format {
+            position: position + offset,
+            context,
+        },
+        Error::Tag {
+            expected,
+            actual,
+            position,
+        } => Error::Tag {
+            expected,
+            actual,
+            position: position + offset,
+        },
+        Error::UnexpectedEof { position } => Error::UnexpectedEof {
+            position: position + offset,
+        },
+        Error::FailedToDecode { position } => Error::FailedToDecode {
+            position: position + offset,
+        },
+        Error::Value {
+            tag,
+            value,
+            position,
+        } => Error::Value {
+            tag,
+            value,
+            position: position + offset,
+        },
+        Error::Timestamp { value, position } => Error::Timestamp {
+            value,
+            position: position + offset,
+        },
+        e => e,
+    }
+}
+
+/// The XML of consecutive pages, wrapped in `<mediawiki>` so that it can be parsed on its own.
+#[cfg(any(feature = "parallel", feature = "async"))]
+pub(crate) struct Chunk {
+    xml: Vec<u8>,
+    /// The position in the input that corresponds to the start of `xml`,
+    /// once a page has been added.
+    start: Option<usize>,
+}
+
+#[cfg(any(feature = "parallel", feature = "async"))]
+impl Chunk {
+    pub(crate) fn new() -> Self {
+        Self {
+            xml: b"<mediawiki>".to_vec(),
+            start: None,
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn len(&self) -> usize {
+        self.xml.len()
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.start.is_none()
+    }
+
+    /// Adds the next page from `reader` and returns `true`,
+    /// or returns `false` if the input ended before the next page.
+    /// If there is an error, the chunk is left as it was.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn read_page<R: BufRead>(
+        &mut self,
+        reader: &mut R,
+        position: &mut usize,
+    ) -> Result<bool, Error> {
+        let len = self.xml.len();
+        match read_page_xml(reader, &mut self.xml, position) {
+            Ok(page_start) => Ok(self.add_page(page_start, *position)),
+            Err(e) => {
+                self.xml.truncate(len);
+                Err(e)
+            }
+        }
+    }
+
+    /// Like [`Chunk::read_page`], but reads from an async reader.
+    #[cfg(feature = "async")]
+    pub(crate) async fn read_page_async<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        position: &mut usize,
+    ) -> Result<bool, Error> {
+        let len = self.xml.len();
+        match read_page_xml_async(reader, &mut self.xml, position).await {
+            Ok(page_start) => Ok(self.add_page(page_start, *position)),
+            Err(e) => {
+                self.xml.truncate(len);
+                Err(e)
+            }
+        }
+    }
+
+    /// Drops the bytes before the first page, such as the header.
+    /// The bytes between later pages are kept so that positions in errors are right.
+    fn add_page(&mut self, page_start: Option<usize>, position: usize) -> bool {
+        let Some(page_start) = page_start else {
+            return false;
+        };
+        if self.start.is_none() {
+            let page_len = position - page_start;
+            self.xml
+                .drain(b"<mediawiki>".len()..self.xml.len() - page_len);
+            self.start = Some(page_start - b"<mediawiki>".len());
+        }
+        true
+    }
+
+    /// Parses the pages, skipping those rejected by `options`.
+    pub(crate) fn parse(&self, options: &ParseOptions) -> Result<Vec<Page>, Error> {
+        let mut reader = Reader::from_reader(self.xml.as_slice().chain(&b"\n</mediawiki>"[..]));
+        let mut buf = Vec::new();
+        let mut pages = Vec::new();
+        let mut read_pages = || -> Result<(), Error> {
+            // `<mediawiki>`
+            read_event(&mut reader, &mut buf)?;
+            while let Some(page) = read_page(&mut reader, &mut buf, options)? {
+                pages.push(page);
+            }
+            Ok(())
+        };
+        read_pages().map_err(|e| with_offset(e, self.start.unwrap_or(0)))?;
+        Ok(pages)
+    }
+}
@@ -4,7 +4,9 @@ use quick_xml::name::QName;
 
 use crate::Error;
 
+/// The name of an element in a MediaWiki XML dump.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Tag {
     Action,
     Base,
@@ -54,8 +56,70 @@ pub enum Tag {
     Username,
 }
 
+const ALL: &[Tag] = &[
+    Tag::Action,
+    Tag::Base,
+    Tag::Case,
+    Tag::Comment,
+    Tag::Contributor,
+    Tag::DbName,
+    Tag::DiscussionThreadingInfo,
+    Tag::Filename,
+    Tag::Format,
+    Tag::Generator,
+    Tag::Id,
+    Tag::Ip,
+    Tag::LogItem,
+    Tag::LogTitle,
+    Tag::MediaWiki,
+    Tag::Minor,
+    Tag::Model,
+    Tag::Namespace,
+    Tag::Namespaces,
+    Tag::Ns,
+    Tag::Origin,
+    Tag::Page,
+    Tag::Params,
+    Tag::ParentId,
+    Tag::Redirect,
+    Tag::Restrictions,
+    Tag::Revision,
+    Tag::Sha1,
+    Tag::SiteInfo,
+    Tag::SiteName,
+    Tag::Size,
+    Tag::Src,
+    Tag::Text,
+    Tag::ThreadAncestor,
+    Tag::ThreadAuthor,
+    Tag::ThreadEditStatus,
+    Tag::ThreadId,
+    Tag::ThreadPage,
+    Tag::ThreadParent,
+    Tag::ThreadSubject,
+    Tag::ThreadType,
+    Tag::Timestamp,
+    Tag::Title,
+    Tag::Type,
+    Tag::Upload,
+    Tag::Username,
+];
+
 impl Tag {
-    pub(crate) fn as_str(&self) -> &str {
+    /// All the tags that the parser knows, in alphabetical order (ignoring case).
+    pub fn all() -> &'static [Tag] {
+        ALL
+    }
+
+    /// Looks up the tag with the element name `name`, such as `b"revision"`,
+    /// ignoring trailing whitespace. Names are case-sensitive, as in XML.
+    /// An unknown name is returned as [`Error::UnexpectedTag`].
+    pub fn from_bytes(name: &[u8]) -> Result<Tag, Error> {
+        Tag::try_from(name)
+    }
+
+    /// The element name of the tag, as it appears in the XML.
+    pub fn as_str(&self) -> &'static str {
         use Tag::*;
         match self {
             Action => "action",
@@ -107,7 +171,7 @@ impl Tag {
         }
     }
 
-    pub(crate) fn as_q_name(&self) -> QName {
+    pub(crate) fn as_q_name(&self) -> QName<'_> {
         QName(self.as_str().as_bytes())
     }
 }
@@ -187,3 +251,14 @@ impl TryFrom<QName<'_>> for Tag {
         TryFrom::try_from(s.as_ref())
     }
 }
+
+#[test]
+fn test_tag_names() {
+    for &tag in Tag::all() {
+        assert_eq!(Tag::from_bytes(tag.as_str().as_bytes()).unwrap(), tag);
+    }
+    assert!(matches!(
+        Tag::from_bytes(b"Revision"),
+        Err(Error::UnexpectedTag(_))
+    ));
+}
@@ -0,0 +1,97 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::Revision;
+
+/// Remembers the text of the revisions of a page by SHA-1, so that a revision whose text
+/// is the same as that of an earlier revision, such as a revert, shares it instead of
+/// holding another copy. Only the `capacity` most recently used texts are remembered,
+/// so that a page whose revisions are not all kept does not keep all of their texts alive.
+pub(crate) struct TextCache {
+    capacity: usize,
+    texts: HashMap<String, (Arc<str>, u64)>,
+    /// Incremented for every revision, to find the least recently used text.
+    uses: u64,
+}
+
+impl TextCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            texts: HashMap::new(),
+            uses: 0,
+        }
+    }
+
+    /// Replaces the text of `revision` with the remembered text with the same SHA-1,
+    /// or remembers it if there is none. Revisions without a SHA-1 are left alone,
+    /// and the texts are compared so that a wrong `<sha1>` cannot change the text.
+    pub(crate) fn share(&mut self, revision: &mut Revision) {
+        let Some(sha1) = revision.sha1.as_ref().filter(|_| self.capacity != 0) else {
+            return;
+        };
+        self.uses += 1;
+        if let Some((text, last_used)) = self.texts.get_mut(sha1) {
+            if *text == revision.text {
+                revision.text = Arc::clone(text);
+                *last_used = self.uses;
+            }
+            return;
+        }
+        if self.texts.len() == self.capacity {
+            // A linear search is fast enough for the small capacities that are useful.
+            let least_recently_used = self
+                .texts
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(sha1, _)| sha1.clone());
+            if let Some(sha1) = least_recently_used {
+                self.texts.remove(&sha1);
+            }
+        }
+        self.texts
+            .insert(sha1.clone(), (Arc::clone(&revision.text), self.uses));
+    }
+}
+
+#[test]
+fn test_text_cache() {
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/pages.xml"));
+    let first = pages[0].revisions[0].clone();
+    let second = pages[0].revisions[1].clone();
+    // The same text in another allocation, as the parser would produce it.
+    let revert = |revision: &Revision| Revision {
+        text: revision.text.to_string().into(),
+        ..revision.clone()
+    };
+
+    let mut cache = TextCache::new(2);
+    let mut revisions = vec![first.clone(), second.clone(), revert(&first)];
+    for revision in &mut revisions {
+        cache.share(revision);
+    }
+    assert!(Arc::ptr_eq(&revisions[0].text, &revisions[2].text));
+    assert!(!Arc::ptr_eq(&revisions[0].text, &revisions[1].text));
+
+    // A revision whose text differs from the one with the same SHA-1 keeps its text.
+    let mut wrong_sha1 = Revision {
+        text: "something else".into(),
+        ..first.clone()
+    };
+    cache.share(&mut wrong_sha1);
+    assert_eq!(&*wrong_sha1.text, "something else");
+
+    // The least recently used text is forgotten.
+    let mut cache = TextCache::new(1);
+    let mut revisions = vec![first.clone(), second, revert(&first)];
+    for revision in &mut revisions {
+        cache.share(revision);
+    }
+    assert!(!Arc::ptr_eq(&revisions[0].text, &revisions[2].text));
+
+    let mut cache = TextCache::new(0);
+    let mut revisions = vec![first.clone(), revert(&first)];
+    for revision in &mut revisions {
+        cache.share(revision);
+    }
+    assert!(!Arc::ptr_eq(&revisions[0].text, &revisions[1].text));
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, convert::TryFrom, io::BufRead};
+
+use quick_xml::{events::Event, name::QName, Reader};
+use serde::{Deserialize, Serialize};
+
+use crate::{parse_text, read_event, read_text, tag::Tag, Error, Page};
+
+/// The contents of `<discussionthreadinginfo>`,
+/// which LiquidThreads adds to the revisions of its thread pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreadingInfo {
+    pub subject: String,
+    /// The thread that this thread replies to.
+    pub parent: Option<u32>,
+    /// The top-level thread of the discussion.
+    pub ancestor: Option<u32>,
+    /// The talk page that the discussion is on.
+    pub page: String,
+    pub id: u32,
+    pub author: String,
+    pub edit_status: String,
+    pub thread_type: String,
+}
+
+pub(crate) fn parse_threading_info<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<ThreadingInfo, Error<E>> {
+    let mut info = ThreadingInfo::default();
+    let mut id = None;
+    loop {
+        let name = match read_event(reader, buf)? {
+            Event::Text(_) => continue,
+            Event::Empty(_) => continue,
+            Event::End(end) if end.name() == Tag::DiscussionThreadingInfo.as_q_name() => break,
+            Event::Start(start) => start.name().as_ref().to_vec(),
+            _ => {
+                return Err(Error::format(
+                    reader,
+                    "expected an element in <DiscussionThreadingInfo>",
+                ))
+            }
+        };
+        match Tag::try_from(name.as_slice()) {
+            Ok(tag @ Tag::ThreadSubject) => info.subject = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::ThreadParent) => info.parent = Some(parse_text(reader, buf, tag)?),
+            Ok(tag @ Tag::ThreadAncestor) => info.ancestor = Some(parse_text(reader, buf, tag)?),
+            Ok(tag @ Tag::ThreadPage) => info.page = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::ThreadId) => id = Some(parse_text(reader, buf, tag)?),
+            Ok(tag @ Tag::ThreadAuthor) => info.author = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::ThreadEditStatus) => info.edit_status = read_text(reader, buf, tag)?,
+            Ok(tag @ Tag::ThreadType) => info.thread_type = read_text(reader, buf, tag)?,
+            // Other LiquidThreads elements, such as `<ThreadSignature>` and `<ThreadSummaryPage>`.
+            _ => {
+                reader.read_to_end_into(QName(&name), buf).map_err(|_| {
+                    Error::format(reader, "invalid element in <DiscussionThreadingInfo>")
+                })?;
+            }
+        }
+    }
+    info.id =
+        id.ok_or_else(|| Error::format(reader, "missing <ThreadID> in <DiscussionThreadingInfo>"))?;
+    Ok(info)
+}
+
+/// A thread in a [`ThreadTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadNode {
+    /// The id of the thread page.
+    pub page_id: u32,
+    pub info: ThreadingInfo,
+    /// The ids of the replies to this thread, in ascending order.
+    pub children: Vec<u32>,
+}
+
+/// LiquidThreads discussions reconstructed from the [`ThreadingInfo`] of thread pages.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadTree {
+    threads: HashMap<u32, ThreadNode>,
+    roots: Vec<u32>,
+}
+
+impl ThreadTree {
+    /// Links each thread to its parent using the threading info of the page's last revision.
+    /// Pages without threading info are ignored, and threads whose parent is not
+    /// among `pages` are treated as top-level threads.
+    pub fn from_pages<'a, I: IntoIterator<Item = &'a Page>>(pages: I) -> Self {
+        let mut threads: HashMap<u32, ThreadNode> = pages
+            .into_iter()
+            .filter_map(|page| {
+                let info = page
+                    .revisions
+                    .iter()
+                    .rev()
+                    .find_map(|revision| revision.threading_info.as_ref())?;
+                Some((
+                    info.id,
+                    ThreadNode {
+                        page_id: page.id,
+                        info: info.clone(),
+                        children: Vec::new(),
+                    },
+                ))
+            })
+            .collect();
+
+        let mut roots = Vec::new();
+        let links: Vec<_> = threads
+            .values()
+            .map(|node| (node.info.id, node.info.parent))
+            .collect();
+        for (id, parent) in links {
+            match parent.and_then(|parent| threads.get_mut(&parent)) {
+                Some(parent) if parent.info.id != id => parent.children.push(id),
+                _ => roots.push(id),
+            }
+        }
+        roots.sort_unstable();
+        for node in threads.values_mut() {
+            node.children.sort_unstable();
+        }
+        Self { threads, roots }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&ThreadNode> {
+        self.threads.get(&id)
+    }
+
+    /// The ids of the top-level threads, in ascending order.
+    pub fn roots(&self) -> &[u32] {
+        &self.roots
+    }
+
+    pub fn children(&self, id: u32) -> &[u32] {
+        self.get(id).map_or(&[], |node| &node.children)
+    }
+
+    pub fn parent(&self, id: u32) -> Option<u32> {
+        self.get(id)?
+            .info
+            .parent
+            .filter(|parent| self.threads.contains_key(parent))
+    }
+}
+
+#[test]
+fn test_thread_tree() {
+    let pages = crate::parse_to_vec(include_bytes!("../tests/fixtures/threads.xml"));
+    let tree = ThreadTree::from_pages(&pages);
+    assert_eq!(tree.roots(), [1]);
+    assert_eq!(tree.children(1), [2, 4]);
+    assert_eq!(tree.children(2), [3]);
+    assert_eq!(tree.children(3), [] as [u32; 0]);
+    assert_eq!(tree.parent(3), Some(2));
+    assert_eq!(tree.parent(1), None);
+    let reply = tree.get(3).unwrap();
+    assert_eq!(reply.page_id, 103);
+    assert_eq!(reply.info.ancestor, Some(1));
+    assert_eq!(reply.info.page, "Talk:Main Page");
+}
+
+#[test]
+fn test_threading_info_after_sha1() {
+    let xml = std::str::from_utf8(include_bytes!("../tests/fixtures/threads.xml")).unwrap();
+    let sha1 = "      <sha1>s2mgtp6qtgkrxxrldtbyrbx3k6mdqzx</sha1>\n";
+    let start = xml.find("      <discussionthreadinginfo>").unwrap();
+    let end = xml.find(sha1).unwrap();
+    let info = &xml[start..end].replace(
+        "<ThreadType>",
+        "<ThreadSignature>[[User:Alice|Alice]]</ThreadSignature>\n        <ThreadType>",
+    );
+    let moved = [&xml[..start], sha1, info, &xml[end + sha1.len()..]].concat();
+    assert_eq!(
+        crate::parse_to_vec(moved.as_bytes()),
+        crate::parse_to_vec(xml.as_bytes())
+    );
+}
@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, NaiveDateTime, TimeZone, Utc,
+};
+use serde::{Serialize, Serializer};
+
+/// How revision timestamps are written by the text-based output formats.
+/// Binary formats always use the native `chrono` representation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimestampFormat {
+    /// `2004-03-04T10:31:12Z`, the same as in the XML.
+    #[default]
+    Rfc3339,
+    /// Seconds since the Unix epoch.
+    Unix,
+    /// Milliseconds since the Unix epoch.
+    UnixMillis,
+    /// A `strftime`-style format string, as accepted by [`DateTime::format`].
+    Custom(String),
+}
+
+impl TimestampFormat {
+    /// Returns a value that serializes `timestamp` in this format:
+    /// as an integer for the Unix formats and as a string otherwise.
+    pub fn apply(&self, timestamp: DateTime<Utc>) -> FormattedTimestamp<'_> {
+        FormattedTimestamp {
+            timestamp,
+            format: self,
+        }
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(if s.eq_ignore_ascii_case("rfc3339") {
+            Self::Rfc3339
+        } else if s.eq_ignore_ascii_case("unix") {
+            Self::Unix
+        } else if s.eq_ignore_ascii_case("unix-ms") {
+            Self::UnixMillis
+        } else if StrftimeItems::new(s).any(|item| item == Item::Error) {
+            return Err("Invalid timestamp format");
+        } else {
+            Self::Custom(s.into())
+        })
+    }
+}
+
+pub struct FormattedTimestamp<'a> {
+    timestamp: DateTime<Utc>,
+    format: &'a TimestampFormat,
+}
+
+impl Serialize for FormattedTimestamp<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.format {
+            TimestampFormat::Rfc3339 => self.timestamp.serialize(serializer),
+            TimestampFormat::Unix => serializer.serialize_i64(self.timestamp.timestamp()),
+            TimestampFormat::UnixMillis => {
+                serializer.serialize_i64(self.timestamp.timestamp_millis())
+            }
+            TimestampFormat::Custom(format) => {
+                serializer.collect_str(&self.timestamp.format(format))
+            }
+        }
+    }
+}
+
+/// A `<timestamp>` as found in dumps: RFC 3339 (`2004-03-04T10:31:12Z`), or the 14-digit form
+/// that MediaWiki stores in its database (`20040304103112`), which some old or hand-made exports use.
+/// Both are in UTC.
+pub(crate) struct DumpTimestamp(pub(crate) DateTime<Utc>);
+
+impl FromStr for DumpTimestamp {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<DateTime<Utc>>()
+            .or_else(|e| {
+                if s.len() == 14 && s.bytes().all(|b| b.is_ascii_digit()) {
+                    NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S")
+                        .map(|naive| Utc.from_utc_datetime(&naive))
+                } else {
+                    Err(e)
+                }
+            })
+            .map(DumpTimestamp)
+    }
+}
+
+#[test]
+fn test_dump_timestamp() {
+    let expected: DateTime<Utc> = "2004-03-04T10:31:12Z".parse().unwrap();
+    let parse = |s: &str| s.parse::<DumpTimestamp>().map(|timestamp| timestamp.0);
+    assert_eq!(parse("2004-03-04T10:31:12Z"), Ok(expected));
+    assert_eq!(parse("2004-03-04T11:31:12+01:00"), Ok(expected));
+    assert_eq!(parse("20040304103112"), Ok(expected));
+    assert!(parse("20041304103112").is_err());
+    assert!(parse("2004030410311").is_err());
+    assert!(parse("1078396272").is_err());
+}
+
+#[test]
+fn test_timestamp_format() {
+    let timestamp: DateTime<Utc> = "2004-03-04T10:31:12Z".parse().unwrap();
+    let to_json = |format: &str| {
+        let format: TimestampFormat = format.parse().unwrap();
+        serde_json::to_string(&format.apply(timestamp)).unwrap()
+    };
+    assert_eq!(to_json("rfc3339"), r#""2004-03-04T10:31:12Z""#);
+    assert_eq!(to_json("unix"), "1078396272");
+    assert_eq!(to_json("unix-ms"), "1078396272000");
+    assert_eq!(to_json("%Y-%m-%d %H:%M"), r#""2004-03-04 10:31""#);
+    assert!("%Y-%!".parse::<TimestampFormat>().is_err());
+}
@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+/// Whether the first letter of titles is always uppercase on a wiki,
+/// from the `<case>` element of `<siteinfo>` (see [`SiteInfo::title_case`](crate::SiteInfo::title_case)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TitleCase {
+    /// `first-letter`, as on most wikis.
+    FirstLetter,
+    /// `case-sensitive`, as on Wiktionary.
+    CaseSensitive,
+}
+
+impl FromStr for TitleCase {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-letter" => Ok(TitleCase::FirstLetter),
+            "case-sensitive" => Ok(TitleCase::CaseSensitive),
+            _ => Err("expected first-letter or case-sensitive"),
+        }
+    }
+}
+
+/// Puts a title in the form used in dumps, as when it comes from a wikilink or URL:
+/// underscores are replaced with spaces, whitespace around the title and after the namespace prefix
+/// is removed, and with [`TitleCase::FirstLetter`], the first letter after the prefix is uppercased.
+/// The prefix is everything up to the first colon if `namespace` is not 0.
+pub fn normalize_title(title: &str, namespace: i32, case: TitleCase) -> String {
+    let title = title.replace('_', " ");
+    let (prefix, name) = match title.split_once(':') {
+        Some((prefix, name)) if namespace != 0 => (Some(prefix.trim()), name),
+        _ => (None, title.as_str()),
+    };
+    let name = name.trim();
+    let mut normalized = String::with_capacity(title.len());
+    if let Some(prefix) = prefix {
+        normalized.push_str(prefix);
+        normalized.push(':');
+    }
+    let mut chars = name.chars();
+    match (case, chars.next()) {
+        (TitleCase::FirstLetter, Some(first)) => {
+            normalized.extend(first.to_uppercase());
+            normalized.push_str(chars.as_str());
+        }
+        _ => normalized.push_str(name),
+    }
+    normalized
+}
+
+#[test]
+fn test_normalize_title() {
+    use TitleCase::*;
+    assert_eq!(
+        normalize_title(" rhythm_and_blues ", 0, CaseSensitive),
+        "rhythm and blues"
+    );
+    assert_eq!(
+        normalize_title("rhythm_and_blues", 0, FirstLetter),
+        "Rhythm and blues"
+    );
+    assert_eq!(
+        normalize_title("Template:_en-noun", 10, FirstLetter),
+        "Template:En-noun"
+    );
+    assert_eq!(
+        normalize_title("Template:en-noun", 10, CaseSensitive),
+        "Template:en-noun"
+    );
+    // Colons in the main namespace are part of the title.
+    assert_eq!(normalize_title("w:_ébauche", 0, FirstLetter), "W: ébauche");
+    assert_eq!(normalize_title("ébauche", 0, FirstLetter), "Ébauche");
+    assert_eq!(normalize_title("", 0, FirstLetter), "");
+}
@@ -0,0 +1,84 @@
+use std::{convert::TryFrom, io::BufRead};
+
+use chrono::{DateTime, Utc};
+use quick_xml::{events::Event, name::QName, Reader};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parse_text, parse_timestamp, read_contributor_contents, read_event, read_text, tag::Tag,
+    Comment, Contributor, Error,
+};
+
+/// A version of a file, from an `<upload>` element of a file page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Upload {
+    pub timestamp: DateTime<Utc>,
+    pub contributor: Contributor,
+    pub comment: Comment,
+    pub filename: String,
+    /// The URL of the file.
+    pub src: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+}
+
+/// Parses the elements of `<upload>` up to and including the end tag.
+/// Elements other than those in [`Upload`], such as `<sha1base36>` and `<contents>`, are skipped.
+pub(crate) fn parse_upload<R: BufRead, E: std::error::Error>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+) -> Result<Upload, Error<E>> {
+    let mut timestamp = None;
+    let mut contributor = None;
+    let mut comment = Comment::Absent;
+    let mut filename = None;
+    let mut src = None;
+    let mut size = None;
+    loop {
+        let (name, is_empty, deleted) = match read_event(reader, buf)? {
+            Event::Text(_) => continue,
+            Event::End(end) if end.name() == Tag::Upload.as_q_name() => break,
+            Event::Start(start) => (start.name().as_ref().to_vec(), false, false),
+            Event::Empty(start) => (
+                start.name().as_ref().to_vec(),
+                true,
+                start
+                    .try_get_attribute("deleted")
+                    .map_err(|_| Error::format(reader, "invalid deleted attribute"))?
+                    .is_some_and(|attr| attr.value.as_ref() == b"deleted"),
+            ),
+            _ => return Err(Error::format(reader, "expected an element in <upload>")),
+        };
+        match (Tag::try_from(name.as_slice()), is_empty) {
+            (Ok(Tag::Timestamp), false) => timestamp = Some(parse_timestamp(reader, buf)?),
+            (Ok(Tag::Contributor), true) if deleted => contributor = Some(Contributor::Deleted),
+            (Ok(Tag::Contributor), false) => {
+                contributor = Some(read_contributor_contents(reader, buf)?)
+            }
+            (Ok(Tag::Comment), true) if deleted => comment = Comment::Deleted,
+            (Ok(Tag::Comment), true) => comment = Comment::Visible(String::new()),
+            (Ok(tag @ Tag::Comment), false) => {
+                comment = Comment::Visible(read_text(reader, buf, tag)?)
+            }
+            (Ok(tag @ Tag::Filename), false) => filename = Some(read_text(reader, buf, tag)?),
+            (Ok(tag @ Tag::Src), false) => src = Some(read_text(reader, buf, tag)?),
+            (Ok(tag @ Tag::Size), false) => size = Some(parse_text(reader, buf, tag)?),
+            (_, true) => {}
+            (_, false) => {
+                reader
+                    .read_to_end_into(QName(&name), buf)
+                    .map_err(|_| Error::format(reader, "invalid element in <upload>"))?;
+            }
+        }
+    }
+
+    let missing = |context| Error::format(reader, context);
+    Ok(Upload {
+        timestamp: timestamp.ok_or_else(|| missing("missing <timestamp> in <upload>"))?,
+        contributor: contributor.ok_or_else(|| missing("missing <contributor> in <upload>"))?,
+        comment,
+        filename: filename.ok_or_else(|| missing("missing <filename> in <upload>"))?,
+        src: src.ok_or_else(|| missing("missing <src> in <upload>"))?,
+        size: size.ok_or_else(|| missing("missing <size> in <upload>"))?,
+    })
+}
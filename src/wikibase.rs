@@ -0,0 +1,82 @@
+use serde::{de::Error as _, Deserialize, Serialize};
+use serde_json::Value;
+
+/// Whether `model` is one of the content models of Wikibase entities, such as `wikibase-item`
+/// on Wikidata, whose text is the JSON of the entity.
+pub fn is_wikibase_model(model: &str) -> bool {
+    model.starts_with("wikibase-")
+}
+
+/// A summary of the JSON of a Wikibase entity, set in [`Revision::entity`](crate::Revision::entity)
+/// if [`ParseOptions::wikibase_entities`](crate::ParseOptions::wikibase_entities) is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntitySummary {
+    /// The ID of the entity, such as `Q42`.
+    pub id: Option<String>,
+    /// The `type` of the entity, such as `item` or `property`.
+    pub entity_type: Option<String>,
+    /// The number of languages with a label.
+    pub labels: usize,
+    /// The number of languages with a description.
+    pub descriptions: usize,
+    /// The number of statements, summed over all properties.
+    pub claims: usize,
+}
+
+impl EntitySummary {
+    /// Parses the JSON of an entity, which must be an object.
+    /// Missing `labels`, `descriptions` and `claims` are counted as empty,
+    /// as are the empty arrays that MediaWiki writes instead of empty objects.
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        let entity = match serde_json::from_str(text)? {
+            Value::Object(entity) => entity,
+            _ => return Err(serde_json::Error::custom("expected a JSON object")),
+        };
+        let string = |key| entity.get(key).and_then(Value::as_str).map(str::to_owned);
+        let len = |value: Option<&Value>| match value {
+            Some(Value::Object(map)) => map.len(),
+            Some(Value::Array(array)) => array.len(),
+            _ => 0,
+        };
+        let claims = match entity.get("claims") {
+            Some(Value::Object(claims)) => claims.values().map(|claims| len(Some(claims))).sum(),
+            _ => 0,
+        };
+        Ok(Self {
+            id: string("id"),
+            entity_type: string("type"),
+            labels: len(entity.get("labels")),
+            descriptions: len(entity.get("descriptions")),
+            claims,
+        })
+    }
+}
+
+#[test]
+fn test_entity_summary() {
+    let summary = EntitySummary::from_json(
+        r#"{"type":"item","id":"Q42",
+            "labels":{"en":{"language":"en","value":"Douglas Adams"},"fr":{"language":"fr","value":"Douglas Adams"}},
+            "descriptions":[],
+            "claims":{"P31":[{"id":"Q42$1"}],"P106":[{"id":"Q42$2"},{"id":"Q42$3"}]}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        summary,
+        EntitySummary {
+            id: Some("Q42".into()),
+            entity_type: Some("item".into()),
+            labels: 2,
+            descriptions: 0,
+            claims: 3,
+        }
+    );
+    assert_eq!(
+        EntitySummary::from_json("{}").unwrap(),
+        EntitySummary::default()
+    );
+    assert!(EntitySummary::from_json("[]").is_err());
+    assert!(EntitySummary::from_json(r#"{"id":"Q42""#).is_err());
+    assert!(is_wikibase_model("wikibase-item"));
+    assert!(!is_wikibase_model("wikitext"));
+}